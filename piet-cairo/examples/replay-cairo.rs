@@ -0,0 +1,48 @@
+//! Record a test picture into a display list, then play it back on Cairo.
+
+use std::fs::File;
+
+use cairo::prelude::SurfaceExt;
+use cairo::{Context, Format, ImageSurface};
+
+use piet::{replay, RecordingContext, RenderContext};
+use piet_cairo::CairoRenderContext;
+
+use piet_test::draw_test_picture;
+
+const TEXTURE_WIDTH: i32 = 400;
+const TEXTURE_HEIGHT: i32 = 200;
+
+const HIDPI: f64 = 2.0;
+
+fn main() {
+    let test_picture_number = std::env::args()
+        .skip(1)
+        .next()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut recording = RecordingContext::new();
+    draw_test_picture(&mut recording, test_picture_number).unwrap();
+    recording.finish().unwrap();
+    let list = recording.into_display_list();
+    println!(
+        "recorded {} commands, {} brushes, {} images",
+        list.commands.len(),
+        list.brushes.len(),
+        list.images.len()
+    );
+
+    let surface = ImageSurface::create(Format::ARgb32, TEXTURE_WIDTH, TEXTURE_HEIGHT)
+        .expect("Can't create surface");
+    let mut cr = Context::new(&surface);
+    cr.scale(HIDPI, HIDPI);
+    let mut piet_context = CairoRenderContext::new(&mut cr);
+    replay(&list, &mut piet_context).unwrap();
+    piet_context.finish().unwrap();
+    surface.flush();
+    let mut file = File::create("temp-replay.png").expect("Couldn't create 'file.png'");
+    surface
+        .write_to_png(&mut file)
+        .expect("Error writing image file");
+}
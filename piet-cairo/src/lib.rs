@@ -1,18 +1,22 @@
 //! The Cairo backend for the Piet 2D graphics abstraction.
 
+use std::f64::consts::PI;
 use std::fmt;
 
 use cairo::{
     BorrowError, Context, Filter, FontFace, FontOptions, FontSlant, FontWeight, Format,
-    ImageSurface, Matrix, Pattern, PatternTrait, ScaledFont, Status, SurfacePattern,
+    ImageSurface, Matrix, Mesh, MeshCorner, Operator, Pattern, PatternTrait, ScaledFont, Status,
+    SurfacePattern,
 };
 
 use piet::kurbo::{Affine, PathEl, Point, QuadBez, Rect, Shape, Vec2};
 
 use piet::{
-    new_error, Color, Error, ErrorKind, FillRule, Font, FontBuilder, Gradient, GradientStop,
-    ImageFormat, InterpolationMode, LineCap, LineJoin, RenderContext, RoundInto, StrokeStyle, Text,
-    TextLayout, TextLayoutBuilder,
+    blur_alpha, blur_extent, blurred_rect_mask, expand_gradient_stops, new_error, AlphaMask,
+    BlendMode, Color, ColorInterpolation, Error, ErrorKind, ExtendMode, FillRule, Font,
+    FontBuilder, Gradient, GradientStop, GradientUnits, ImageFormat, InterpolationMode, LineCap,
+    LineJoin, MaskMode, RenderContext, RoundInto, StrokeStyle, SweepGradient, Text, TextLayout,
+    TextLayoutBuilder,
 };
 
 pub struct CairoRenderContext<'a> {
@@ -20,6 +24,26 @@ pub struct CairoRenderContext<'a> {
     // concurrency problems.
     ctx: &'a mut Context,
     text: CairoText,
+    /// Our view of the saved states, mirroring the cairo gstate stack.
+    ///
+    /// Cairo tracks most of the state itself, but we need to know which saves
+    /// were layers in order to report unbalanced stacks, and masks are applied
+    /// when the state is restored. There is always at least one, until
+    /// finishing.
+    ctx_stack: Vec<CtxState>,
+    err: Result<(), Error>,
+}
+
+#[derive(Default)]
+struct CtxState {
+    /// The opacity, if this state was pushed by `push_layer`.
+    layer_opacity: Option<f64>,
+    /// Whether this state was pushed by `begin_mask`.
+    is_mask_content: bool,
+    /// The masks set in this state.
+    ///
+    /// Each mask has a corresponding group pushed on the cairo context.
+    masks: Vec<Pattern>,
 }
 
 impl<'a> CairoRenderContext<'a> {
@@ -32,14 +56,22 @@ impl<'a> CairoRenderContext<'a> {
         CairoRenderContext {
             ctx,
             text: CairoText,
+            ctx_stack: vec![CtxState::default()],
+            err: Ok(()),
         }
     }
 }
 
 pub enum Brush {
-    Solid(u32),
+    Solid(Color),
     Linear(cairo::LinearGradient),
     Radial(cairo::RadialGradient),
+    /// Cairo has no sweep gradients, so the mesh approximating one is built
+    /// when the brush is used.
+    Sweep(SweepGradient),
+    /// A gradient in bounding box units, resolved when the brush is used.
+    BoundingBox(Gradient),
+    Image(SurfacePattern),
 }
 
 /// Right now, we don't need any state, as the "toy text API" treats the
@@ -82,7 +114,7 @@ trait WrapError<T> {
 impl<T> WrapError<T> for Result<T, BorrowError> {
     fn wrap(self) -> Result<T, Error> {
         self.map_err(|e| {
-            let e: Box<dyn std::error::Error> = Box::new(e);
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(e);
             e.into()
         })
     }
@@ -91,7 +123,7 @@ impl<T> WrapError<T> for Result<T, BorrowError> {
 impl<T> WrapError<T> for Result<T, Status> {
     fn wrap(self) -> Result<T, Error> {
         self.map_err(|e| {
-            let e: Box<dyn std::error::Error> = Box::new(WrappedStatus(e));
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedStatus(e));
             e.into()
         })
     }
@@ -104,6 +136,39 @@ fn convert_fill_rule(fill_rule: piet::FillRule) -> cairo::FillRule {
     }
 }
 
+fn convert_blend_mode(mode: BlendMode) -> Operator {
+    match mode {
+        BlendMode::Clear => Operator::Clear,
+        BlendMode::Src => Operator::Source,
+        BlendMode::Dst => Operator::Dest,
+        BlendMode::SrcOver => Operator::Over,
+        BlendMode::DstOver => Operator::DestOver,
+        BlendMode::SrcIn => Operator::In,
+        BlendMode::DstIn => Operator::DestIn,
+        BlendMode::SrcOut => Operator::Out,
+        BlendMode::DstOut => Operator::DestOut,
+        BlendMode::SrcAtop => Operator::Atop,
+        BlendMode::DstAtop => Operator::DestAtop,
+        BlendMode::Xor => Operator::Xor,
+        BlendMode::Add => Operator::Add,
+        BlendMode::Multiply => Operator::Multiply,
+        BlendMode::Screen => Operator::Screen,
+        BlendMode::Overlay => Operator::Overlay,
+        BlendMode::Darken => Operator::Darken,
+        BlendMode::Lighten => Operator::Lighten,
+        BlendMode::ColorDodge => Operator::ColorDodge,
+        BlendMode::ColorBurn => Operator::ColorBurn,
+        BlendMode::HardLight => Operator::HardLight,
+        BlendMode::SoftLight => Operator::SoftLight,
+        BlendMode::Difference => Operator::Difference,
+        BlendMode::Exclusion => Operator::Exclusion,
+        BlendMode::Hue => Operator::HslHue,
+        BlendMode::Saturation => Operator::HslSaturation,
+        BlendMode::Color => Operator::HslColor,
+        BlendMode::Luminosity => Operator::HslLuminosity,
+    }
+}
+
 impl<'a> RenderContext for CairoRenderContext<'a> {
     /// Cairo mostly uses raw f64, so this is as convenient as anything.
     type Point = Vec2;
@@ -116,62 +181,204 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
     type Image = ImageSurface;
 
     fn status(&mut self) -> Result<(), Error> {
+        std::mem::replace(&mut self.err, Ok(()))?;
         let status = self.ctx.status();
         if status == Status::Success {
             Ok(())
         } else {
-            let e: Box<dyn std::error::Error> = Box::new(WrappedStatus(status));
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedStatus(status));
             Err(e.into())
         }
     }
 
     fn clear(&mut self, color: Color) {
-        let rgba = color.as_rgba32();
-        self.ctx.set_source_rgb(
-            byte_to_frac(rgba >> 24),
-            byte_to_frac(rgba >> 16),
-            byte_to_frac(rgba >> 8),
-        );
+        let (r, g, b, _) = color.as_rgba();
+        // Clearing replaces the pixels, whatever the current blend mode.
+        self.ctx.save();
+        self.ctx.set_operator(Operator::Source);
+        self.ctx.set_source_rgb(r, g, b);
         self.ctx.paint();
+        self.ctx.restore();
     }
 
     fn solid_brush(&mut self, color: Color) -> Brush {
-        Brush::Solid(color.as_rgba32())
+        Brush::Solid(color)
     }
 
     fn gradient(&mut self, gradient: Gradient) -> Result<Brush, Error> {
+        if gradient.stops().is_empty() {
+            return Err(new_error(ErrorKind::InvalidGradient));
+        }
+        if gradient.units() == GradientUnits::ObjectBoundingBox {
+            return Ok(Brush::BoundingBox(gradient));
+        }
         match gradient {
             Gradient::Linear(linear) => {
                 let (x0, y0) = (linear.start.x, linear.start.y);
                 let (x1, y1) = (linear.end.x, linear.end.y);
                 let mut lg = cairo::LinearGradient::new(x0, y0, x1, y1);
-                set_gradient_stops(&mut lg, &linear.stops);
+                set_gradient_stops(&mut lg, &linear.stops, linear.interpolation);
+                lg.set_extend(convert_extend(linear.extend));
+                if let Some(transform) = linear.transform {
+                    lg.set_matrix(affine_to_matrix(transform.inverse()));
+                }
                 Ok(Brush::Linear(lg))
             }
             Gradient::Radial(radial) => {
                 let (xc, yc) = (radial.center.x, radial.center.y);
                 let (xo, yo) = (radial.origin_offset.x, radial.origin_offset.y);
-                let r = radial.radius;
-                let mut rg = cairo::RadialGradient::new(xc + xo, yc + yo, 0.0, xc, yc, r);
-                set_gradient_stops(&mut rg, &radial.stops);
+                let (r0, r) = (radial.start_radius, radial.radius);
+                let mut rg = cairo::RadialGradient::new(xc + xo, yc + yo, r0, xc, yc, r);
+                set_gradient_stops(&mut rg, &radial.stops, radial.interpolation);
+                rg.set_extend(convert_extend(radial.extend));
+                if let Some(transform) = radial.transform {
+                    rg.set_matrix(affine_to_matrix(transform.inverse()));
+                }
                 Ok(Brush::Radial(rg))
             }
+            Gradient::Sweep(sweep) => Ok(Brush::Sweep(sweep)),
+        }
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &Self::Image,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<Brush, Error> {
+        // Cairo patterns have a single extend mode for both directions.
+        if extend_x != extend_y {
+            return Err(new_error(ErrorKind::NotSupported));
         }
+        let surface_pattern = SurfacePattern::create(image);
+        surface_pattern.set_extend(convert_extend(extend_x));
+        surface_pattern.set_filter(convert_filter(interp));
+        surface_pattern.set_matrix(affine_to_matrix(transform.inverse()));
+        Ok(Brush::Image(surface_pattern))
     }
 
     fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: FillRule) {
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            if let Some(brush) = self.resolve_gradient(gradient, shape.bounding_box()) {
+                self.fill(shape, &brush, fill_rule);
+            }
+            return;
+        }
         self.set_path(shape);
         self.set_brush(brush);
         self.ctx.set_fill_rule(convert_fill_rule(fill_rule));
         self.ctx.fill();
     }
 
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &Self::Brush) {
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            if let Some(brush) = self.resolve_gradient(gradient, rect) {
+                self.blurred_rect(rect, blur_radius, &brush);
+            }
+            return;
+        }
+        // Rasterize the mask at device resolution, in user space scaled by
+        // the current transform, so it stays sharp when zoomed in.
+        let scale = device_scale(&self.ctx);
+        if scale.x == 0.0 || scale.y == 0.0 {
+            return;
+        }
+        let scaled_rect = Rect::new(
+            rect.x0 * scale.x,
+            rect.y0 * scale.y,
+            rect.x1 * scale.x,
+            rect.y1 * scale.y,
+        );
+        let mask = blurred_rect_mask(scaled_rect, blur_radius * (scale.x * scale.y).sqrt());
+        match alpha_surface(&mask) {
+            Ok(surface) => {
+                self.set_brush(brush);
+                self.ctx.save();
+                self.ctx.scale(1.0 / scale.x, 1.0 / scale.y);
+                self.ctx
+                    .mask_surface(&surface, mask.x0 as f64, mask.y0 as f64);
+                self.ctx.restore();
+            }
+            Err(e) => self.err = Err(e),
+        }
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        let scale = device_scale(&self.ctx);
+        if scale.x == 0.0 || scale.y == 0.0 {
+            return;
+        }
+        match shadow_surface(shape, blur_radius, scale) {
+            Ok((surface, origin)) => {
+                self.set_brush(&Brush::Solid(color));
+                self.ctx.save();
+                self.ctx.translate(offset.x, offset.y);
+                self.ctx.scale(1.0 / scale.x, 1.0 / scale.y);
+                self.ctx.mask_surface(&surface, origin.x, origin.y);
+                self.ctx.restore();
+            }
+            Err(e) => self.err = Err(e),
+        }
+    }
+
     fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
         self.set_path(shape);
         self.ctx.set_fill_rule(convert_fill_rule(fill_rule));
         self.ctx.clip();
     }
 
+    fn mask(&mut self, mask: &Self::Image, rect: impl Into<Rect>, mode: MaskMode) {
+        let rect = rect.into();
+        let surface_pattern = match mode {
+            MaskMode::Alpha => SurfacePattern::create(mask),
+            MaskMode::Luminance => match luminance_mask(mask) {
+                Ok(surface) => SurfacePattern::create(&surface),
+                Err(e) => {
+                    self.err = Err(e);
+                    return;
+                }
+            },
+        };
+        // The pattern matrix maps user space to image space.
+        let sx = (mask.get_width() as f64) / rect.width();
+        let sy = (mask.get_height() as f64) / rect.height();
+        surface_pattern.set_matrix(Matrix {
+            xx: sx,
+            yx: 0.0,
+            xy: 0.0,
+            yy: sy,
+            x0: -rect.x0 * sx,
+            y0: -rect.y0 * sy,
+        });
+        self.push_mask(Pattern::SurfacePattern(surface_pattern));
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        self.ctx.push_group();
+        self.ctx_stack.push(CtxState {
+            is_mask_content: true,
+            ..Default::default()
+        });
+        self.status()
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        if !self.ctx_stack.last().unwrap().is_mask_content {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        let state = self.ctx_stack.pop().unwrap();
+        self.pop_masks(state.masks);
+        let content = self.ctx.pop_group();
+        let pattern = match mode {
+            MaskMode::Alpha => content,
+            MaskMode::Luminance => self.luminance_group_mask(&content)?,
+        };
+        self.push_mask(pattern);
+        self.status()
+    }
+
     fn stroke(
         &mut self,
         shape: impl Shape,
@@ -179,6 +386,12 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
         width: impl RoundInto<Self::Coord>,
         style: Option<&StrokeStyle>,
     ) {
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            if let Some(brush) = self.resolve_gradient(gradient, shape.bounding_box()) {
+                self.stroke(shape, &brush, width, style);
+            }
+            return;
+        }
         self.set_path(shape);
         self.set_stroke(width.round_into(), style);
         self.set_brush(brush);
@@ -195,24 +408,79 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
         pos: impl RoundInto<Self::Point>,
         brush: &Self::Brush,
     ) {
+        let pos: Vec2 = pos.round_into();
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            let extents = layout.font.extents();
+            let bbox = Rect::new(
+                pos.x,
+                pos.y - extents.ascent,
+                pos.x + layout.width(),
+                pos.y + extents.descent,
+            );
+            if let Some(brush) = self.resolve_gradient(gradient, bbox) {
+                self.draw_text(layout, pos, &brush);
+            }
+            return;
+        }
         self.ctx.set_scaled_font(&layout.font);
         self.set_brush(brush);
-        let pos = pos.round_into();
         self.ctx.move_to(pos.x, pos.y);
         self.ctx.show_text(&layout.text);
     }
 
     fn save(&mut self) -> Result<(), Error> {
         self.ctx.save();
+        self.ctx_stack.push(CtxState::default());
         self.status()
     }
 
     fn restore(&mut self) -> Result<(), Error> {
+        let state = self.ctx_stack.last().unwrap();
+        if self.ctx_stack.len() <= 1 || state.layer_opacity.is_some() || state.is_mask_content {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        let state = self.ctx_stack.pop().unwrap();
+        self.pop_masks(state.masks);
+        self.ctx.restore();
+        self.status()
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        // The outer save holds the layer clip, which then also applies when
+        // the group is composited.
+        self.ctx.save();
+        if let Some(shape) = clip {
+            self.set_path(shape);
+            self.ctx.set_fill_rule(cairo::FillRule::Winding);
+            self.ctx.clip();
+        }
+        self.ctx.push_group();
+        self.ctx_stack.push(CtxState {
+            layer_opacity: Some(opacity),
+            ..Default::default()
+        });
+        self.status()
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        let opacity = match self.ctx_stack.last().unwrap().layer_opacity {
+            Some(opacity) if self.ctx_stack.len() > 1 => opacity,
+            _ => return Err(new_error(ErrorKind::StackUnbalance)),
+        };
+        let state = self.ctx_stack.pop().unwrap();
+        self.pop_masks(state.masks);
+        self.ctx.pop_group_to_source();
+        self.ctx.paint_with_alpha(opacity);
         self.ctx.restore();
         self.status()
     }
 
     fn finish(&mut self) -> Result<(), Error> {
+        if self.ctx_stack.len() != 1 {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        let state = self.ctx_stack.pop().unwrap();
+        self.pop_masks(state.masks);
         self.status()
     }
 
@@ -220,6 +488,11 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
         self.ctx.transform(affine_to_matrix(transform));
     }
 
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        // The operator is part of the cairo gstate, so save/restore covers it.
+        self.ctx.set_operator(convert_blend_mode(mode));
+    }
+
     fn make_image(
         &mut self,
         width: usize,
@@ -228,15 +501,24 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
         format: ImageFormat,
     ) -> Result<Self::Image, Error> {
         let cairo_fmt = match format {
-            ImageFormat::Rgb => Format::Rgb24,
-            ImageFormat::RgbaSeparate | ImageFormat::RgbaPremul => Format::ARgb32,
+            ImageFormat::Rgb | ImageFormat::Gray8 => Format::Rgb24,
+            ImageFormat::RgbaSeparate
+            | ImageFormat::RgbaPremul
+            | ImageFormat::BgraPremul
+            | ImageFormat::Rgba16 => Format::ARgb32,
+            ImageFormat::Alpha8 => Format::A8,
             _ => return Err(new_error(ErrorKind::NotSupported)),
         };
+        format.check_buffer(width, height, buf)?;
         let mut image = ImageSurface::create(cairo_fmt, width as i32, height as i32).wrap()?;
         // Confident no borrow errors because we just created it.
         let bytes_per_pixel = format.bytes_per_pixel();
         let bytes_per_row = width * bytes_per_pixel;
         let stride = image.get_stride() as usize;
+        fn premul(x: u8, a: u8) -> u8 {
+            let y = (x as u16) * (a as u16);
+            ((y + (y >> 8) + 0x80) >> 8) as u8
+        }
         {
             let mut data = image.get_data().wrap()?;
             for y in 0..height {
@@ -253,7 +535,7 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
                     ImageFormat::RgbaPremul => {
                         // It's annoying that Cairo exposes only ARGB. Ah well. Let's
                         // hope that LLVM generates pretty good code for this.
-                        // TODO: consider adding BgraPremul format.
+                        // Use BgraPremul to skip the swizzle.
                         for x in 0..width {
                             data[dst_off + x * 4 + 0] = buf[src_off + x * 4 + 2];
                             data[dst_off + x * 4 + 1] = buf[src_off + x * 4 + 1];
@@ -262,10 +544,6 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
                         }
                     }
                     ImageFormat::RgbaSeparate => {
-                        fn premul(x: u8, a: u8) -> u8 {
-                            let y = (x as u16) * (a as u16);
-                            ((y + (y >> 8) + 0x80) >> 8) as u8
-                        }
                         for x in 0..width {
                             let a = buf[src_off + x * 4 + 3];
                             data[dst_off + x * 4 + 0] = premul(buf[src_off + x * 4 + 2], a);
@@ -274,6 +552,44 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
                             data[dst_off + x * 4 + 3] = a;
                         }
                     }
+                    ImageFormat::BgraPremul => {
+                        let src = &buf[src_off..src_off + bytes_per_row];
+                        let dst = &mut data[dst_off..dst_off + bytes_per_row];
+                        if cfg!(target_endian = "little") {
+                            // Cairo's native-endian ARGB words, so copy whole rows.
+                            dst.copy_from_slice(src);
+                        } else {
+                            for (d, s) in dst.chunks_mut(4).zip(src.chunks(4)) {
+                                let pixel = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+                                d.copy_from_slice(&pixel.to_ne_bytes());
+                            }
+                        }
+                    }
+                    // A8 pixels are single bytes, so rows only differ in padding
+                    // on any machine.
+                    ImageFormat::Alpha8 => {
+                        data[dst_off..dst_off + bytes_per_row]
+                            .copy_from_slice(&buf[src_off..src_off + bytes_per_row]);
+                    }
+                    ImageFormat::Gray8 => {
+                        for x in 0..width {
+                            let v = buf[src_off + x];
+                            data[dst_off + x * 4 + 0] = v;
+                            data[dst_off + x * 4 + 1] = v;
+                            data[dst_off + x * 4 + 2] = v;
+                        }
+                    }
+                    ImageFormat::Rgba16 => {
+                        for x in 0..width {
+                            let p = &buf[src_off + x * 8..src_off + x * 8 + 8];
+                            let c = |i: usize| narrow16(u16::from_ne_bytes([p[i], p[i + 1]]));
+                            let a = c(6);
+                            data[dst_off + x * 4 + 0] = premul(c(4), a);
+                            data[dst_off + x * 4 + 1] = premul(c(2), a);
+                            data[dst_off + x * 4 + 2] = premul(c(0), a);
+                            data[dst_off + x * 4 + 3] = a;
+                        }
+                    }
                     _ => return Err(new_error(ErrorKind::NotSupported)),
                 }
             }
@@ -289,11 +605,7 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
     ) {
         let _ = self.with_save(|rc| {
             let surface_pattern = SurfacePattern::create(image);
-            let filter = match interp {
-                InterpolationMode::NearestNeighbor => Filter::Nearest,
-                InterpolationMode::Bilinear => Filter::Bilinear,
-            };
-            surface_pattern.set_filter(filter);
+            surface_pattern.set_filter(convert_filter(interp));
             let rect = rect.into();
             rc.ctx.translate(rect.x0, rect.y0);
             rc.ctx.scale(
@@ -301,22 +613,160 @@ impl<'a> RenderContext for CairoRenderContext<'a> {
                 rect.height() / (image.get_height() as f64),
             );
             rc.ctx.set_source(&Pattern::SurfacePattern(surface_pattern));
-            rc.ctx.paint();
+            // Fill the image bounds rather than `paint`, so that unbounded
+            // operators such as `Src` don't affect the rest of the surface.
+            rc.ctx.rectangle(
+                0.0,
+                0.0,
+                image.get_width() as f64,
+                image.get_height() as f64,
+            );
+            rc.ctx.fill();
             Ok(())
         });
     }
 }
 
-fn set_gradient_stops(dst: &mut impl cairo::Gradient, src: &[GradientStop]) {
-    for stop in src {
-        let rgba = stop.color.as_rgba32();
-        dst.add_color_stop_rgba(
-            stop.pos as f64,
-            byte_to_frac(rgba >> 24),
-            byte_to_frac(rgba >> 16),
-            byte_to_frac(rgba >> 8),
-            byte_to_frac(rgba),
+fn convert_filter(interp: InterpolationMode) -> Filter {
+    match interp {
+        InterpolationMode::NearestNeighbor => Filter::Nearest,
+        InterpolationMode::Bilinear => Filter::Bilinear,
+    }
+}
+
+fn convert_extend(extend: ExtendMode) -> cairo::Extend {
+    match extend {
+        ExtendMode::Pad => cairo::Extend::Pad,
+        ExtendMode::Repeat => cairo::Extend::Repeat,
+        ExtendMode::Reflect => cairo::Extend::Reflect,
+    }
+}
+
+/// The number of patches in a full circle, when approximating sweep gradients.
+const SWEEP_SEGMENTS: usize = 64;
+
+/// Approximate a sweep gradient with a mesh pattern.
+///
+/// The mesh is a fan of patches around the center, large enough to cover the
+/// current clip, with extra patch boundaries at the stops to keep them sharp.
+/// It is built in gradient coordinates, with the pattern matrix mapping user
+/// space to them.
+fn sweep_mesh(ctx: &Context, sweep: &SweepGradient) -> Mesh {
+    let (cx, cy) = (sweep.center.x, sweep.center.y);
+    let inverse = sweep.transform.unwrap_or_default().inverse();
+    let (x0, y0, x1, y1) = ctx.clip_extents();
+    let mut radius = 1.0f64;
+    for &(x, y) in &[(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+        let p = inverse * Point::new(x, y);
+        radius = radius.max((p.x - cx).hypot(p.y - cy));
+    }
+
+    let mut angles = (0..=SWEEP_SEGMENTS)
+        .map(|i| i as f64 * (2.0 * PI / SWEEP_SEGMENTS as f64))
+        .collect::<Vec<_>>();
+    // The stops are sampled with separate alpha, like cairo gradients.
+    let stops = expand_gradient_stops(&sweep.stops, sweep.interpolation, NATIVE_INTERPOLATION);
+    let span = sweep.end_angle - sweep.start_angle;
+    for stop in &stops {
+        let angle = (sweep.start_angle + stop.pos as f64 * span) % (2.0 * PI);
+        angles.push(if angle < 0.0 { angle + 2.0 * PI } else { angle });
+    }
+    angles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let pos = |angle: f64| {
+        if span == 0.0 {
+            0.0
+        } else {
+            (angle - sweep.start_angle) / span
+        }
+    };
+    let mesh = Mesh::new();
+    for pair in angles.windows(2) {
+        let (a0, a1) = (pair[0], pair[1]);
+        if a1 - a0 < 1e-9 {
+            continue;
+        }
+        // Sample just inside the patch, so that hard stops stay hard.
+        let delta = (a1 - a0) * 1e-4;
+        let (r0, g0, b0, alpha0) = sample_stops(&stops, pos(a0 + delta), sweep.extend);
+        let (r1, g1, b1, alpha1) = sample_stops(&stops, pos(a1 - delta), sweep.extend);
+        // Control points of the cubic approximating the arc.
+        let k = 4.0 / 3.0 * ((a1 - a0) / 4.0).tan();
+        let (s0, c0) = a0.sin_cos();
+        let (s1, c1) = a1.sin_cos();
+        mesh.begin_patch();
+        mesh.move_to(cx, cy);
+        mesh.line_to(cx + radius * c0, cy + radius * s0);
+        mesh.curve_to(
+            cx + radius * (c0 - k * s0),
+            cy + radius * (s0 + k * c0),
+            cx + radius * (c1 + k * s1),
+            cy + radius * (s1 - k * c1),
+            cx + radius * c1,
+            cy + radius * s1,
         );
+        mesh.line_to(cx, cy);
+        mesh.set_corner_color_rgba(MeshCorner::MeshCorner0, r0, g0, b0, alpha0);
+        mesh.set_corner_color_rgba(MeshCorner::MeshCorner1, r0, g0, b0, alpha0);
+        mesh.set_corner_color_rgba(MeshCorner::MeshCorner2, r1, g1, b1, alpha1);
+        mesh.set_corner_color_rgba(MeshCorner::MeshCorner3, r1, g1, b1, alpha1);
+        mesh.end_patch();
+    }
+    mesh.set_matrix(affine_to_matrix(inverse));
+    mesh
+}
+
+/// The color of a gradient at a position, interpolated in unpremultiplied
+/// sRGB like cairo's own gradients.
+fn sample_stops(stops: &[GradientStop], pos: f64, extend: ExtendMode) -> (f64, f64, f64, f64) {
+    let pos = match extend {
+        ExtendMode::Pad => pos,
+        ExtendMode::Repeat => pos - pos.floor(),
+        ExtendMode::Reflect => {
+            let pos = pos - 2.0 * (pos / 2.0).floor();
+            if pos > 1.0 {
+                2.0 - pos
+            } else {
+                pos
+            }
+        }
+    };
+    let components = |stop: &GradientStop| stop.color.as_rgba();
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return (0.0, 0.0, 0.0, 0.0),
+    };
+    if pos <= first.pos as f64 {
+        return components(first);
+    }
+    for pair in stops.windows(2) {
+        let (p0, p1) = (pair[0].pos as f64, pair[1].pos as f64);
+        if pos < p1 {
+            let t = (pos - p0) / (p1 - p0);
+            let (r0, g0, b0, a0) = components(&pair[0]);
+            let (r1, g1, b1, a1) = components(&pair[1]);
+            return (
+                r0 + t * (r1 - r0),
+                g0 + t * (g1 - g0),
+                b0 + t * (b1 - b0),
+                a0 + t * (a1 - a0),
+            );
+        }
+    }
+    components(last)
+}
+
+/// Cairo interpolates gradients in sRGB with separate alpha.
+const NATIVE_INTERPOLATION: ColorInterpolation = ColorInterpolation::UnpremultipliedSrgb;
+
+fn set_gradient_stops(
+    dst: &mut impl cairo::Gradient,
+    src: &[GradientStop],
+    interpolation: ColorInterpolation,
+) {
+    for stop in &expand_gradient_stops(src, interpolation, NATIVE_INTERPOLATION) {
+        let (r, g, b, a) = stop.color.as_rgba();
+        dst.add_color_stop_rgba(stop.pos as f64, r, g, b, a);
     }
 }
 
@@ -371,24 +821,103 @@ fn convert_line_join(line_join: LineJoin) -> cairo::LineJoin {
 }
 
 impl<'a> CairoRenderContext<'a> {
+    /// Start masked drawing in the current state.
+    fn push_mask(&mut self, pattern: Pattern) {
+        self.ctx.push_group();
+        self.ctx_stack.last_mut().unwrap().masks.push(pattern);
+    }
+
+    /// Composite the groups of masked drawing, innermost first.
+    ///
+    /// Popping a group restores the state at the time it was pushed, so the
+    /// mask patterns are in the right user space.
+    fn pop_masks(&mut self, masks: Vec<Pattern>) {
+        for pattern in masks.iter().rev() {
+            self.ctx.pop_group_to_source();
+            self.ctx.mask(pattern);
+        }
+    }
+
+    /// Convert the content of a popped mask group to a luminance mask.
+    ///
+    /// The content is rendered to an image covering the clip extents in
+    /// device space, which is then converted in place.
+    fn luminance_group_mask(&mut self, content: &Pattern) -> Result<Pattern, Error> {
+        let (x0, y0, x1, y1) = self.ctx.clip_extents();
+        let mut bbox = Rect::new(
+            std::f64::INFINITY,
+            std::f64::INFINITY,
+            -std::f64::INFINITY,
+            -std::f64::INFINITY,
+        );
+        for &(x, y) in &[(x0, y0), (x1, y0), (x0, y1), (x1, y1)] {
+            let (dx, dy) = self.ctx.user_to_device(x, y);
+            bbox.x0 = bbox.x0.min(dx);
+            bbox.y0 = bbox.y0.min(dy);
+            bbox.x1 = bbox.x1.max(dx);
+            bbox.y1 = bbox.y1.max(dy);
+        }
+        let (dx0, dy0) = (bbox.x0.floor(), bbox.y0.floor());
+        let (dx1, dy1) = (bbox.x1, bbox.y1);
+        let width = (dx1.ceil() - dx0).max(1.0) as i32;
+        let height = (dy1.ceil() - dy0).max(1.0) as i32;
+
+        // Maps user space to the pixels of the mask image.
+        let mut matrix = self.ctx.get_matrix();
+        matrix.x0 -= dx0;
+        matrix.y0 -= dy0;
+
+        let mut surface = ImageSurface::create(Format::ARgb32, width, height).wrap()?;
+        {
+            let cr = Context::new(&surface);
+            cr.set_matrix(matrix);
+            cr.set_source(content);
+            cr.paint();
+        }
+        surface.flush();
+        luminance_to_alpha(&mut surface)?;
+        let surface_pattern = SurfacePattern::create(&surface);
+        surface_pattern.set_matrix(matrix);
+        Ok(Pattern::SurfacePattern(surface_pattern))
+    }
+
     /// Set the source pattern to the brush.
     ///
     /// Cairo is super stateful, and we're trying to have more retained stuff.
     /// This is part of the impedance matching.
     fn set_brush(&mut self, brush: &Brush) {
         match *brush {
-            Brush::Solid(rgba) => self.ctx.set_source_rgba(
-                byte_to_frac(rgba >> 24),
-                byte_to_frac(rgba >> 16),
-                byte_to_frac(rgba >> 8),
-                byte_to_frac(rgba),
-            ),
+            Brush::Solid(ref color) => {
+                let (r, g, b, a) = color.as_rgba();
+                self.ctx.set_source_rgba(r, g, b, a);
+            }
             Brush::Linear(ref linear) => self
                 .ctx
                 .set_source(&Pattern::LinearGradient(linear.clone())),
             Brush::Radial(ref radial) => self
                 .ctx
                 .set_source(&Pattern::RadialGradient(radial.clone())),
+            Brush::Sweep(ref sweep) => {
+                let mesh = sweep_mesh(self.ctx, sweep);
+                self.ctx.set_source(&Pattern::Mesh(mesh));
+            }
+            Brush::Image(ref image) => self.ctx.set_source(&Pattern::SurfacePattern(image.clone())),
+            // Resolved before getting here.
+            Brush::BoundingBox(_) => unreachable!(),
+        }
+    }
+
+    /// Resolve a gradient in bounding box units to a brush.
+    ///
+    /// Returns `None` if nothing should be drawn.
+    fn resolve_gradient(&mut self, gradient: &Gradient, bbox: Rect) -> Option<Brush> {
+        let gradient = gradient.resolve_units(bbox)?;
+        match self.gradient(gradient) {
+            Ok(brush) => Some(brush),
+            Err(e) => {
+                self.err = Err(e);
+                None
+            }
         }
     }
 
@@ -416,39 +945,150 @@ impl<'a> CairoRenderContext<'a> {
     }
 
     fn set_path(&mut self, shape: impl Shape) {
-        // This shouldn't be necessary, we always leave the context in no-path
-        // state. But just in case, and it should be harmless.
-        self.ctx.new_path();
-        let mut last = Point::ZERO;
-        for el in shape.to_bez_path(1e-3) {
-            match el {
-                PathEl::MoveTo(p) => {
-                    self.ctx.move_to(p.x, p.y);
-                    last = p;
-                }
-                PathEl::LineTo(p) => {
-                    self.ctx.line_to(p.x, p.y);
-                    last = p;
-                }
-                PathEl::QuadTo(p1, p2) => {
-                    let q = QuadBez::new(last, p1, p2);
-                    let c = q.raise();
-                    self.ctx
-                        .curve_to(c.p1.x, c.p1.y, c.p2.x, c.p2.y, p2.x, p2.y);
-                    last = p2;
-                }
-                PathEl::CurveTo(p1, p2, p3) => {
-                    self.ctx.curve_to(p1.x, p1.y, p2.x, p2.y, p3.x, p3.y);
-                    last = p3;
-                }
-                PathEl::ClosePath => self.ctx.close_path(),
+        set_path(self.ctx, shape);
+    }
+}
+
+fn set_path(ctx: &Context, shape: impl Shape) {
+    // This shouldn't be necessary, we always leave the context in no-path
+    // state. But just in case, and it should be harmless.
+    ctx.new_path();
+    let mut last = Point::ZERO;
+    for el in shape.to_bez_path(1e-3) {
+        match el {
+            PathEl::MoveTo(p) => {
+                ctx.move_to(p.x, p.y);
+                last = p;
+            }
+            PathEl::LineTo(p) => {
+                ctx.line_to(p.x, p.y);
+                last = p;
+            }
+            PathEl::QuadTo(p1, p2) => {
+                let q = QuadBez::new(last, p1, p2);
+                let c = q.raise();
+                ctx.curve_to(c.p1.x, c.p1.y, c.p2.x, c.p2.y, p2.x, p2.y);
+                last = p2;
             }
+            PathEl::CurveTo(p1, p2, p3) => {
+                ctx.curve_to(p1.x, p1.y, p2.x, p2.y, p3.x, p3.y);
+                last = p3;
+            }
+            PathEl::ClosePath => ctx.close_path(),
+        }
+    }
+}
+
+/// Copy an alpha mask into an image surface.
+fn alpha_surface(mask: &AlphaMask) -> Result<ImageSurface, Error> {
+    let mut surface =
+        ImageSurface::create(Format::A8, mask.width as i32, mask.height as i32).wrap()?;
+    {
+        let stride = surface.get_stride() as usize;
+        let mut data = surface.get_data().wrap()?;
+        for y in 0..mask.height {
+            let src = &mask.data[y * mask.width..(y + 1) * mask.width];
+            data[y * stride..y * stride + mask.width].copy_from_slice(src);
         }
     }
+    Ok(surface)
 }
 
-fn byte_to_frac(byte: u32) -> f64 {
-    ((byte & 255) as f64) * (1.0 / 255.0)
+/// The scale from user space to device space along each user space axis.
+fn device_scale(ctx: &Context) -> Vec2 {
+    let (xx, xy) = ctx.user_to_device_distance(1.0, 0.0);
+    let (yx, yy) = ctx.user_to_device_distance(0.0, 1.0);
+    Vec2::new(xx.hypot(xy), yx.hypot(yy))
+}
+
+/// Render the coverage of a shape into an alpha surface, and blur it.
+///
+/// The surface is in user space scaled by `scale`, which should be the
+/// `device_scale`, so that it has about one pixel per device pixel. Returns
+/// the surface and the position of its origin in that scaled space.
+fn shadow_surface(
+    shape: impl Shape,
+    blur_radius: f64,
+    scale: Vec2,
+) -> Result<(ImageSurface, Vec2), Error> {
+    let bbox = shape.bounding_box();
+    let bbox = Rect::new(
+        bbox.x0 * scale.x,
+        bbox.y0 * scale.y,
+        bbox.x1 * scale.x,
+        bbox.y1 * scale.y,
+    );
+    let blur_radius = blur_radius * (scale.x * scale.y).sqrt();
+    let extent = blur_extent(blur_radius);
+    let x0 = (bbox.x0 - extent).floor();
+    let y0 = (bbox.y0 - extent).floor();
+    let width = ((bbox.x1 + extent).ceil() - x0) as i32;
+    let height = ((bbox.y1 + extent).ceil() - y0) as i32;
+    let mut surface = ImageSurface::create(Format::A8, width, height).wrap()?;
+    {
+        let cr = Context::new(&surface);
+        cr.translate(-x0, -y0);
+        cr.scale(scale.x, scale.y);
+        set_path(&cr, shape);
+        cr.fill();
+    }
+    surface.flush();
+    {
+        let stride = surface.get_stride() as usize;
+        let mut data = surface.get_data().wrap()?;
+        blur_alpha(
+            &mut data,
+            width as usize,
+            height as usize,
+            stride,
+            blur_radius,
+        );
+    }
+    Ok((surface, Vec2::new(x0, y0)))
+}
+
+/// Convert a 16 bit channel to 8 bits, rounding to nearest.
+fn narrow16(x: u16) -> u8 {
+    ((x as u32 * 255 + 32767) / 65535) as u8
+}
+
+/// Make a copy of an image, with the luminance as alpha.
+fn luminance_mask(image: &ImageSurface) -> Result<ImageSurface, Error> {
+    // We can't borrow the data of an image we don't exclusively own, so
+    // render it into a fresh surface first.
+    let mut surface =
+        ImageSurface::create(Format::ARgb32, image.get_width(), image.get_height()).wrap()?;
+    {
+        let cr = Context::new(&surface);
+        cr.set_source_surface(image, 0.0, 0.0);
+        cr.paint();
+    }
+    surface.flush();
+    luminance_to_alpha(&mut surface)?;
+    Ok(surface)
+}
+
+/// Replace the alpha of a premultiplied ARGB image with its luminance.
+///
+/// The color channels are cleared, as only alpha matters for masking.
+fn luminance_to_alpha(surface: &mut ImageSurface) -> Result<(), Error> {
+    let width = surface.get_width() as usize;
+    let height = surface.get_height() as usize;
+    let stride = surface.get_stride() as usize;
+    let mut data = surface.get_data().wrap()?;
+    for y in 0..height {
+        for x in 0..width {
+            let off = y * stride + x * 4;
+            let pixel = &mut data[off..off + 4];
+            let w = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            let r = ((w >> 16) & 255) as f64;
+            let g = ((w >> 8) & 255) as f64;
+            let b = (w & 255) as f64;
+            let lum = (0.2125 * r + 0.7154 * g + 0.0721 * b).round().min(255.0) as u32;
+            pixel.copy_from_slice(&(lum << 24).to_ne_bytes());
+        }
+    }
+    Ok(())
 }
 
 /// Can't implement RoundFrom here because both types belong to other crates.
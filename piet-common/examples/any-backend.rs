@@ -0,0 +1,39 @@
+//! Render a test picture with a backend chosen at runtime.
+//!
+//! The backend is named by the `PIET_BACKEND` environment variable, for
+//! example `PIET_BACKEND=raqote cargo run --example any-backend --features raqote`.
+
+use piet::{ImageFormat, RenderContext};
+use piet_common::AnyDevice;
+
+use piet_test::draw_test_picture;
+
+const TEXTURE_WIDTH: usize = 400;
+const TEXTURE_HEIGHT: usize = 200;
+
+const HIDPI: f64 = 2.0;
+
+fn main() {
+    let test_picture_number = std::env::args()
+        .nth(1)
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let device = AnyDevice::new().unwrap();
+    let width = (TEXTURE_WIDTH as f64 * HIDPI) as usize;
+    let height = (TEXTURE_HEIGHT as f64 * HIDPI) as usize;
+    let mut bitmap = device.bitmap_target(width, height, HIDPI).unwrap();
+    let mut rc = bitmap.render_context();
+    draw_test_picture(&mut rc, test_picture_number).unwrap();
+    rc.finish().unwrap();
+    std::mem::drop(rc);
+    let raw_pixels = bitmap.into_raw_pixels(ImageFormat::RgbaSeparate).unwrap();
+    image::save_buffer(
+        format!("temp-{}.png", device.backend()),
+        &raw_pixels,
+        width as u32,
+        height as u32,
+        image::ColorType::RGBA(8),
+    )
+    .unwrap();
+}
@@ -0,0 +1,45 @@
+//! Draw through a `DynRenderContext`, as separately compiled code would.
+
+use piet::kurbo::{Circle, Line, Rect, Vec2};
+
+use piet::{Color, DynAdapter, DynRenderContext, Error, FillRule, ImageFormat};
+use piet_common::Device;
+
+/// Drawing code that doesn't know which backend it draws with.
+fn plugin(rc: &mut dyn DynRenderContext) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    let blue = rc.solid_brush(Color::rgb24(0x00_00_80));
+    let red = rc.solid_brush(Color::rgba32(0xff_00_00_80));
+    rc.stroke(&Line::new((10.0, 10.0), (100.0, 50.0)), &blue, 1.0, None);
+    rc.with_save(|rc| {
+        rc.clip(&Rect::new(20.0, 20.0, 120.0, 120.0), FillRule::NonZero);
+        rc.fill(&Circle::new((100.0, 100.0), 50.0), &red, FillRule::NonZero);
+        Ok(())
+    })?;
+    let font = rc.new_font("Helvetica", 12.0)?;
+    let layout = rc.new_text_layout(&font, "Hello piet!")?;
+    rc.draw_text(&layout, Vec2::new(190.0 - layout.width(), 180.0), &blue);
+    Ok(())
+}
+
+fn main() {
+    let device = Device::new().unwrap();
+    let width = 200;
+    let height = 200;
+    let mut bitmap = device.bitmap_target(width, height, 1.0).unwrap();
+    let mut rc = bitmap.render_context();
+    let mut adapter = DynAdapter::new(&mut rc);
+    plugin(&mut adapter).unwrap();
+    adapter.finish().unwrap();
+    std::mem::drop(adapter);
+    std::mem::drop(rc);
+    let raw_pixels = bitmap.into_raw_pixels(ImageFormat::RgbaSeparate).unwrap();
+    image::save_buffer(
+        "temp-dyn.png",
+        &raw_pixels,
+        width as u32,
+        height as u32,
+        image::ColorType::RGBA(8),
+    )
+    .unwrap();
+}
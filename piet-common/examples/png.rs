@@ -13,7 +13,7 @@ fn main() {
     let brush = rc.solid_brush(Color::rgb24(0x00_00_80));
     rc.stroke(Line::new((10.0, 10.0), (100.0, 50.0)), &brush, 1.0, None);
     rc.finish().unwrap();
-    let raw_pixels = bitmap.into_raw_pixels(ImageFormat::RgbaPremul).unwrap();
+    let raw_pixels = bitmap.into_raw_pixels(ImageFormat::RgbaSeparate).unwrap();
     image::save_buffer(
         "temp-image.png",
         &raw_pixels,
@@ -0,0 +1,710 @@
+//! Runtime selection between the backends that are compiled in.
+//!
+//! The types in this module wrap the corresponding types of each backend in
+//! an enum, and implement the piet traits by dispatching to the backend that
+//! was chosen when the [`AnyDevice`] was created.
+//!
+//! [`AnyDevice`]: enum.AnyDevice.html
+
+use std::convert::Infallible;
+use std::env;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+
+use piet::kurbo::{Affine, Rect, Shape, Vec2};
+use piet::{
+    new_error, BlendMode, Color, Error, ErrorKind, ExtendMode, FillRule, Font, FontBuilder,
+    Gradient, ImageFormat, InterpolationMode, MaskMode, RenderContext, RoundInto, StrokeStyle,
+    Text, TextLayout, TextLayoutBuilder,
+};
+
+/// The environment variable that [`AnyDevice::new`] reads the backend from.
+///
+/// [`AnyDevice::new`]: enum.AnyDevice.html#method.new
+pub const BACKEND_VAR: &str = "PIET_BACKEND";
+
+/// Match on a value of one of the enums in this module, binding `$x` to the
+/// backend's value.
+///
+/// With `$from => $to`, the result is wrapped in the same variant of `$to`.
+macro_rules! dispatch {
+    ($value:expr, $from:ident, $x:ident => $body:expr) => {
+        match $value {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            $from::Cairo($x) => $body,
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            $from::D2D($x) => $body,
+            #[cfg(feature = "raqote")]
+            $from::Raqote($x) => $body,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        }
+    };
+    ($value:expr, $from:ident => $to:ident, $x:ident => $body:expr) => {
+        match $value {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            $from::Cairo($x) => $to::Cairo($body),
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            $from::D2D($x) => $to::D2D($body),
+            #[cfg(feature = "raqote")]
+            $from::Raqote($x) => $to::Raqote($body),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        }
+    };
+}
+
+/// Like `dispatch!`, for two values that must come from the same backend.
+///
+/// If they don't, the result is `$fallback`.
+macro_rules! dispatch_pair {
+    (($a:expr, $b:expr), ($ea:ident, $eb:ident), ($x:ident, $y:ident) => $body:expr,
+     else $fallback:expr) => {
+        match ($a, $b) {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            ($ea::Cairo($x), $eb::Cairo($y)) => $body,
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            ($ea::D2D($x), $eb::D2D($y)) => $body,
+            #[cfg(feature = "raqote")]
+            ($ea::Raqote($x), $eb::Raqote($y)) => $body,
+            #[allow(unreachable_patterns)]
+            _ => $fallback,
+        }
+    };
+    (($a:expr, $b:expr), ($ea:ident, $eb:ident) => $to:ident, ($x:ident, $y:ident) => $body:expr,
+     else $fallback:expr) => {
+        match ($a, $b) {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            ($ea::Cairo($x), $eb::Cairo($y)) => $to::Cairo($body),
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            ($ea::D2D($x), $eb::D2D($y)) => $to::D2D($body),
+            #[cfg(feature = "raqote")]
+            ($ea::Raqote($x), $eb::Raqote($y)) => $to::Raqote($body),
+            #[allow(unreachable_patterns)]
+            _ => $fallback,
+        }
+    };
+}
+
+/// A piet backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Cairo,
+    D2D,
+    Raqote,
+}
+
+impl Backend {
+    /// The name of the backend, as used in [`BACKEND_VAR`].
+    ///
+    /// [`BACKEND_VAR`]: constant.BACKEND_VAR.html
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Cairo => "cairo",
+            Backend::D2D => "d2d",
+            Backend::Raqote => "raqote",
+        }
+    }
+
+    /// Look up a backend by name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name.to_ascii_lowercase().as_str() {
+            "cairo" => Some(Backend::Cairo),
+            "d2d" | "direct2d" => Some(Backend::D2D),
+            "raqote" => Some(Backend::Raqote),
+            _ => None,
+        }
+    }
+
+    /// Whether the backend is compiled in.
+    pub fn is_available(self) -> bool {
+        match self {
+            Backend::Cairo => cfg!(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            )),
+            Backend::D2D => cfg!(any(feature = "d2d", target_os = "windows")),
+            Backend::Raqote => cfg!(feature = "raqote"),
+        }
+    }
+
+    /// All the backends that are compiled in.
+    pub fn available() -> Vec<Backend> {
+        [Backend::Cairo, Backend::D2D, Backend::Raqote]
+            .iter()
+            .cloned()
+            .filter(|backend| backend.is_available())
+            .collect()
+    }
+}
+
+/// The default is the backend that [`Device`](struct.Device.html) uses.
+impl Default for Backend {
+    fn default() -> Backend {
+        if cfg!(feature = "raqote") {
+            Backend::Raqote
+        } else if cfg!(any(feature = "d2d", target_os = "windows")) {
+            Backend::D2D
+        } else {
+            Backend::Cairo
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A backend was asked for that isn't compiled in or doesn't exist.
+#[derive(Debug)]
+struct UnknownBackend(String);
+
+impl fmt::Display for UnknownBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Backend {:?} is not available", self.0)
+    }
+}
+
+impl std::error::Error for UnknownBackend {}
+
+fn unknown_backend(name: impl Into<String>) -> Error {
+    new_error(ErrorKind::BackendUnavailable(Box::new(UnknownBackend(
+        name.into(),
+    ))))
+}
+
+/// A device for any of the backends that are compiled in.
+pub enum AnyDevice {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::Device),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::Device),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::Device),
+}
+
+/// A bitmap target of an [`AnyDevice`](enum.AnyDevice.html).
+pub enum AnyBitmapTarget<'a> {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::BitmapTarget<'a>),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::BitmapTarget<'a>),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::BitmapTarget<'a>),
+}
+
+/// The `RenderContext` of an [`AnyBitmapTarget`](enum.AnyBitmapTarget.html).
+///
+/// Brushes, images and text resources of one backend can't be used with
+/// another. Doing so is an `InvalidInput` error, which is reported by
+/// [`status`](#method.status) for the methods that don't return a result.
+pub struct AnyRenderContext<'a> {
+    inner: ContextInner<'a>,
+    text: AnyText<'a>,
+    err: Result<(), Error>,
+}
+
+enum ContextInner<'a> {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::CairoRenderContext<'a>),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DRenderContext<'a>),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::RaqoteRenderContext<'a>),
+}
+
+/// The brush type of [`AnyRenderContext`](struct.AnyRenderContext.html).
+pub enum AnyBrush<'a> {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::Brush),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DBrush),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::Brush<'a>),
+    // Not every backend's type has a lifetime.
+    #[doc(hidden)]
+    _Lifetime(PhantomData<&'a ()>, Infallible),
+}
+
+/// The image type of [`AnyRenderContext`](struct.AnyRenderContext.html).
+pub enum AnyImage {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(cairo::ImageSurface),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(direct2d::image::Bitmap),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::InternalImage),
+}
+
+/// The text factory of [`AnyRenderContext`](struct.AnyRenderContext.html).
+pub enum AnyText<'a> {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::CairoText),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DText<'a>),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::RaqoteText),
+    #[doc(hidden)]
+    _Lifetime(PhantomData<&'a ()>, Infallible),
+}
+
+/// A font, created by [`AnyText`](enum.AnyText.html).
+pub enum AnyFont {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::CairoFont),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DFont),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::RaqoteFont),
+}
+
+/// A font builder, created by [`AnyText`](enum.AnyText.html).
+pub enum AnyFontBuilder<'a> {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::CairoFontBuilder),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DFontBuilder<'a>),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::RaqoteFontBuilder),
+    #[doc(hidden)]
+    _Lifetime(PhantomData<&'a ()>, Infallible),
+}
+
+/// A text layout, created by [`AnyText`](enum.AnyText.html).
+pub enum AnyTextLayout {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::CairoTextLayout),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DTextLayout),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::RaqoteTextLayout),
+}
+
+/// A text layout builder, created by [`AnyText`](enum.AnyText.html).
+pub enum AnyTextLayoutBuilder<'a> {
+    #[cfg(all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ))]
+    Cairo(crate::cairo_back::CairoTextLayoutBuilder),
+    #[cfg(any(feature = "d2d", target_os = "windows"))]
+    D2D(crate::direct2d_back::D2DTextLayoutBuilder<'a>),
+    #[cfg(feature = "raqote")]
+    Raqote(crate::raqote_back::RaqoteTextLayoutBuilder),
+    #[doc(hidden)]
+    _Lifetime(PhantomData<&'a ()>, Infallible),
+}
+
+impl AnyDevice {
+    /// Create a new device.
+    ///
+    /// The backend is the one named by the `PIET_BACKEND` environment
+    /// variable if it is set, and the default backend otherwise.
+    pub fn new() -> Result<AnyDevice, Error> {
+        match env::var_os(BACKEND_VAR) {
+            Some(name) => {
+                let name = name.to_string_lossy();
+                let backend = Backend::from_name(&name).ok_or_else(|| unknown_backend(name))?;
+                AnyDevice::with_backend(backend)
+            }
+            None => AnyDevice::with_backend(Backend::default()),
+        }
+    }
+
+    /// Create a new device for the given backend.
+    pub fn with_backend(backend: Backend) -> Result<AnyDevice, Error> {
+        match backend {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            Backend::Cairo => Ok(AnyDevice::Cairo(crate::cairo_back::Device::new()?)),
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            Backend::D2D => Ok(AnyDevice::D2D(crate::direct2d_back::Device::new()?)),
+            #[cfg(feature = "raqote")]
+            Backend::Raqote => Ok(AnyDevice::Raqote(crate::raqote_back::Device::new()?)),
+            #[allow(unreachable_patterns)]
+            _ => Err(unknown_backend(backend.name())),
+        }
+    }
+
+    /// The backend of this device.
+    pub fn backend(&self) -> Backend {
+        match self {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            AnyDevice::Cairo(_) => Backend::Cairo,
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            AnyDevice::D2D(_) => Backend::D2D,
+            #[cfg(feature = "raqote")]
+            AnyDevice::Raqote(_) => Backend::Raqote,
+        }
+    }
+
+    /// Create a new bitmap target.
+    pub fn bitmap_target(
+        &self,
+        width: usize,
+        height: usize,
+        pix_scale: f64,
+    ) -> Result<AnyBitmapTarget, Error> {
+        Ok(dispatch!(self, AnyDevice => AnyBitmapTarget, device => {
+            device.bitmap_target(width, height, pix_scale)?
+        }))
+    }
+}
+
+impl<'a> AnyBitmapTarget<'a> {
+    /// Get a piet `RenderContext` for the bitmap.
+    ///
+    /// Note: caller is responsible for calling `finish` on the render
+    /// context at the end of rendering.
+    pub fn render_context<'b>(&'b mut self) -> AnyRenderContext<'b> {
+        let (inner, text) = match self {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            AnyBitmapTarget::Cairo(target) => (
+                ContextInner::Cairo(target.render_context()),
+                AnyText::Cairo(crate::cairo_back::CairoText),
+            ),
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            AnyBitmapTarget::D2D(target) => {
+                let text = AnyText::D2D(target.text());
+                (ContextInner::D2D(target.render_context()), text)
+            }
+            #[cfg(feature = "raqote")]
+            AnyBitmapTarget::Raqote(target) => (
+                ContextInner::Raqote(target.render_context()),
+                AnyText::Raqote(crate::raqote_back::RaqoteText),
+            ),
+        };
+        AnyRenderContext {
+            inner,
+            text,
+            err: Ok(()),
+        }
+    }
+
+    /// Get raw pixels from the bitmap.
+    pub fn into_raw_pixels(self, fmt: ImageFormat) -> Result<Vec<u8>, Error> {
+        dispatch!(self, AnyBitmapTarget, target => target.into_raw_pixels(fmt))
+    }
+}
+
+impl<'a> AnyRenderContext<'a> {
+    /// The backend this context draws with.
+    pub fn backend(&self) -> Backend {
+        match self.inner {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            ContextInner::Cairo(_) => Backend::Cairo,
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            ContextInner::D2D(_) => Backend::D2D,
+            #[cfg(feature = "raqote")]
+            ContextInner::Raqote(_) => Backend::Raqote,
+        }
+    }
+
+    /// Record that a resource of another backend was used.
+    fn mismatch(&mut self) {
+        if self.err.is_ok() {
+            self.err = Err(new_error(ErrorKind::InvalidInput));
+        }
+    }
+}
+
+impl<'a> RenderContext for AnyRenderContext<'a> {
+    type Point = Vec2;
+    type Coord = f64;
+    type Brush = AnyBrush<'a>;
+
+    type Text = AnyText<'a>;
+    type TextLayout = AnyTextLayout;
+
+    type Image = AnyImage;
+
+    fn status(&mut self) -> Result<(), Error> {
+        let err = mem::replace(&mut self.err, Ok(()));
+        let status = dispatch!(&mut self.inner, ContextInner, rc => rc.status());
+        err.and(status)
+    }
+
+    fn solid_brush(&mut self, color: Color) -> AnyBrush<'a> {
+        dispatch!(&mut self.inner, ContextInner => AnyBrush, rc => rc.solid_brush(color))
+    }
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<AnyBrush<'a>, Error> {
+        Ok(dispatch!(&mut self.inner, ContextInner => AnyBrush, rc => rc.gradient(gradient)?))
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &AnyImage,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<AnyBrush<'a>, Error> {
+        Ok(dispatch_pair!(
+            (&mut self.inner, image), (ContextInner, AnyImage) => AnyBrush, (rc, image) => {
+                rc.image_brush(image, extend_x, extend_y, transform, interp)?
+            },
+            else return Err(new_error(ErrorKind::InvalidInput))
+        ))
+    }
+
+    fn clear(&mut self, color: Color) {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.clear(color))
+    }
+
+    fn stroke(
+        &mut self,
+        shape: impl Shape,
+        brush: &AnyBrush<'a>,
+        width: impl RoundInto<f64>,
+        style: Option<&StrokeStyle>,
+    ) {
+        let width: f64 = width.round_into();
+        dispatch_pair!(
+            (&mut self.inner, brush), (ContextInner, AnyBrush), (rc, brush) => {
+                rc.stroke(shape, brush, width, style)
+            },
+            else self.mismatch()
+        )
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &AnyBrush<'a>, fill_rule: FillRule) {
+        dispatch_pair!(
+            (&mut self.inner, brush), (ContextInner, AnyBrush), (rc, brush) => {
+                rc.fill(shape, brush, fill_rule)
+            },
+            else self.mismatch()
+        )
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &AnyBrush<'a>) {
+        dispatch_pair!(
+            (&mut self.inner, brush), (ContextInner, AnyBrush), (rc, brush) => {
+                rc.blurred_rect(rect, blur_radius, brush)
+            },
+            else self.mismatch()
+        )
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        dispatch!(&mut self.inner, ContextInner, rc => {
+            rc.draw_shadow(shape, offset, blur_radius, color)
+        })
+    }
+
+    fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.clip(shape, fill_rule))
+    }
+
+    fn mask(&mut self, mask: &AnyImage, rect: impl Into<Rect>, mode: MaskMode) {
+        let rect = rect.into();
+        dispatch_pair!(
+            (&mut self.inner, mask), (ContextInner, AnyImage), (rc, mask) => {
+                rc.mask(mask, rect, mode)
+            },
+            else self.mismatch()
+        )
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.begin_mask())
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.end_mask(mode))
+    }
+
+    fn text(&mut self) -> &mut AnyText<'a> {
+        &mut self.text
+    }
+
+    fn draw_text(
+        &mut self,
+        layout: &AnyTextLayout,
+        pos: impl RoundInto<Vec2>,
+        brush: &AnyBrush<'a>,
+    ) {
+        let pos: Vec2 = pos.round_into();
+        match (&mut self.inner, layout, brush) {
+            #[cfg(all(
+                feature = "cairo",
+                not(any(target_arch = "wasm32", target_os = "windows"))
+            ))]
+            (ContextInner::Cairo(rc), AnyTextLayout::Cairo(layout), AnyBrush::Cairo(brush)) => {
+                rc.draw_text(layout, pos, brush)
+            }
+            #[cfg(any(feature = "d2d", target_os = "windows"))]
+            (ContextInner::D2D(rc), AnyTextLayout::D2D(layout), AnyBrush::D2D(brush)) => {
+                rc.draw_text(layout, pos, brush)
+            }
+            #[cfg(feature = "raqote")]
+            (ContextInner::Raqote(rc), AnyTextLayout::Raqote(layout), AnyBrush::Raqote(brush)) => {
+                rc.draw_text(layout, pos, brush)
+            }
+            _ => self.mismatch(),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.save())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.restore())
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.push_layer(opacity, clip))
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.pop_layer())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let err = mem::replace(&mut self.err, Ok(()));
+        let status = dispatch!(&mut self.inner, ContextInner, rc => rc.finish());
+        err.and(status)
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.transform(transform))
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        dispatch!(&mut self.inner, ContextInner, rc => rc.set_blend_mode(mode))
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<AnyImage, Error> {
+        Ok(dispatch!(&mut self.inner, ContextInner => AnyImage, rc => {
+            rc.make_image(width, height, buf, format)?
+        }))
+    }
+
+    fn draw_image(&mut self, image: &AnyImage, rect: impl Into<Rect>, interp: InterpolationMode) {
+        let rect = rect.into();
+        dispatch_pair!(
+            (&mut self.inner, image), (ContextInner, AnyImage), (rc, image) => {
+                rc.draw_image(image, rect, interp)
+            },
+            else self.mismatch()
+        )
+    }
+}
+
+impl<'a> Text for AnyText<'a> {
+    type Coord = f64;
+    type FontBuilder = AnyFontBuilder<'a>;
+    type Font = AnyFont;
+    type TextLayoutBuilder = AnyTextLayoutBuilder<'a>;
+    type TextLayout = AnyTextLayout;
+
+    fn new_font_by_name(
+        &mut self,
+        name: &str,
+        size: impl RoundInto<f64>,
+    ) -> Result<AnyFontBuilder<'a>, Error> {
+        let size: f64 = size.round_into();
+        Ok(dispatch!(self, AnyText => AnyFontBuilder, text => {
+            text.new_font_by_name(name, size)?
+        }))
+    }
+
+    fn new_text_layout(
+        &mut self,
+        font: &AnyFont,
+        text: &str,
+    ) -> Result<AnyTextLayoutBuilder<'a>, Error> {
+        Ok(dispatch_pair!(
+            (self, font), (AnyText, AnyFont) => AnyTextLayoutBuilder, (factory, font) => {
+                factory.new_text_layout(font, text)?
+            },
+            else return Err(new_error(ErrorKind::InvalidInput))
+        ))
+    }
+}
+
+impl<'a> FontBuilder for AnyFontBuilder<'a> {
+    type Out = AnyFont;
+
+    fn build(self) -> Result<AnyFont, Error> {
+        Ok(dispatch!(self, AnyFontBuilder => AnyFont, builder => builder.build()?))
+    }
+}
+
+impl Font for AnyFont {}
+
+impl<'a> TextLayoutBuilder for AnyTextLayoutBuilder<'a> {
+    type Out = AnyTextLayout;
+
+    fn build(self) -> Result<AnyTextLayout, Error> {
+        Ok(dispatch!(self, AnyTextLayoutBuilder => AnyTextLayout, builder => builder.build()?))
+    }
+}
+
+impl TextLayout for AnyTextLayout {
+    type Coord = f64;
+
+    fn width(&self) -> f64 {
+        dispatch!(self, AnyTextLayout, layout => layout.width().into())
+    }
+}
@@ -1,11 +1,14 @@
 //! Support for piet Cairo back-end.
 
+use std::fmt;
 use std::marker::PhantomData;
+use std::path::Path;
 
 use cairo::prelude::SurfaceExt;
-use cairo::{Context, Format, ImageSurface};
+use cairo::{pdf, ps, Context, Format, ImageSurface, Status};
 
-use piet::{ErrorKind, ImageFormat};
+use piet::kurbo::Vec2;
+use piet::{DocumentTarget, ErrorKind, ImageFormat};
 
 pub use piet_cairo::*;
 
@@ -24,6 +27,47 @@ pub struct BitmapTarget<'a> {
     phantom: PhantomData<&'a ()>,
 }
 
+/// A struct that provides a `RenderContext` drawing a PDF document.
+///
+/// Draw a single page with [`render_context`](#method.render_context), or
+/// several through the `DocumentTarget` trait.
+pub struct PdfTarget<'a> {
+    surface: pdf::File,
+    cr: Context,
+    page_open: bool,
+    phantom: PhantomData<&'a ()>,
+}
+
+/// A struct that provides a `RenderContext` drawing a PostScript document.
+///
+/// Like [`PdfTarget`](struct.PdfTarget.html), this can draw several pages.
+pub struct PsTarget<'a> {
+    surface: ps::File,
+    cr: Context,
+    page_open: bool,
+    phantom: PhantomData<&'a ()>,
+}
+
+#[derive(Debug)]
+struct WrappedStatus(Status);
+
+impl fmt::Display for WrappedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cairo error: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for WrappedStatus {}
+
+fn check_status(status: Status) -> Result<(), piet::Error> {
+    if status == Status::Success {
+        Ok(())
+    } else {
+        let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedStatus(status));
+        Err(e.into())
+    }
+}
+
 impl Device {
     /// Create a new device.
     pub fn new() -> Result<Device, piet::Error> {
@@ -47,6 +91,53 @@ impl Device {
             phantom,
         })
     }
+
+    /// Create a new target writing a PDF file.
+    ///
+    /// The page size is in points, which are 1/72 inch. Initially, user space
+    /// units are points as well.
+    pub fn pdf_target(
+        &self,
+        path: impl AsRef<Path>,
+        width: f64,
+        height: f64,
+    ) -> Result<PdfTarget, piet::Error> {
+        let surface = pdf::File::new(width, height, path);
+        check_status(surface.status())?;
+        let cr = Context::new(&surface);
+        let phantom = Default::default();
+        Ok(PdfTarget {
+            surface,
+            cr,
+            page_open: false,
+            phantom,
+        })
+    }
+
+    /// Create a new target writing a PostScript file.
+    ///
+    /// The page size is in points, as for [`pdf_target`](#method.pdf_target).
+    /// If `eps` is set, the file is Encapsulated PostScript, which must only
+    /// have a single page.
+    pub fn ps_target(
+        &self,
+        path: impl AsRef<Path>,
+        width: f64,
+        height: f64,
+        eps: bool,
+    ) -> Result<PsTarget, piet::Error> {
+        let surface = ps::File::new(width, height, path);
+        check_status(surface.status())?;
+        surface.set_eps(eps);
+        let cr = Context::new(&surface);
+        let phantom = Default::default();
+        Ok(PsTarget {
+            surface,
+            cr,
+            page_open: false,
+            phantom,
+        })
+    }
 }
 
 impl<'a> BitmapTarget<'a> {
@@ -58,22 +149,35 @@ impl<'a> BitmapTarget<'a> {
         CairoRenderContext::new(&mut self.cr)
     }
 
-    /// Get raw RGBA pixels from the bitmap.
+    /// Get raw pixels from the bitmap, in the given format.
     pub fn into_raw_pixels(mut self, fmt: ImageFormat) -> Result<Vec<u8>, piet::Error> {
-        // TODO: convert other formats.
-        if fmt != ImageFormat::RgbaPremul {
-            return Err(piet::new_error(ErrorKind::NotSupported));
-        }
         std::mem::drop(self.cr);
         self.surface.flush();
         let stride = self.surface.get_stride() as usize;
         let width = self.surface.get_width() as usize;
         let height = self.surface.get_height() as usize;
-        let mut raw_data = vec![0; width * height * 4];
         let buf = self
             .surface
             .get_data()
-            .map_err(|e| Into::<Box<dyn std::error::Error>>::into(e))?;
+            .map_err(|e| Into::<Box<dyn std::error::Error + Send + Sync>>::into(e))?;
+        if fmt == ImageFormat::BgraPremul {
+            // Cairo stores native-endian ARGB words, which are BGRA bytes on
+            // little endian, so there only the row padding has to go.
+            let mut raw_data = Vec::with_capacity(width * height * 4);
+            for y in 0..height {
+                let row = &buf[y * stride..y * stride + width * 4];
+                if cfg!(target_endian = "little") {
+                    raw_data.extend_from_slice(row);
+                } else {
+                    for p in row.chunks(4) {
+                        let pixel = u32::from_ne_bytes([p[0], p[1], p[2], p[3]]);
+                        raw_data.extend_from_slice(&pixel.to_le_bytes());
+                    }
+                }
+            }
+            return Ok(raw_data);
+        }
+        let mut raw_data = vec![0; width * height * 4];
         for y in 0..height {
             let src_off = y * stride;
             let dst_off = y * width * 4;
@@ -84,6 +188,114 @@ impl<'a> BitmapTarget<'a> {
                 raw_data[dst_off + x * 4 + 3] = buf[src_off + x * 4 + 3];
             }
         }
-        Ok(raw_data)
+        crate::pixels::from_rgba_premul(raw_data, fmt)
+    }
+}
+
+impl<'a> PdfTarget<'a> {
+    /// Get a piet `RenderContext` for the document.
+    ///
+    /// Note: caller is responsible for calling `finish` on the render
+    /// context at the end of rendering.
+    pub fn render_context<'b>(&'b mut self) -> CairoRenderContext<'b> {
+        CairoRenderContext::new(&mut self.cr)
+    }
+
+    /// Finish the document and write the file.
+    pub fn finish(self) -> Result<(), piet::Error> {
+        check_status(self.cr.status())?;
+        std::mem::drop(self.cr);
+        self.surface.finish();
+        check_status(self.surface.status())
+    }
+}
+
+impl<'a> PsTarget<'a> {
+    /// Get a piet `RenderContext` for the document.
+    ///
+    /// Note: caller is responsible for calling `finish` on the render
+    /// context at the end of rendering.
+    pub fn render_context<'b>(&'b mut self) -> CairoRenderContext<'b> {
+        CairoRenderContext::new(&mut self.cr)
+    }
+
+    /// Finish the document and write the file.
+    pub fn finish(self) -> Result<(), piet::Error> {
+        check_status(self.cr.status())?;
+        std::mem::drop(self.cr);
+        self.surface.finish();
+        check_status(self.surface.status())
+    }
+}
+
+/// Begin a page on a cairo document surface.
+///
+/// The context state is saved, so that nothing carries over to the next page.
+fn begin_page(cr: &Context, page_open: &mut bool) -> Result<(), piet::Error> {
+    if *page_open {
+        return Err(piet::new_error(ErrorKind::StackUnbalance));
+    }
+    *page_open = true;
+    cr.save();
+    Ok(())
+}
+
+fn end_page(cr: &Context, page_open: &mut bool) -> Result<(), piet::Error> {
+    if !*page_open {
+        return Err(piet::new_error(ErrorKind::StackUnbalance));
+    }
+    *page_open = false;
+    cr.restore();
+    cr.show_page();
+    check_status(cr.status())
+}
+
+impl<'a> DocumentTarget for PdfTarget<'a> {
+    type Context<'b>
+        = CairoRenderContext<'b>
+    where
+        Self: 'b;
+
+    /// Begin a page, with the size in points.
+    fn begin_page(&mut self, size: Vec2) -> Result<CairoRenderContext<'_>, piet::Error> {
+        begin_page(&self.cr, &mut self.page_open)?;
+        self.surface.set_size(size.x, size.y);
+        Ok(CairoRenderContext::new(&mut self.cr))
+    }
+
+    fn end_page(&mut self) -> Result<(), piet::Error> {
+        end_page(&self.cr, &mut self.page_open)
+    }
+
+    fn finish(self) -> Result<(), piet::Error> {
+        if self.page_open {
+            return Err(piet::new_error(ErrorKind::StackUnbalance));
+        }
+        PdfTarget::finish(self)
+    }
+}
+
+impl<'a> DocumentTarget for PsTarget<'a> {
+    type Context<'b>
+        = CairoRenderContext<'b>
+    where
+        Self: 'b;
+
+    /// Begin a page, with the size in points.
+    fn begin_page(&mut self, size: Vec2) -> Result<CairoRenderContext<'_>, piet::Error> {
+        begin_page(&self.cr, &mut self.page_open)?;
+        self.surface.set_size(size.x, size.y);
+        Ok(CairoRenderContext::new(&mut self.cr))
+    }
+
+    fn end_page(&mut self) -> Result<(), piet::Error> {
+        end_page(&self.cr, &mut self.page_open)
+    }
+
+    fn finish(self) -> Result<(), piet::Error> {
+        if self.page_open {
+            return Err(piet::new_error(ErrorKind::StackUnbalance));
+        }
+        PsTarget::finish(self)
     }
 }
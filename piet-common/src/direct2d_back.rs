@@ -51,9 +51,14 @@ struct WrappedD3D11Error(direct3d11::Error);
 #[derive(Debug)]
 struct WrappedDxgiError(dxgi::Error);
 
+/// A failure to initialize one of the system libraries, as a message.
+#[derive(Debug)]
+struct WrappedInitError(String);
+
 impl std::error::Error for WrappedD2DTag {}
 impl std::error::Error for WrappedD3D11Error {}
 impl std::error::Error for WrappedDxgiError {}
+impl std::error::Error for WrappedInitError {}
 
 impl fmt::Display for WrappedD2DTag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -67,6 +72,12 @@ impl fmt::Display for WrappedD3D11Error {
     }
 }
 
+impl fmt::Display for WrappedInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Initialization error: {}", self.0)
+    }
+}
+
 impl fmt::Display for WrappedDxgiError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Dxgi error: {}", self.0)
@@ -76,7 +87,7 @@ impl fmt::Display for WrappedDxgiError {
 impl<T> WrapError<T> for Result<T, (direct2d::Error, Option<RenderTag>)> {
     fn wrap(self) -> Result<T, piet::Error> {
         self.map_err(|(e, t)| {
-            let e: Box<dyn std::error::Error> = Box::new(WrappedD2DTag(e, t));
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedD2DTag(e, t));
             e.into()
         })
     }
@@ -85,7 +96,7 @@ impl<T> WrapError<T> for Result<T, (direct2d::Error, Option<RenderTag>)> {
 impl<T> WrapError<T> for Result<T, direct3d11::Error> {
     fn wrap(self) -> Result<T, piet::Error> {
         self.map_err(|e| {
-            let e: Box<dyn std::error::Error> = Box::new(WrappedD3D11Error(e));
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedD3D11Error(e));
             e.into()
         })
     }
@@ -94,29 +105,35 @@ impl<T> WrapError<T> for Result<T, direct3d11::Error> {
 impl<T> WrapError<T> for Result<T, dxgi::Error> {
     fn wrap(self) -> Result<T, piet::Error> {
         self.map_err(|e| {
-            let e: Box<dyn std::error::Error> = Box::new(WrappedDxgiError(e));
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedDxgiError(e));
             e.into()
         })
     }
 }
 
+/// Report that a system library couldn't be initialized.
+fn unavailable(e: impl fmt::Debug) -> piet::Error {
+    let e = Box::new(WrappedInitError(format!("{:?}", e)));
+    piet::new_error(ErrorKind::BackendUnavailable(e))
+}
+
 impl Device {
     /// Create a new device.
     ///
     /// This creates new Direct2D and DirectWrite factories, a Direct3D
     /// device, and a Direct2D device.
     pub fn new() -> Result<Device, piet::Error> {
-        let d2d = direct2d::Factory::new().unwrap();
-        let dwrite = directwrite::Factory::new().unwrap();
+        let d2d = direct2d::Factory::new().map_err(unavailable)?;
+        let dwrite = directwrite::Factory::new().map_err(unavailable)?;
 
         // Initialize a D3D Device
         let (_, d3d, d3d_ctx) = direct3d11::Device::create()
             .with_flags(CreateDeviceFlags::BGRA_SUPPORT)
             .build()
-            .unwrap();
+            .map_err(unavailable)?;
 
         // Create the D2D Device and Context
-        let device = direct2d::Device::create(&d2d, &d3d.as_dxgi()).unwrap();
+        let device = direct2d::Device::create(&d2d, &d3d.as_dxgi()).map_err(unavailable)?;
 
         Ok(Device {
             d2d,
@@ -177,12 +194,13 @@ impl<'a> BitmapTarget<'a> {
         D2DRenderContext::new(self.d2d, self.dwrite, &mut self.context)
     }
 
-    /// Get raw RGBA pixels from the bitmap.
+    /// A text factory matching the render context.
+    pub(crate) fn text(&self) -> D2DText<'a> {
+        D2DText::new(self.dwrite)
+    }
+
+    /// Get raw pixels from the bitmap, in the given format.
     pub fn into_raw_pixels(mut self, fmt: ImageFormat) -> Result<Vec<u8>, piet::Error> {
-        // TODO: convert other formats.
-        if fmt != ImageFormat::RgbaPremul {
-            return Err(piet::new_error(ErrorKind::NotSupported));
-        }
         self.context.end_draw().wrap()?;
         let temp_texture = direct3d11::texture2d::Texture2D::create(self.d3d)
             .with_size(self.width as u32, self.height as u32)
@@ -209,6 +227,6 @@ impl<'a> BitmapTarget<'a> {
                 raw_pixels.extend_from_slice(&map.row(y)[..self.width * 4]);
             }
         }
-        Ok(raw_pixels)
+        crate::pixels::from_rgba_premul(raw_pixels, fmt)
     }
 }
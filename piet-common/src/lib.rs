@@ -9,6 +9,14 @@
 //! supporting multiple backends simultaneously) you should use crates such as
 //! [piet][] and [piet-cairo][] directly.
 //!
+//! On platforms other than Windows and the web, the Cairo backend is used by
+//! default. Enabling the `raqote` feature selects the pure-Rust raqote backend
+//! instead; with default features turned off, that avoids the system Cairo
+//! dependency entirely.
+//!
+//! To choose between the backends that are compiled in at runtime, use
+//! [`AnyDevice`](enum.AnyDevice.html) in place of `Device`.
+//!
 //! [piet]: https://crates.io/crates/piet
 //! [kurbo]: https://crates.io/crates/kurbo
 //! [piet-cairo]: https://crates.io/crates/piet-cairo
@@ -18,18 +26,46 @@ pub use piet::*;
 #[doc(hidden)]
 pub use piet::kurbo;
 
-#[cfg(any(
-    feature = "raq",
-    not(any(target_arch = "wasm32", target_os = "windows", feature = "direct2d"))
+// Every backend that is available is compiled, so that `AnyDevice` can
+// choose between them at runtime. One of them is selected below.
+
+#[cfg(all(
+    feature = "cairo",
+    not(any(target_arch = "wasm32", target_os = "windows"))
+))]
+mod cairo_back;
+
+#[cfg(any(feature = "d2d", target_os = "windows"))]
+mod direct2d_back;
+
+#[cfg(feature = "raqote")]
+mod raqote_back;
+
+#[cfg(feature = "raqote")]
+mod backend {
+    pub use crate::raqote_back::*;
+}
+
+#[cfg(all(
+    feature = "cairo",
+    not(any(
+        target_arch = "wasm32",
+        target_os = "windows",
+        feature = "d2d",
+        feature = "raqote",
+        feature = "web"
+    ))
 ))]
-#[path = "cairo_back.rs"]
-mod backend;
+mod backend {
+    pub use crate::cairo_back::*;
+}
 
-#[cfg(any(feature = "d2d", all(target_os = "windows", not(feature = "cairo"))))]
-#[path = "direct2d_back.rs"]
-mod backend;
+#[cfg(all(not(feature = "raqote"), any(feature = "d2d", target_os = "windows")))]
+mod backend {
+    pub use crate::direct2d_back::*;
+}
 
-#[cfg(any(feature = "web", target_arch = "wasm32"))]
+#[cfg(all(not(feature = "raqote"), any(feature = "web", target_arch = "wasm32")))]
 mod backend {
     pub use piet_web::*;
     pub type Piet<'a> = WebRenderContext<'a>;
@@ -37,3 +73,26 @@ mod backend {
 
 #[doc(hidden)]
 pub use backend::*;
+
+#[cfg(not(any(feature = "web", target_arch = "wasm32")))]
+mod any_back;
+
+#[cfg(not(any(feature = "web", target_arch = "wasm32")))]
+mod png_document;
+
+#[cfg(any(
+    all(
+        feature = "cairo",
+        not(any(target_arch = "wasm32", target_os = "windows"))
+    ),
+    feature = "d2d",
+    target_os = "windows",
+    feature = "raqote"
+))]
+mod pixels;
+
+#[cfg(not(any(feature = "web", target_arch = "wasm32")))]
+pub use crate::any_back::*;
+
+#[cfg(not(any(feature = "web", target_arch = "wasm32")))]
+pub use crate::png_document::*;
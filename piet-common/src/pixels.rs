@@ -0,0 +1,57 @@
+//! Conversion of the pixels read back from a bitmap target.
+
+use piet::{new_error, Error, ErrorKind, ImageFormat};
+
+/// Convert pixels in `RgbaPremul` format to `fmt`.
+///
+/// Converting to `Rgb` or `Gray8` drops the alpha channel of the
+/// premultiplied color, which is the same as compositing the pixels over
+/// black.
+pub(crate) fn from_rgba_premul(mut pixels: Vec<u8>, fmt: ImageFormat) -> Result<Vec<u8>, Error> {
+    match fmt {
+        ImageFormat::RgbaPremul => Ok(pixels),
+        ImageFormat::RgbaSeparate => {
+            unpremultiply(&mut pixels);
+            Ok(pixels)
+        }
+        ImageFormat::Rgb => Ok(pixels
+            .chunks(4)
+            .flat_map(|pixel| pixel[..3].iter().cloned())
+            .collect()),
+        ImageFormat::BgraPremul => {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+            Ok(pixels)
+        }
+        ImageFormat::Gray8 => Ok(pixels.chunks(4).map(luminance).collect()),
+        ImageFormat::Alpha8 => Ok(pixels.chunks(4).map(|pixel| pixel[3]).collect()),
+        ImageFormat::Rgba16 => {
+            unpremultiply(&mut pixels);
+            let mut output = Vec::with_capacity(pixels.len() * 2);
+            for &c in &pixels {
+                output.extend_from_slice(&(c as u16 * 257).to_ne_bytes());
+            }
+            Ok(output)
+        }
+        _ => Err(new_error(ErrorKind::NotSupported)),
+    }
+}
+
+/// Convert premultiplied RGBA pixels to separate alpha, in place.
+fn unpremultiply(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_mut(4) {
+        let a = pixel[3] as u32;
+        if a != 0 {
+            for c in &mut pixel[..3] {
+                *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+            }
+        }
+    }
+}
+
+/// The luminance of an RGB pixel, with the weights of the luminance masks.
+fn luminance(pixel: &[u8]) -> u8 {
+    let (r, g, b) = (pixel[0] as f64, pixel[1] as f64, pixel[2] as f64);
+    (0.2125 * r + 0.7154 * g + 0.0721 * b).round().min(255.0) as u8
+}
@@ -0,0 +1,89 @@
+//! Raster documents, drawn as a numbered sequence of PNG files.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use piet::kurbo::Vec2;
+use piet::{new_error, DocumentTarget, Error, ErrorKind, ImageFormat};
+
+use crate::{BitmapTarget, Device, Piet};
+
+/// A document drawn on bitmaps, one per page, which are saved as PNG files.
+///
+/// Page sizes are in user space units, which are scaled by `pix_scale` to
+/// get the size of the bitmap in pixels.
+pub struct PngDocument<'a> {
+    device: &'a Device,
+    prefix: PathBuf,
+    pix_scale: f64,
+    /// The number of pages begun so far.
+    pages: usize,
+    /// The bitmap of the current page, with its width and height.
+    page: Option<(BitmapTarget<'a>, usize, usize)>,
+}
+
+impl<'a> PngDocument<'a> {
+    /// Create a new document.
+    ///
+    /// Page `n`, counting from 1, is saved to `prefix` followed by `-n.png`.
+    pub fn new(device: &'a Device, prefix: impl Into<PathBuf>, pix_scale: f64) -> PngDocument<'a> {
+        PngDocument {
+            device,
+            prefix: prefix.into(),
+            pix_scale,
+            pages: 0,
+            page: None,
+        }
+    }
+
+    fn page_path(&self) -> PathBuf {
+        let mut path = OsString::from(self.prefix.as_os_str());
+        path.push(format!("-{}.png", self.pages));
+        path.into()
+    }
+}
+
+impl<'a> DocumentTarget for PngDocument<'a> {
+    type Context<'b>
+        = Piet<'b>
+    where
+        Self: 'b;
+
+    fn begin_page(&mut self, size: Vec2) -> Result<Piet<'_>, Error> {
+        if self.page.is_some() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        let width = (size.x * self.pix_scale).ceil() as usize;
+        let height = (size.y * self.pix_scale).ceil() as usize;
+        let bitmap = self.device.bitmap_target(width, height, self.pix_scale)?;
+        self.pages += 1;
+        let page = self.page.get_or_insert((bitmap, width, height));
+        Ok(page.0.render_context())
+    }
+
+    fn end_page(&mut self) -> Result<(), Error> {
+        let (bitmap, width, height) = self
+            .page
+            .take()
+            .ok_or_else(|| new_error(ErrorKind::StackUnbalance))?;
+        let pixels = bitmap.into_raw_pixels(ImageFormat::RgbaSeparate)?;
+        image::save_buffer(
+            self.page_path(),
+            &pixels,
+            width as u32,
+            height as u32,
+            image::ColorType::RGBA(8),
+        )
+        .map_err(|e| {
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(e);
+            e.into()
+        })
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.page.is_some() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        Ok(())
+    }
+}
@@ -1,78 +1,82 @@
-//! Support for piet raqote back-end
+//! Support for piet raqote back-end.
 
 use std::marker::PhantomData;
 
-use raqote::{DrawTarget};
+use piet_raqote::raqote::DrawTarget;
 
-use piet::{ErrorKind, ImageFormat};
+use piet::kurbo::Affine;
+use piet::{ImageFormat, RenderContext};
 
 pub use piet_raqote::*;
 
+/// The `RenderContext` for the raqote backend, which is selected.
 pub type Piet<'a> = RaqoteRenderContext<'a>;
 
+/// A struct that can be used to create bitmap render contexts.
+///
+/// In the case of raqote, being a software renderer, no state is needed.
 pub struct Device;
 
-//QUESTION do I need this phantom data thing?
+/// A struct provides a `RenderContext` and then can have its bitmap extracted.
 pub struct BitmapTarget<'a> {
-  dt: DrawTarget,
-  phantom: PhantomData<&'a ()>
+    dt: DrawTarget,
+    pix_scale: f64,
+    phantom: PhantomData<&'a ()>,
 }
 
 impl Device {
-  pub fn new() -> Result<Device, piet::Error> {
-    Ok(Device)
-  }
+    /// Create a new device.
+    pub fn new() -> Result<Device, piet::Error> {
+        Ok(Device)
+    }
 
-  //TODO support scale
-  pub fn bitmap_target(
-    &self,
-    width: usize,
-    height: usize,
-    _pix_scale: f64,
-  ) -> Result<BitmapTarget, piet::Error> {
-    let dt = DrawTarget::new(width as i32, height as i32);
-    let phantom = Default::default();
-    Ok(BitmapTarget {
-      dt, phantom
-    })
-  }
+    /// Create a new bitmap target.
+    pub fn bitmap_target(
+        &self,
+        width: usize,
+        height: usize,
+        pix_scale: f64,
+    ) -> Result<BitmapTarget, piet::Error> {
+        let dt = DrawTarget::new(width as i32, height as i32);
+        let phantom = Default::default();
+        Ok(BitmapTarget {
+            dt,
+            pix_scale,
+            phantom,
+        })
+    }
 }
 
 impl<'a> BitmapTarget<'a> {
-  pub fn render_context<'b>(&'b mut self) -> RaqoteRenderContext<'b> {
-    RaqoteRenderContext::new(&mut self.dt)
-  }
-
-  pub fn into_raw_pixels(mut self, fmt: ImageFormat) -> Result<Vec<u8>, piet::Error> {
-    //Only support RGBA currently
-    if fmt != ImageFormat::RgbaPremul {
-      return Err(piet::new_error(ErrorKind::NotSupported));
+    /// Get a piet `RenderContext` for the bitmap.
+    ///
+    /// Note: caller is responsible for calling `finish` on the render
+    /// context at the end of rendering.
+    pub fn render_context<'b>(&'b mut self) -> RaqoteRenderContext<'b> {
+        let mut rc = RaqoteRenderContext::new(&mut self.dt);
+        // The scale is part of the base state, so `restore` can't undo it.
+        rc.transform(Affine::scale(self.pix_scale));
+        rc
     }
 
-    let buf = self.dt.get_data();
-
-    //TODO if we know the width * height we can size this vec upfront
-    let mut output = Vec::new();
-
-    for pixel in buf {
-            let a = (pixel >> 24) & 0xffu32;
-            let mut r = (pixel >> 16) & 0xffu32;
-            let mut g = (pixel >> 8) & 0xffu32;
-            let mut b = (pixel >> 0) & 0xffu32;
-
-            if a > 0u32 {
-                r = r * 255u32 / a;
-                g = g * 255u32 / a;
-                b = b * 255u32 / a;
+    /// Get raw pixels from the bitmap, in the given format.
+    pub fn into_raw_pixels(self, fmt: ImageFormat) -> Result<Vec<u8>, piet::Error> {
+        // Raqote stores premultiplied ARGB in native-endian words.
+        let buf = self.dt.get_data();
+        let mut output = Vec::with_capacity(buf.len() * 4);
+        if fmt == ImageFormat::BgraPremul {
+            // The little endian bytes of the words, so no conversion needed.
+            for pixel in buf {
+                output.extend_from_slice(&pixel.to_le_bytes());
             }
-
-            output.push(r as u8);
-            output.push(g as u8);
-            output.push(b as u8);
-            output.push(a as u8);
+            return Ok(output);
         }
-    
-    Ok(output)
-
-  }
+        for pixel in buf {
+            output.push((pixel >> 16) as u8);
+            output.push((pixel >> 8) as u8);
+            output.push(*pixel as u8);
+            output.push((pixel >> 24) as u8);
+        }
+        crate::pixels::from_rgba_premul(output, fmt)
+    }
 }
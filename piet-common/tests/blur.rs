@@ -0,0 +1,78 @@
+//! Check that the analytic and general blur paths agree.
+//!
+//! The same rect is drawn with `blurred_rect` and with `draw_shadow`, and the
+//! pixels must match to within rounding.
+
+#![cfg(not(any(feature = "web", target_arch = "wasm32")))]
+
+use piet::kurbo::{Rect, Vec2};
+use piet::{Color, ImageFormat, RenderContext};
+use piet_common::{AnyDevice, AnyRenderContext, Backend};
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 100;
+
+/// The largest difference allowed in any channel.
+const TOLERANCE: i32 = 3;
+
+/// Render on a fresh bitmap and read the bitmap back.
+fn render(backend: Backend, pix_scale: f64, f: impl FnOnce(&mut AnyRenderContext)) -> Vec<u8> {
+    let device = AnyDevice::with_backend(backend).unwrap();
+    let mut bitmap = device.bitmap_target(WIDTH, HEIGHT, pix_scale).unwrap();
+    let mut rc = bitmap.render_context();
+    f(&mut rc);
+    rc.finish().unwrap();
+    std::mem::drop(rc);
+    bitmap.into_raw_pixels(ImageFormat::RgbaPremul).unwrap()
+}
+
+/// Compare the two paths for a rect, on every backend with both of them.
+fn check(rect: Rect, blur_radius: f64, pix_scale: f64) {
+    for backend in Backend::available() {
+        if backend == Backend::D2D {
+            continue;
+        }
+        let analytic = render(backend, pix_scale, |rc| {
+            let brush = rc.solid_brush(Color::BLACK);
+            rc.blurred_rect(rect, blur_radius, &brush);
+        });
+        let general = render(backend, pix_scale, |rc| {
+            rc.draw_shadow(rect, Vec2::ZERO, blur_radius, Color::BLACK);
+        });
+        let diff = analytic
+            .iter()
+            .zip(&general)
+            .map(|(&a, &g)| (a as i32 - g as i32).abs())
+            .max()
+            .unwrap_or(0);
+        assert!(
+            diff <= TOLERANCE,
+            "{} {:?} blurred by {} at scale {}: max difference {}",
+            backend,
+            rect,
+            blur_radius,
+            pix_scale,
+            diff
+        );
+    }
+}
+
+#[test]
+fn wide_blur() {
+    check(Rect::new(20.0, 20.0, 80.0, 60.0), 5.0, 1.0);
+}
+
+#[test]
+fn narrow_blur() {
+    check(Rect::new(10.0, 10.0, 30.0, 40.0), 1.0, 1.0);
+}
+
+#[test]
+fn subpixel_blur() {
+    check(Rect::new(60.0, 10.0, 150.0, 30.0), 0.5, 1.0);
+}
+
+#[test]
+fn scaled_blur() {
+    check(Rect::new(10.0, 10.0, 50.0, 30.0), 3.0, 2.0);
+}
@@ -0,0 +1,159 @@
+//! Check that the Cairo document targets produce well-formed files.
+
+#![cfg(all(
+    feature = "cairo",
+    not(any(
+        target_arch = "wasm32",
+        target_os = "windows",
+        feature = "d2d",
+        feature = "raqote",
+        feature = "web"
+    ))
+))]
+
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+use flate2::read::ZlibDecoder;
+
+use piet::kurbo::Vec2;
+use piet::{DocumentTarget, RenderContext};
+use piet_common::{Device, PngDocument};
+use piet_test::draw_test_picture;
+
+/// US Letter, in points.
+const WIDTH: f64 = 612.0;
+const HEIGHT: f64 = 792.0;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("piet-common-{}-{}", std::process::id(), name))
+}
+
+fn count(haystack: &[u8], needle: &[u8]) -> usize {
+    haystack
+        .windows(needle.len())
+        .filter(|window| *window == needle)
+        .count()
+}
+
+/// The bytes of a PDF file, with the content of compressed streams appended.
+///
+/// Newer versions of cairo put the page objects in compressed object streams.
+fn pdf_with_streams(bytes: &[u8]) -> Vec<u8> {
+    let mut result = bytes.to_vec();
+    let mut rest = bytes;
+    while let Some(start) = find(rest, b"stream\n") {
+        rest = &rest[start + b"stream\n".len()..];
+        let end = find(rest, b"endstream").unwrap_or(rest.len());
+        let (data, next) = rest.split_at(end);
+        let mut decoded = Vec::new();
+        if ZlibDecoder::new(data).read_to_end(&mut decoded).is_ok() {
+            result.extend(decoded);
+        }
+        rest = &next[b"endstream".len().min(next.len())..];
+    }
+    result
+}
+
+/// The number of pages in a PDF file, checked against the page tree.
+fn pdf_page_count(bytes: &[u8]) -> usize {
+    // Page objects are "/Type /Page", the page tree is "/Type /Pages".
+    let objects = pdf_with_streams(bytes);
+    let pages = count(&objects, b"/Type /Page") - count(&objects, b"/Type /Pages");
+    let tree_count = format!("/Count {}", pages);
+    assert_eq!(count(&objects, tree_count.as_bytes()), 1);
+    pages
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[test]
+fn pdf_header_and_page_count() {
+    let path = temp_path("test.pdf");
+    let device = Device::new().unwrap();
+    let mut target = device.pdf_target(&path, WIDTH, HEIGHT).unwrap();
+    {
+        let mut rc = target.render_context();
+        draw_test_picture(&mut rc, 0).unwrap();
+        rc.finish().unwrap();
+    }
+    target.finish().unwrap();
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(bytes.starts_with(b"%PDF-1."), "missing PDF header");
+    let trailer = &bytes[bytes.len().saturating_sub(32)..];
+    assert_eq!(count(trailer, b"%%EOF"), 1, "missing end of file marker");
+    assert_eq!(pdf_page_count(&bytes), 1);
+}
+
+#[test]
+fn pdf_multiple_pages() {
+    let path = temp_path("pages.pdf");
+    let device = Device::new().unwrap();
+    let mut target = device.pdf_target(&path, WIDTH, HEIGHT).unwrap();
+    for number in 0..3 {
+        let mut rc = target.begin_page(Vec2::new(WIDTH, HEIGHT)).unwrap();
+        draw_test_picture(&mut rc, number).unwrap();
+        rc.finish().unwrap();
+        drop(rc);
+        target.end_page().unwrap();
+    }
+    DocumentTarget::finish(target).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(bytes.starts_with(b"%PDF-1."), "missing PDF header");
+    assert_eq!(pdf_page_count(&bytes), 3);
+}
+
+#[test]
+fn png_document_pages() {
+    let prefix = temp_path("pages");
+    let device = Device::new().unwrap();
+    let mut document = PngDocument::new(&device, &prefix, 2.0);
+    for number in 0..2 {
+        let mut rc = document.begin_page(Vec2::new(200.0, 100.0)).unwrap();
+        draw_test_picture(&mut rc, number).unwrap();
+        rc.finish().unwrap();
+        drop(rc);
+        document.end_page().unwrap();
+    }
+    document.finish().unwrap();
+    for n in 1..=2 {
+        let path = temp_path(&format!("pages-{}.png", n));
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+            "missing PNG signature"
+        );
+    }
+}
+
+#[test]
+fn eps_header_and_page_count() {
+    let path = temp_path("test.eps");
+    let device = Device::new().unwrap();
+    let mut target = device.ps_target(&path, WIDTH, HEIGHT, true).unwrap();
+    {
+        let mut rc = target.render_context();
+        draw_test_picture(&mut rc, 0).unwrap();
+        rc.finish().unwrap();
+    }
+    target.finish().unwrap();
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(
+        bytes.starts_with(b"%!PS-Adobe-3.0 EPSF-3.0"),
+        "missing EPS header"
+    );
+    assert_eq!(count(&bytes, b"%%Pages: 1"), 1);
+    assert_eq!(count(&bytes, b"%%EOF"), 1);
+}
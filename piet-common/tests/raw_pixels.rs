@@ -0,0 +1,167 @@
+//! Check that images drawn at 1:1 read back unchanged, in every format.
+
+#![cfg(not(any(feature = "web", target_arch = "wasm32")))]
+
+use piet::kurbo::Rect;
+use piet::{ImageFormat, InterpolationMode, RenderContext};
+use piet_common::{AnyDevice, Backend};
+
+const WIDTH: usize = 16;
+const HEIGHT: usize = 16;
+
+/// The pixels of the test image, with separate alpha.
+fn test_pixels() -> Vec<[u8; 4]> {
+    let mut pixels = Vec::new();
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let a = [255, 192, 128, 64, 0][(x + y) % 5];
+            pixels.push([(x * 17) as u8, (y * 17) as u8, (255 - x * 17) as u8, a]);
+        }
+    }
+    pixels
+}
+
+fn premul(x: u8, a: u8) -> u8 {
+    let y = (x as u16) * (a as u16);
+    ((y + (y >> 8) + 0x80) >> 8) as u8
+}
+
+/// Encode the test image in the given format.
+fn encode(format: ImageFormat) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for [r, g, b, a] in test_pixels() {
+        match format {
+            ImageFormat::Rgb => buf.extend_from_slice(&[r, g, b]),
+            ImageFormat::RgbaSeparate => buf.extend_from_slice(&[r, g, b, a]),
+            ImageFormat::RgbaPremul => {
+                buf.extend_from_slice(&[premul(r, a), premul(g, a), premul(b, a), a])
+            }
+            ImageFormat::BgraPremul => {
+                buf.extend_from_slice(&[premul(b, a), premul(g, a), premul(r, a), a])
+            }
+            ImageFormat::Gray8 => buf.push(r),
+            ImageFormat::Alpha8 => buf.push(a),
+            ImageFormat::Rgba16 => {
+                for &c in &[r, g, b, a] {
+                    buf.extend_from_slice(&(c as u16 * 257).to_ne_bytes());
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    buf
+}
+
+/// Draw the image on a fresh bitmap and read the bitmap back.
+fn round_trip(backend: Backend, buf: &[u8], format: ImageFormat) -> Vec<u8> {
+    let device = AnyDevice::with_backend(backend).unwrap();
+    let mut bitmap = device.bitmap_target(WIDTH, HEIGHT, 1.0).unwrap();
+    let mut rc = bitmap.render_context();
+    let image = rc.make_image(WIDTH, HEIGHT, buf, format).unwrap();
+    let rect = Rect::new(0.0, 0.0, WIDTH as f64, HEIGHT as f64);
+    rc.draw_image(&image, rect, InterpolationMode::NearestNeighbor);
+    rc.finish().unwrap();
+    std::mem::drop(rc);
+    bitmap.into_raw_pixels(format).unwrap()
+}
+
+/// Split an encoded image into pixels of 8 bit channels.
+fn channels(buf: &[u8], format: ImageFormat) -> Vec<Vec<i32>> {
+    let pixels = buf.chunks(format.bytes_per_pixel());
+    match format {
+        ImageFormat::Rgba16 => pixels
+            .map(|p| {
+                p.chunks(2)
+                    .map(|c| (u16::from_ne_bytes([c[0], c[1]]) as i32 + 128) / 257)
+                    .collect()
+            })
+            .collect(),
+        _ => pixels
+            .map(|p| p.iter().map(|&c| c as i32).collect())
+            .collect(),
+    }
+}
+
+/// Check a round trip in the given format on the given backends.
+///
+/// Bitmaps store premultiplied alpha, so with separate alpha the colors can
+/// only be recovered to within the rounding error scaled up by `255 / a`.
+fn check_on(format: ImageFormat, backends: &[Backend]) {
+    let expected = encode(format);
+    for &backend in backends {
+        let actual = round_trip(backend, &expected, format);
+        assert_eq!(actual.len(), expected.len(), "{} {:?}", backend, format);
+        let expected = channels(&expected, format);
+        let actual = channels(&actual, format);
+        for (i, (e, a)) in expected.iter().zip(&actual).enumerate() {
+            for c in 0..e.len() {
+                let tolerance = match format {
+                    ImageFormat::RgbaSeparate | ImageFormat::Rgba16 if c < 3 => match e[3] {
+                        0 => continue,
+                        alpha => 1 + 255 / alpha,
+                    },
+                    _ => 1,
+                };
+                let diff = (e[c] - a[c]).abs();
+                assert!(
+                    diff <= tolerance,
+                    "{} {:?}: pixel {} is {:?}, expected {:?}",
+                    backend,
+                    format,
+                    i,
+                    a,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Check a round trip in the given format on every backend.
+fn check(format: ImageFormat) {
+    check_on(format, &Backend::available());
+}
+
+/// Check a round trip on the backends that take every image format.
+fn check_software(format: ImageFormat) {
+    let backends: Vec<_> = Backend::available()
+        .into_iter()
+        .filter(|&backend| backend != Backend::D2D)
+        .collect();
+    check_on(format, &backends);
+}
+
+#[test]
+fn rgb_round_trip() {
+    check(ImageFormat::Rgb);
+}
+
+#[test]
+fn rgba_separate_round_trip() {
+    check(ImageFormat::RgbaSeparate);
+}
+
+#[test]
+fn rgba_premul_round_trip() {
+    check(ImageFormat::RgbaPremul);
+}
+
+#[test]
+fn bgra_premul_round_trip() {
+    check_software(ImageFormat::BgraPremul);
+}
+
+#[test]
+fn gray8_round_trip() {
+    check_software(ImageFormat::Gray8);
+}
+
+#[test]
+fn alpha8_round_trip() {
+    check_software(ImageFormat::Alpha8);
+}
+
+#[test]
+fn rgba16_round_trip() {
+    check_software(ImageFormat::Rgba16);
+}
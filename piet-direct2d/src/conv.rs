@@ -4,7 +4,9 @@ use direct2d::math::{ColorF, Matrix3x2F, Point2F, RectF};
 
 use piet::kurbo::{Affine, Point, Rect, Vec2};
 
-use piet::{Color, Error, GradientStop, LineCap, LineJoin, RoundFrom, RoundInto, StrokeStyle};
+use piet::{
+    Color, Error, ExtendMode, GradientStop, LineCap, LineJoin, RoundFrom, RoundInto, StrokeStyle,
+};
 
 use crate::error::WrapError;
 
@@ -91,8 +93,13 @@ pub(crate) fn rect_to_rectf(rect: Rect) -> RectF {
 }
 
 pub(crate) fn color_to_colorf(color: Color) -> ColorF {
-    let rgba = color.as_rgba32();
-    (rgba >> 8, ((rgba & 255) as f32) * (1.0 / 255.0)).into()
+    let (r, g, b, a) = color.as_rgba();
+    ColorF {
+        r: r as f32,
+        g: g as f32,
+        b: b as f32,
+        a: a as f32,
+    }
 }
 
 pub(crate) fn gradient_stop_to_d2d(stop: &GradientStop) -> direct2d::brush::gradient::GradientStop {
@@ -102,6 +109,14 @@ pub(crate) fn gradient_stop_to_d2d(stop: &GradientStop) -> direct2d::brush::grad
     }
 }
 
+pub(crate) fn convert_extend(extend: ExtendMode) -> direct2d::enums::ExtendMode {
+    match extend {
+        ExtendMode::Pad => direct2d::enums::ExtendMode::Clamp,
+        ExtendMode::Repeat => direct2d::enums::ExtendMode::Wrap,
+        ExtendMode::Reflect => direct2d::enums::ExtendMode::Mirror,
+    }
+}
+
 fn convert_line_cap(line_cap: LineCap) -> direct2d::enums::CapStyle {
     match line_cap {
         LineCap::Butt => direct2d::enums::CapStyle::Flat,
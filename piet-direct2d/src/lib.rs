@@ -4,8 +4,8 @@ mod conv;
 pub mod error;
 
 use crate::conv::{
-    affine_to_matrix3x2f, color_to_colorf, convert_stroke_style, gradient_stop_to_d2d,
-    rect_to_rectf, to_point2f, Point2,
+    affine_to_matrix3x2f, color_to_colorf, convert_extend, convert_stroke_style,
+    gradient_stop_to_d2d, rect_to_rectf, to_point2f, Point2,
 };
 use crate::error::WrapError;
 
@@ -33,13 +33,18 @@ use directwrite::text_format::TextFormatBuilder;
 use directwrite::text_layout;
 use directwrite::TextFormat;
 
-use piet::kurbo::{Affine, PathEl, Rect, Shape};
+use piet::kurbo::{Affine, PathEl, Rect, Shape, Vec2};
 
 use piet::{
-    new_error, Color, Error, ErrorKind, FillRule, Font, FontBuilder, Gradient, ImageFormat,
-    InterpolationMode, RenderContext, RoundInto, StrokeStyle, Text, TextLayout, TextLayoutBuilder,
+    expand_gradient_stops, new_error, BlendMode, Color, ColorInterpolation, Error, ErrorKind,
+    ExtendMode, FillRule, Font, FontBuilder, Gradient, GradientUnits, ImageFormat,
+    InterpolationMode, MaskMode, RenderContext, RoundInto, StrokeStyle, Text, TextLayout,
+    TextLayoutBuilder,
 };
 
+/// Direct2D interpolates gradients in sRGB with premultiplied alpha.
+const NATIVE_INTERPOLATION: ColorInterpolation = ColorInterpolation::PremultipliedSrgb;
+
 pub struct D2DRenderContext<'a> {
     factory: &'a direct2d::Factory,
     inner_text: D2DText<'a>,
@@ -53,6 +58,15 @@ pub struct D2DRenderContext<'a> {
     err: Result<(), Error>,
 }
 
+/// A brush, which is a Direct2D brush except for gradients in bounding box
+/// units.
+#[derive(Clone)]
+pub enum D2DBrush {
+    Native(GenericBrush),
+    /// A gradient in bounding box units, resolved when the brush is used.
+    BoundingBox(Gradient),
+}
+
 pub struct D2DText<'a> {
     dwrite: &'a directwrite::Factory,
 }
@@ -79,6 +93,16 @@ struct CtxState {
     // Note: when we start pushing both layers and axis aligned clips, this will
     // need to keep track of which is which. But for now, keep it simple.
     n_layers_pop: usize,
+
+    /// Whether this state was pushed by `push_layer`.
+    is_layer: bool,
+}
+
+impl<'a> D2DText<'a> {
+    /// Create a new text factory, using the given DirectWrite factory.
+    pub fn new(dwrite: &'a directwrite::Factory) -> D2DText<'a> {
+        D2DText { dwrite }
+    }
 }
 
 impl<'b, 'a: 'b> D2DRenderContext<'a> {
@@ -92,7 +116,7 @@ impl<'b, 'a: 'b> D2DRenderContext<'a> {
         dwrite: &'a directwrite::Factory,
         rt: &'b mut RT,
     ) -> D2DRenderContext<'b> {
-        let inner_text = D2DText { dwrite };
+        let inner_text = D2DText::new(dwrite);
         D2DRenderContext {
             factory,
             inner_text: inner_text,
@@ -107,6 +131,83 @@ impl<'b, 'a: 'b> D2DRenderContext<'a> {
         self.ctx_stack.last().unwrap().transform
     }
 
+    /// Create a Direct2D brush for a gradient in user space units.
+    fn native_gradient(&mut self, gradient: Gradient) -> Result<GenericBrush, Error> {
+        match gradient {
+            Gradient::Linear(linear) => {
+                let mut builder = LinearGradientBrushBuilder::new(&self.rt)
+                    .with_start(to_point2f(linear.start))
+                    .with_end(to_point2f(linear.end))
+                    .with_extend_mode(convert_extend(linear.extend));
+                let stops = expand_gradient_stops(
+                    &linear.stops,
+                    linear.interpolation,
+                    NATIVE_INTERPOLATION,
+                );
+                for stop in &stops {
+                    builder = builder.with_stop(gradient_stop_to_d2d(stop));
+                }
+                let mut brush = builder.build().wrap()?;
+                if let Some(transform) = linear.transform {
+                    brush.set_transform(&affine_to_matrix3x2f(transform));
+                }
+                // Same concern about extra COM clone as in `solid_brush`.
+                Ok(brush.to_generic())
+            }
+            Gradient::Radial(radial) => {
+                // Direct2D gradients always start from a point at the origin.
+                if radial.start_radius != 0.0 {
+                    return Err(new_error(ErrorKind::NotSupported));
+                }
+                let radius = radial.radius as f32;
+                let mut builder = RadialGradientBrushBuilder::new(&self.rt)
+                    .with_center(to_point2f(radial.center))
+                    .with_origin_offset(to_point2f(radial.origin_offset))
+                    .with_radius(radius, radius)
+                    .with_extend_mode(convert_extend(radial.extend));
+                let stops = expand_gradient_stops(
+                    &radial.stops,
+                    radial.interpolation,
+                    NATIVE_INTERPOLATION,
+                );
+                for stop in &stops {
+                    builder = builder.with_stop(gradient_stop_to_d2d(stop));
+                }
+                let mut brush = builder.build().wrap()?;
+                if let Some(transform) = radial.transform {
+                    brush.set_transform(&affine_to_matrix3x2f(transform));
+                }
+                // Ditto
+                Ok(brush.to_generic())
+            }
+            Gradient::Sweep(_) => Err(new_error(ErrorKind::NotSupported)),
+        }
+    }
+
+    /// The Direct2D brush for drawing something with the given bounding box,
+    /// resolving gradients in bounding box units.
+    ///
+    /// Returns `None` if nothing should be drawn.
+    fn native_brush<'c>(
+        &mut self,
+        brush: &'c D2DBrush,
+        bbox: Rect,
+    ) -> Option<Cow<'c, GenericBrush>> {
+        match *brush {
+            D2DBrush::Native(ref brush) => Some(Cow::Borrowed(brush)),
+            D2DBrush::BoundingBox(ref gradient) => {
+                let gradient = gradient.resolve_units(bbox)?;
+                match self.native_gradient(gradient) {
+                    Ok(brush) => Some(Cow::Owned(brush)),
+                    Err(e) => {
+                        self.err = Err(e);
+                        None
+                    }
+                }
+            }
+        }
+    }
+
     fn pop_state(&mut self) {
         // This is an unwrap because we protect the invariant.
         let old_state = self.ctx_stack.pop().unwrap();
@@ -198,7 +299,7 @@ fn path_from_shape(
 impl<'a> RenderContext for D2DRenderContext<'a> {
     type Point = Point2;
     type Coord = f32;
-    type Brush = GenericBrush;
+    type Brush = D2DBrush;
 
     type Text = D2DText<'a>;
 
@@ -214,48 +315,46 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
         self.rt.clear(color.as_rgba32() >> 8);
     }
 
-    fn solid_brush(&mut self, color: Color) -> GenericBrush {
-        SolidColorBrush::create(&self.rt)
+    fn solid_brush(&mut self, color: Color) -> D2DBrush {
+        let brush = SolidColorBrush::create(&self.rt)
             .with_color(color_to_colorf(color))
             .build()
             .wrap()
             .expect("error creating solid brush")
-            .to_generic() // This does an extra COM clone; avoid somehow?
+            .to_generic(); // This does an extra COM clone; avoid somehow?
+        D2DBrush::Native(brush)
     }
 
-    fn gradient(&mut self, gradient: Gradient) -> Result<GenericBrush, Error> {
-        match gradient {
-            Gradient::Linear(linear) => {
-                let mut builder = LinearGradientBrushBuilder::new(&self.rt)
-                    .with_start(to_point2f(linear.start))
-                    .with_end(to_point2f(linear.end));
-                for stop in &linear.stops {
-                    builder = builder.with_stop(gradient_stop_to_d2d(stop));
-                }
-                let brush = builder.build().wrap()?;
-                // Same concern about extra COM clone as above.
-                Ok(brush.to_generic())
-            }
-            Gradient::Radial(radial) => {
-                let radius = radial.radius as f32;
-                let mut builder = RadialGradientBrushBuilder::new(&self.rt)
-                    .with_center(to_point2f(radial.center))
-                    .with_origin_offset(to_point2f(radial.origin_offset))
-                    .with_radius(radius, radius);
-                for stop in &radial.stops {
-                    builder = builder.with_stop(gradient_stop_to_d2d(stop));
-                }
-                let brush = builder.build().wrap()?;
-                // Ditto
-                Ok(brush.to_generic())
-            }
+    fn gradient(&mut self, gradient: Gradient) -> Result<D2DBrush, Error> {
+        if gradient.stops().is_empty() {
+            return Err(new_error(ErrorKind::InvalidGradient));
         }
+        if gradient.units() == GradientUnits::ObjectBoundingBox {
+            return Ok(D2DBrush::BoundingBox(gradient));
+        }
+        self.native_gradient(gradient).map(D2DBrush::Native)
+    }
+
+    fn image_brush(
+        &mut self,
+        _image: &Self::Image,
+        _extend_x: ExtendMode,
+        _extend_y: ExtendMode,
+        _transform: Affine,
+        _interp: InterpolationMode,
+    ) -> Result<D2DBrush, Error> {
+        // TODO: use a bitmap brush.
+        Err(new_error(ErrorKind::NotSupported))
     }
 
     fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: FillRule) {
+        let brush = match self.native_brush(brush, shape.bounding_box()) {
+            Some(brush) => brush,
+            None => return,
+        };
         // TODO: various special-case shapes, for efficiency
         match path_from_shape(self.factory, true, shape, fill_rule) {
-            Ok(path) => self.rt.fill_geometry(&path, brush),
+            Ok(path) => self.rt.fill_geometry(&path, &*brush),
             Err(e) => self.err = Err(e),
         }
     }
@@ -267,6 +366,10 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
         width: impl RoundInto<Self::Coord>,
         style: Option<&StrokeStyle>,
     ) {
+        let brush = match self.native_brush(brush, shape.bounding_box()) {
+            Some(brush) => brush,
+            None => return,
+        };
         // TODO: various special-case shapes, for efficiency
         let path = match path_from_shape(self.factory, false, shape, FillRule::EvenOdd) {
             Ok(path) => path,
@@ -281,7 +384,17 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
         } else {
             None
         };
-        self.rt.draw_geometry(&path, brush, width, style.as_ref());
+        self.rt.draw_geometry(&path, &*brush, width, style.as_ref());
+    }
+
+    fn blurred_rect(&mut self, _rect: Rect, _blur_radius: f64, _brush: &D2DBrush) {
+        // TODO: use the Gaussian blur effect.
+        self.err = Err(new_error(ErrorKind::NotSupported));
+    }
+
+    fn draw_shadow(&mut self, _shape: impl Shape, _offset: Vec2, _blur_radius: f64, _color: Color) {
+        // TODO: use the shadow effect.
+        self.err = Err(new_error(ErrorKind::NotSupported));
     }
 
     fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
@@ -312,6 +425,19 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
         self.ctx_stack.last_mut().unwrap().n_layers_pop += 1;
     }
 
+    fn mask(&mut self, _mask: &Self::Image, _rect: impl Into<Rect>, _mode: MaskMode) {
+        // TODO: use an opacity mask layer with a bitmap brush.
+        self.err = Err(new_error(ErrorKind::NotSupported));
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        Err(new_error(ErrorKind::NotSupported))
+    }
+
+    fn end_mask(&mut self, _mode: MaskMode) -> Result<(), Error> {
+        Err(new_error(ErrorKind::NotSupported))
+    }
+
     fn text(&mut self) -> &mut Self::Text {
         &mut self.inner_text
     }
@@ -332,23 +458,34 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
         // Direct2D takes upper-left, so adjust for baseline.
         let pos = pos.round_into().0;
         let pos = pos - Vector2F::new(0.0, line_metrics[0].baseline());
+        let bbox = Rect::new(
+            pos.x as f64,
+            pos.y as f64,
+            (pos.x + layout.width()) as f64,
+            (pos.y + line_metrics[0].height()) as f64,
+        );
+        let brush = match self.native_brush(brush, bbox) {
+            Some(brush) => brush,
+            None => return,
+        };
         let text_options = DrawTextOptions::NONE;
 
         self.rt
-            .draw_text_layout(pos, &layout.0, brush, text_options);
+            .draw_text_layout(pos, &layout.0, &*brush, text_options);
     }
 
     fn save(&mut self) -> Result<(), Error> {
         let new_state = CtxState {
             transform: self.current_transform(),
             n_layers_pop: 0,
+            is_layer: false,
         };
         self.ctx_stack.push(new_state);
         Ok(())
     }
 
     fn restore(&mut self) -> Result<(), Error> {
-        if self.ctx_stack.len() <= 1 {
+        if self.ctx_stack.len() <= 1 || self.ctx_stack.last().unwrap().is_layer {
             return Err(new_error(ErrorKind::StackUnbalance));
         }
         self.pop_state();
@@ -358,6 +495,46 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
         Ok(())
     }
 
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        let layer = Layer::create(&mut self.rt, None).wrap()?;
+        let transform = affine_to_matrix3x2f(self.current_transform());
+        match clip {
+            Some(shape) => {
+                let path = path_from_shape(self.factory, false, shape, FillRule::NonZero)?;
+                // See the use-after-free workaround in `clip`.
+                let _clone = path.clone();
+                self.rt
+                    .push_layer(&layer)
+                    .with_mask(path)
+                    .with_mask_transform(transform)
+                    .with_opacity(opacity as f32)
+                    .push();
+            }
+            None => self
+                .rt
+                .push_layer(&layer)
+                .with_opacity(opacity as f32)
+                .push(),
+        }
+        let new_state = CtxState {
+            transform: self.current_transform(),
+            n_layers_pop: 1,
+            is_layer: true,
+        };
+        self.ctx_stack.push(new_state);
+        Ok(())
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        if self.ctx_stack.len() <= 1 || !self.ctx_stack.last().unwrap().is_layer {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.pop_state();
+        self.rt
+            .set_transform(&affine_to_matrix3x2f(self.current_transform()));
+        Ok(())
+    }
+
     // Discussion question: should this subsume EndDraw, with BeginDraw on
     // D2DRenderContext creation? I'm thinking not, as the shell might want
     // to do other stuff, possibly related to incremental paint.
@@ -375,6 +552,15 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
             .set_transform(&affine_to_matrix3x2f(self.current_transform()));
     }
 
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        // TODO: the other modes need either the primitive blend of
+        // ID2D1DeviceContext or a blend effect, neither of which are exposed
+        // by the direct2d crate yet.
+        if mode != BlendMode::SrcOver {
+            self.err = Err(new_error(ErrorKind::NotSupported));
+        }
+    }
+
     fn make_image(
         &mut self,
         width: usize,
@@ -388,6 +574,7 @@ impl<'a> RenderContext for D2DRenderContext<'a> {
             ImageFormat::RgbaPremul | ImageFormat::RgbaSeparate => AlphaMode::Premultiplied,
             _ => return Err(new_error(ErrorKind::NotSupported)),
         };
+        format.check_buffer(width, height, buf)?;
         let buf = match format {
             ImageFormat::Rgb => {
                 let mut new_buf = vec![255; width * height * 4];
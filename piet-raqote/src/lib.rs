@@ -2,7 +2,8 @@
 
 // TODO: dpi scaling!!
 use raqote::{
-    Spread, ExtendMode, DrawOptions, DrawTarget, Path, PathBuilder, Point, SolidSource, Source, Transform, Winding,
+    DrawOptions, DrawTarget, ExtendMode, Mask, Path, PathBuilder, Point, SolidSource, Source,
+    Spread, Transform, Winding,
 };
 
 use piet::kurbo::{Affine, PathEl, Rect, Shape, Vec2};
@@ -14,19 +15,90 @@ use font_kit::source::SystemSource;
 use skribo::{make_layout, FontRef, Layout, TextStyle};
 
 use piet::{
-    new_error, Error, ErrorKind, FillRule, Font, FontBuilder, Gradient, GradientStop, ImageFormat,
-    InterpolationMode, LineCap, LineJoin, RenderContext, RoundFrom, RoundInto, StrokeStyle, Text,
-    TextLayout, TextLayoutBuilder, Color,
+    blur_alpha, blur_extent, blurred_rect_mask, expand_gradient_stops, new_error, AlphaMask,
+    BlendMode, Color, ColorInterpolation, Error, ErrorKind, FillRule, Font, FontBuilder, Gradient,
+    GradientStop, GradientUnits, ImageFormat, InterpolationMode, LineCap, LineJoin, MaskMode,
+    RenderContext, RoundFrom, RoundInto, StrokeStyle, Text, TextLayout, TextLayoutBuilder,
 };
 
+/// The raqote crate this backend draws with, re-exported.
+pub use raqote;
+
+#[derive(Clone, Copy, PartialEq)]
+enum StateKind {
+    Save,
+    Layer,
+    MaskContent,
+}
+
+impl Default for StateKind {
+    fn default() -> StateKind {
+        StateKind::Save
+    }
+}
+
+/// Something pushed in a context state, to be undone when it is popped.
+enum Pushed {
+    /// A clip on the current draw target.
+    Clip,
+    /// An offscreen for masked drawing.
+    Mask,
+}
+
 #[derive(Default)]
 struct CtxState {
     transform: Affine,
+    blend_mode: BlendMode,
+    /// Clips and masks pushed in this state, in order.
+    pushed: Vec<Pushed>,
+    kind: StateKind,
+}
+
+/// An offscreen draw target.
+struct Offscreen {
+    dt: DrawTarget,
+    /// The mask to composite with, or `None` for the content of a mask.
+    mask: Option<Mask>,
+}
+
+pub enum Brush<'a> {
+    Source(Source<'a>),
+    /// A gradient in bounding box units, resolved when the brush is used.
+    BoundingBox(Gradient),
+    Image(ImageBrush),
+}
+
+/// An image brush, with its own copy of the pixels.
+pub struct ImageBrush {
+    width: i32,
+    height: i32,
+    data: Vec<u32>,
+    repeat: bool,
+    /// The transform from user space to image space.
+    transform: Transform,
+}
+
+impl ImageBrush {
+    fn source(&self) -> Source {
+        let image = raqote::Image {
+            width: self.width,
+            height: self.height,
+            data: &self.data[..],
+        };
+        let extend = if self.repeat {
+            ExtendMode::Repeat
+        } else {
+            ExtendMode::Pad
+        };
+        Source::Image(image, extend, self.transform)
+    }
 }
 
 pub struct RaqoteRenderContext<'a> {
     draw_target: &'a mut DrawTarget,
     ctx_stack: Vec<CtxState>,
+    /// Offscreens for masks; drawing goes to the last one, if any.
+    offscreens: Vec<Offscreen>,
 
     // TODO: Do actual text
     text: RaqoteText,
@@ -37,17 +109,186 @@ impl<'a> RaqoteRenderContext<'a> {
         RaqoteRenderContext {
             draw_target,
             ctx_stack: vec![CtxState::default()],
+            offscreens: Vec::new(),
             text: RaqoteText,
         }
     }
 
+    /// The draw target that drawing operations currently go to.
+    fn target(&mut self) -> &mut DrawTarget {
+        match self.offscreens.last_mut() {
+            Some(offscreen) => &mut offscreen.dt,
+            None => &mut *self.draw_target,
+        }
+    }
+
+    /// Set the transform of the current draw target from the context state.
+    fn update_transform(&mut self) {
+        let transform = affine_to_transform(self.current_transform());
+        self.target().set_transform(&transform);
+    }
+
     fn current_transform(&self) -> Affine {
         // This is an unwrap because we protect the invariant.
         self.ctx_stack.last().unwrap().transform
     }
 
+    /// The draw options for the current context state.
+    fn draw_options(&self) -> DrawOptions {
+        let mut options = DrawOptions::new();
+        options.blend_mode = convert_blend_mode(self.ctx_stack.last().unwrap().blend_mode);
+        options
+    }
+
+    /// Push a new state, inheriting the transform and blend mode.
+    fn push_state(&mut self, kind: StateKind) {
+        let new_state = CtxState {
+            transform: self.current_transform(),
+            blend_mode: self.ctx_stack.last().unwrap().blend_mode,
+            pushed: Vec::new(),
+            kind,
+        };
+        self.ctx_stack.push(new_state);
+    }
+
+    /// Pop a state, undoing its clips and compositing its masks.
+    ///
+    /// The caller is responsible for updating the transform.
     fn pop_state(&mut self) {
-        self.ctx_stack.pop();
+        // This is an unwrap because we protect the invariant.
+        let old_state = self.ctx_stack.pop().unwrap();
+        for pushed in old_state.pushed.iter().rev() {
+            match pushed {
+                Pushed::Clip => self.target().pop_clip(),
+                Pushed::Mask => self.composite_offscreen(),
+            }
+        }
+    }
+
+    fn current_kind(&self) -> StateKind {
+        self.ctx_stack.last().unwrap().kind
+    }
+
+    /// Redirect drawing in the current state to an offscreen with the mask.
+    fn push_mask(&mut self, mask: Mask) {
+        let dt = self.new_offscreen_target();
+        self.offscreens.push(Offscreen {
+            dt,
+            mask: Some(mask),
+        });
+        self.ctx_stack.last_mut().unwrap().pushed.push(Pushed::Mask);
+    }
+
+    /// Create a draw target the same size as the current one.
+    fn new_offscreen_target(&mut self) -> DrawTarget {
+        let (width, height) = {
+            let target = self.target();
+            (target.width(), target.height())
+        };
+        let mut dt = DrawTarget::new(width, height);
+        dt.set_transform(&affine_to_transform(self.current_transform()));
+        dt
+    }
+
+    /// Composite the innermost offscreen through its mask.
+    fn composite_offscreen(&mut self) {
+        // This is an unwrap because masks and offscreens are pushed together.
+        let offscreen = self.offscreens.pop().unwrap();
+        let mask = offscreen.mask.unwrap();
+        let image = raqote::Image {
+            width: offscreen.dt.width(),
+            height: offscreen.dt.height(),
+            data: offscreen.dt.get_data(),
+        };
+        // The offscreen is in device space, same as the mask.
+        let target = self.target();
+        target.set_transform(&Transform::identity());
+        target.mask(
+            &Source::Image(image, ExtendMode::Pad, Transform::identity()),
+            0,
+            0,
+            &mask,
+        );
+    }
+
+    /// Render the blurred coverage of a shape into a device space mask.
+    ///
+    /// The blur radius is scaled by the current transform. Returns `None` if
+    /// the shadow doesn't touch the draw target.
+    fn shadow_mask(
+        &mut self,
+        shape: impl Shape,
+        offset: Vec2,
+        blur_radius: f64,
+    ) -> Option<AlphaMask> {
+        let transform = self.current_transform() * Affine::translate(offset);
+        let radius = blur_radius * transform_scale(transform);
+        let extent = blur_extent(radius);
+        let (width, height) = {
+            let target = self.target();
+            (target.width() as f64, target.height() as f64)
+        };
+        // Content further than the extent outside the target can't reach it.
+        let bbox = transform_bbox(transform, shape.bounding_box());
+        let x0 = (bbox.x0.max(-extent) - extent).floor() as i32;
+        let y0 = (bbox.y0.max(-extent) - extent).floor() as i32;
+        let x1 = (bbox.x1.min(width + extent) + extent).ceil() as i32;
+        let y1 = (bbox.y1.min(height + extent) + extent).ceil() as i32;
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+        let mut dt = DrawTarget::new(x1 - x0, y1 - y0);
+        let origin = Vec2::new(-x0 as f64, -y0 as f64);
+        dt.set_transform(&affine_to_transform(Affine::translate(origin) * transform));
+        let opaque = Source::Solid(SolidSource {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        });
+        dt.fill(&shape_to_path(shape), &opaque, &DrawOptions::new());
+        let (width, height) = ((x1 - x0) as usize, (y1 - y0) as usize);
+        let mut data = dt
+            .get_data()
+            .iter()
+            .map(|pixel| (pixel >> 24) as u8)
+            .collect::<Vec<_>>();
+        blur_alpha(&mut data, width, height, width, radius);
+        Some(AlphaMask {
+            x0,
+            y0,
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Draw a source through a device space alpha mask.
+    fn fill_mask(&mut self, source: &Source, mask: &AlphaMask) {
+        let (width, height) = {
+            let target = self.target();
+            (target.width(), target.height())
+        };
+        // Crop the mask to the draw target, as raqote expects it to fit.
+        let x0 = mask.x0.max(0);
+        let y0 = mask.y0.max(0);
+        let x1 = (mask.x0 + mask.width as i32).min(width);
+        let y1 = (mask.y0 + mask.height as i32).min(height);
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+        let mut data = Vec::with_capacity(((x1 - x0) * (y1 - y0)) as usize);
+        for y in y0..y1 {
+            let row = (y - mask.y0) as usize * mask.width;
+            let start = row + (x0 - mask.x0) as usize;
+            data.extend_from_slice(&mask.data[start..start + (x1 - x0) as usize]);
+        }
+        let mask = Mask {
+            width: x1 - x0,
+            height: y1 - y0,
+            data,
+        };
+        self.target().mask(source, x0, y0, &mask);
     }
 }
 
@@ -112,6 +353,39 @@ fn convert_line_cap(line_cap: LineCap) -> raqote::LineCap {
     }
 }
 
+fn convert_blend_mode(mode: BlendMode) -> raqote::BlendMode {
+    match mode {
+        BlendMode::Clear => raqote::BlendMode::Clear,
+        BlendMode::Src => raqote::BlendMode::Src,
+        BlendMode::Dst => raqote::BlendMode::Dst,
+        BlendMode::SrcOver => raqote::BlendMode::SrcOver,
+        BlendMode::DstOver => raqote::BlendMode::DstOver,
+        BlendMode::SrcIn => raqote::BlendMode::SrcIn,
+        BlendMode::DstIn => raqote::BlendMode::DstIn,
+        BlendMode::SrcOut => raqote::BlendMode::SrcOut,
+        BlendMode::DstOut => raqote::BlendMode::DstOut,
+        BlendMode::SrcAtop => raqote::BlendMode::SrcAtop,
+        BlendMode::DstAtop => raqote::BlendMode::DstAtop,
+        BlendMode::Xor => raqote::BlendMode::Xor,
+        BlendMode::Add => raqote::BlendMode::Add,
+        BlendMode::Multiply => raqote::BlendMode::Multiply,
+        BlendMode::Screen => raqote::BlendMode::Screen,
+        BlendMode::Overlay => raqote::BlendMode::Overlay,
+        BlendMode::Darken => raqote::BlendMode::Darken,
+        BlendMode::Lighten => raqote::BlendMode::Lighten,
+        BlendMode::ColorDodge => raqote::BlendMode::ColorDodge,
+        BlendMode::ColorBurn => raqote::BlendMode::ColorBurn,
+        BlendMode::HardLight => raqote::BlendMode::HardLight,
+        BlendMode::SoftLight => raqote::BlendMode::SoftLight,
+        BlendMode::Difference => raqote::BlendMode::Difference,
+        BlendMode::Exclusion => raqote::BlendMode::Exclusion,
+        BlendMode::Hue => raqote::BlendMode::Hue,
+        BlendMode::Saturation => raqote::BlendMode::Saturation,
+        BlendMode::Color => raqote::BlendMode::Color,
+        BlendMode::Luminosity => raqote::BlendMode::Luminosity,
+    }
+}
+
 fn convert_dash(dash: &(Vec<f64>, f64)) -> (Vec<f32>, f32) {
     // TODO: find cheaper way to do this?
     (dash.0.iter().map(|d| *d as f32).collect(), dash.1 as f32)
@@ -129,35 +403,96 @@ fn affine_to_transform(affine: Affine) -> Transform {
     )
 }
 
+/// Copy an image, mirrored to the right and/or below, so that repeating the
+/// copy reflects the original.
+fn mirror_image(image: &InternalImage, mirror_x: bool, mirror_y: bool) -> (usize, usize, Vec<u32>) {
+    let width = if mirror_x {
+        2 * image.width
+    } else {
+        image.width
+    };
+    let height = if mirror_y {
+        2 * image.height
+    } else {
+        image.height
+    };
+    let mut data = Vec::with_capacity(width * height);
+    for y in 0..height {
+        let src_y = if y < image.height {
+            y
+        } else {
+            2 * image.height - 1 - y
+        };
+        for x in 0..width {
+            let src_x = if x < image.width {
+                x
+            } else {
+                2 * image.width - 1 - x
+            };
+            data.push(image.data[src_y * image.width + src_x]);
+        }
+    }
+    (width, height, data)
+}
+
+/// The mask coverage of a premultiplied ARGB pixel.
+fn coverage(pixel: u32, mode: MaskMode) -> u8 {
+    match mode {
+        MaskMode::Alpha => (pixel >> 24) as u8,
+        MaskMode::Luminance => {
+            let r = ((pixel >> 16) & 255) as f32;
+            let g = ((pixel >> 8) & 255) as f32;
+            let b = (pixel & 255) as f32;
+            (0.2125 * r + 0.7154 * g + 0.0721 * b).round().min(255.0) as u8
+        }
+    }
+}
+
 // Convert a RGBA u32 to a ARBG u32
 fn rgba_to_arbg(rgba: u32) -> u32 {
     (rgba << 24) | (rgba >> 8)
 }
 
+/// Convert a 16 bit channel to 8 bits, rounding to nearest.
+fn narrow16(x: u16) -> u8 {
+    ((x as u32 * 255 + 32767) / 65535) as u8
+}
+
+/// The transform from user space to the pixels of an image drawn in `rect`.
+///
+/// Raqote image sources map user space to image space.
 fn transform_image_to_rect(rect: Rect, image: &raqote::Image) -> Transform {
     let translate = Transform::create_translation(-rect.x0 as f32, -rect.y0 as f32);
+    let scale = Transform::create_scale(
+        (image.width as f64 / rect.width()) as f32,
+        (image.height as f64 / rect.height()) as f32,
+    );
+    translate.post_mul(&scale)
+}
 
-    dbg!(rect.width());
-    dbg!(image.width);
-    let rect_width = rect.width();
-    let rect_height = rect.height();
-// 1 - (16 * 40 / 1000)
-    let scale_width = (1. - (image.width as f64 * rect.width() / 1000.)) as f32;
-    let scale_height = (1. - (image.height as f64 * rect.height() / 1000.)) as f32;
-
-    // let scale_width = (image.width as f64 / rect_width) as f32;
-    // let scale_height = (image.height as f64 / rect_height) as f32;
-
-    //This number seems plausible but it multiplies with overflow
-    println!("possible scale: {:?}, {:?}", scale_width, scale_height);
-
-    let scale = Transform::create_scale(scale_width, scale_height);
-
-
-    dbg!(scale_width * image.width as f32);
+/// The geometric mean of the scale factors of a transform.
+fn transform_scale(transform: Affine) -> f64 {
+    let c = transform.as_coeffs();
+    (c[0] * c[3] - c[1] * c[2]).abs().sqrt()
+}
 
-    // TODO: Move `inverse()` to Raqote
-    translate.post_mul(&scale)
+/// The bounding box of a transformed rectangle.
+fn transform_bbox(transform: Affine, rect: Rect) -> Rect {
+    let corners = [
+        transform * piet::kurbo::Point::new(rect.x0, rect.y0),
+        transform * piet::kurbo::Point::new(rect.x1, rect.y0),
+        transform * piet::kurbo::Point::new(rect.x0, rect.y1),
+        transform * piet::kurbo::Point::new(rect.x1, rect.y1),
+    ];
+    let first = Rect::new(corners[0].x, corners[0].y, corners[0].x, corners[0].y);
+    corners.iter().fold(first, |bbox, p| {
+        Rect::new(
+            bbox.x0.min(p.x),
+            bbox.y0.min(p.y),
+            bbox.x1.max(p.x),
+            bbox.y1.max(p.y),
+        )
+    })
 }
 
 fn shape_to_path(shape: impl Shape) -> Path {
@@ -192,8 +527,99 @@ fn shape_to_path(shape: impl Shape) -> Path {
     path
 }
 
-fn convert_gradient_stops(stops: Vec<GradientStop>) -> Vec<raqote::GradientStop> {
-    stops
+fn convert_gradient<'a>(gradient: Gradient) -> Source<'a> {
+    let (source, transform) = match gradient {
+        Gradient::Linear(gradient) => {
+            let stops = convert_gradient_stops(&gradient.stops, gradient.interpolation);
+            let start = to_point((gradient.start.x, gradient.start.y));
+            let end = to_point((gradient.end.x, gradient.end.y));
+
+            let source = Source::new_linear_gradient(
+                raqote::Gradient { stops },
+                start,
+                end,
+                convert_extend(gradient.extend),
+            );
+            (source, gradient.transform)
+        }
+        Gradient::Radial(gradient) => {
+            let stops = convert_gradient_stops(&gradient.stops, gradient.interpolation);
+            let center = to_point((gradient.center.x, gradient.center.y));
+            let origin = gradient.center + gradient.origin_offset;
+            let origin = to_point((origin.x, origin.y));
+
+            // Same as cairo, from the start circle around the origin to
+            // the end circle around the center.
+            let source = Source::new_two_circle_radial_gradient(
+                raqote::Gradient { stops },
+                origin,
+                gradient.start_radius as f32,
+                center,
+                gradient.radius as f32,
+                convert_extend(gradient.extend),
+            );
+            (source, gradient.transform)
+        }
+        Gradient::Sweep(gradient) => {
+            let stops = convert_gradient_stops(&gradient.stops, gradient.interpolation);
+            let center = to_point((gradient.center.x, gradient.center.y));
+
+            // Raqote sweep angles are in degrees.
+            let source = Source::new_sweep_gradient(
+                raqote::Gradient { stops },
+                center,
+                gradient.start_angle.to_degrees() as f32,
+                gradient.end_angle.to_degrees() as f32,
+                convert_extend(gradient.extend),
+            );
+            (source, gradient.transform)
+        }
+    };
+    match transform {
+        Some(transform) => transform_source(source, transform),
+        None => source,
+    }
+}
+
+/// Apply a gradient transform to a gradient source.
+///
+/// The transform of a raqote gradient maps user space to the gradient's own
+/// space, so the inverse of the gradient transform goes before it.
+fn transform_source<'a>(source: Source<'a>, transform: Affine) -> Source<'a> {
+    let inverse = affine_to_transform(transform.inverse());
+    match source {
+        Source::LinearGradient(gradient, spread, t) => {
+            Source::LinearGradient(gradient, spread, inverse.post_mul(&t))
+        }
+        Source::RadialGradient(gradient, spread, t) => {
+            Source::RadialGradient(gradient, spread, inverse.post_mul(&t))
+        }
+        Source::TwoCircleRadialGradient(gradient, spread, c1, r1, c2, r2, t) => {
+            Source::TwoCircleRadialGradient(gradient, spread, c1, r1, c2, r2, inverse.post_mul(&t))
+        }
+        Source::SweepGradient(gradient, spread, start, end, t) => {
+            Source::SweepGradient(gradient, spread, start, end, inverse.post_mul(&t))
+        }
+        source => source,
+    }
+}
+
+fn convert_extend(extend: piet::ExtendMode) -> Spread {
+    match extend {
+        piet::ExtendMode::Pad => Spread::Pad,
+        piet::ExtendMode::Repeat => Spread::Repeat,
+        piet::ExtendMode::Reflect => Spread::Reflect,
+    }
+}
+
+/// Raqote interpolates gradients in sRGB with separate alpha.
+const NATIVE_INTERPOLATION: ColorInterpolation = ColorInterpolation::UnpremultipliedSrgb;
+
+fn convert_gradient_stops(
+    stops: &[GradientStop],
+    interpolation: ColorInterpolation,
+) -> Vec<raqote::GradientStop> {
+    expand_gradient_stops(stops, interpolation, NATIVE_INTERPOLATION)
         .iter()
         .map(|stop| raqote::GradientStop {
             position: stop.pos,
@@ -208,7 +634,7 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
     type Coord = f32;
 
     // The render context must outlive the brush
-    type Brush = Source<'a>;
+    type Brush = Brush<'a>;
 
     //QUESTION Text should of type TextLayout??
     // type Text: Text<TextLayout = Self::TextLayout>;
@@ -224,42 +650,56 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
 
     fn solid_brush(&mut self, rgba: Color) -> Self::Brush {
         let (r, g, b, a) = split_rgba(rgba);
-        Source::Solid(SolidSource { r, g, b, a })
+        Brush::Source(Source::Solid(SolidSource { r, g, b, a }))
     }
 
     fn gradient(&mut self, gradient: Gradient) -> Result<Self::Brush, Error> {
-        match gradient {
-            Gradient::Linear(gradient) => {
-                let stops = convert_gradient_stops(gradient.stops);
-                let start = to_point((gradient.start.x, gradient.start.y));
-                let end = to_point((gradient.end.x, gradient.end.y));
-
-                Ok(Source::new_linear_gradient(
-                    raqote::Gradient { stops },
-                    start,
-                    end,
-                    Spread::Pad
-                ))
-            }
-            Gradient::Radial(gradient) => {
-                let stops = convert_gradient_stops(gradient.stops);
-                let center = to_point((gradient.center.x, gradient.center.y));
-
-                Ok(Source::new_radial_gradient(
-                    raqote::Gradient { stops },
-                    center,
-                    gradient.radius as f32,
-                    Spread::Pad
-                ))
-            }
+        if gradient.stops().is_empty() {
+            return Err(new_error(ErrorKind::InvalidGradient));
+        }
+        if gradient.units() == GradientUnits::ObjectBoundingBox {
+            return Ok(Brush::BoundingBox(gradient));
         }
+        Ok(Brush::Source(convert_gradient(gradient)))
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &Self::Image,
+        extend_x: piet::ExtendMode,
+        extend_y: piet::ExtendMode,
+        transform: Affine,
+        _interp: InterpolationMode,
+    ) -> Result<Self::Brush, Error> {
+        // Raqote image sources have no filter setting and are always sampled
+        // bilinearly, so the interpolation mode is ignored.
+        //
+        // Raqote images either pad or repeat, in both directions. Reflection
+        // is done by repeating a mirrored copy.
+        let repeat = match (extend_x, extend_y) {
+            (piet::ExtendMode::Pad, piet::ExtendMode::Pad) => false,
+            (piet::ExtendMode::Pad, _) | (_, piet::ExtendMode::Pad) => {
+                return Err(new_error(ErrorKind::NotSupported));
+            }
+            _ => true,
+        };
+        let mirror_x = extend_x == piet::ExtendMode::Reflect;
+        let mirror_y = extend_y == piet::ExtendMode::Reflect;
+        let (width, height, data) = mirror_image(image, mirror_x, mirror_y);
+        Ok(Brush::Image(ImageBrush {
+            width: width as i32,
+            height: height as i32,
+            data,
+            repeat,
+            transform: affine_to_transform(transform.inverse()),
+        }))
     }
 
     fn clear(&mut self, rgba: Color) {
         // let rgba = (rgb << 8) | 0xff;
         let (r, g, b, a) = split_rgba(rgba);
         let source = SolidSource { r, g, b, a };
-        self.draw_target.clear(source);
+        self.target().clear(source);
     }
 
     fn stroke(
@@ -269,6 +709,21 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
         width: impl RoundInto<Self::Coord>,
         style: Option<&StrokeStyle>,
     ) {
+        let image_source;
+        let brush = match *brush {
+            Brush::Source(ref source) => source,
+            Brush::Image(ref image) => {
+                image_source = image.source();
+                &image_source
+            }
+            Brush::BoundingBox(ref gradient) => {
+                if let Some(gradient) = gradient.resolve_units(shape.bounding_box()) {
+                    let brush = Brush::Source(convert_gradient(gradient));
+                    self.stroke(shape, &brush, width, style);
+                }
+                return;
+            }
+        };
         let path = shape_to_path(shape);
 
         // TODO: Factor this out
@@ -303,11 +758,26 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
             dash_offset,
         };
 
-        self.draw_target
-            .stroke(&path, brush, &stroke_style, &DrawOptions::default());
+        let options = self.draw_options();
+        self.target().stroke(&path, brush, &stroke_style, &options);
     }
 
     fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: FillRule) {
+        let image_source;
+        let brush = match *brush {
+            Brush::Source(ref source) => source,
+            Brush::Image(ref image) => {
+                image_source = image.source();
+                &image_source
+            }
+            Brush::BoundingBox(ref gradient) => {
+                if let Some(gradient) = gradient.resolve_units(shape.bounding_box()) {
+                    let brush = Brush::Source(convert_gradient(gradient));
+                    self.fill(shape, &brush, fill_rule);
+                }
+                return;
+            }
+        };
         let mut path = shape_to_path(shape);
 
         path.winding = match fill_rule {
@@ -315,7 +785,45 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
             FillRule::NonZero => Winding::NonZero,
         };
 
-        self.draw_target.fill(&path, brush, &DrawOptions::default());
+        let options = self.draw_options();
+        self.target().fill(&path, brush, &options);
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &Self::Brush) {
+        let image_source;
+        let brush = match *brush {
+            Brush::Source(ref source) => source,
+            Brush::Image(ref image) => {
+                image_source = image.source();
+                &image_source
+            }
+            Brush::BoundingBox(ref gradient) => {
+                if let Some(gradient) = gradient.resolve_units(rect) {
+                    let brush = Brush::Source(convert_gradient(gradient));
+                    self.blurred_rect(rect, blur_radius, &brush);
+                }
+                return;
+            }
+        };
+        let transform = self.current_transform();
+        let c = transform.as_coeffs();
+        if c[1] == 0.0 && c[2] == 0.0 {
+            // The rect stays axis-aligned in device space, so we can compute
+            // the blur analytically. A non-uniform scale blurs by the mean.
+            let radius = blur_radius * transform_scale(transform);
+            let mask = blurred_rect_mask(transform_bbox(transform, rect), radius);
+            self.fill_mask(brush, &mask);
+        } else if let Some(mask) = self.shadow_mask(rect, Vec2::ZERO, blur_radius) {
+            self.fill_mask(brush, &mask);
+        }
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        let (r, g, b, a) = split_rgba(color);
+        let source = Source::Solid(SolidSource { r, g, b, a });
+        if let Some(mask) = self.shadow_mask(shape, offset, blur_radius) {
+            self.fill_mask(&source, &mask);
+        }
     }
 
     fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
@@ -326,8 +834,71 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
             FillRule::NonZero => Winding::NonZero,
         };
 
-        //QUESTION we don't ever pop clip I hope that's okay?
-        self.draw_target.push_clip(&path);
+        self.target().push_clip(&path);
+        self.ctx_stack.last_mut().unwrap().pushed.push(Pushed::Clip);
+    }
+
+    fn mask(&mut self, mask: &Self::Image, rect: impl Into<Rect>, mode: MaskMode) {
+        let rect = rect.into();
+        let (width, height) = {
+            let target = self.target();
+            (target.width(), target.height())
+        };
+        // Rasterize the mask in device space, sampling the nearest pixel.
+        let image_to_user = Affine::new([
+            rect.width() / (mask.width as f64),
+            0.0,
+            0.0,
+            rect.height() / (mask.height as f64),
+            rect.x0,
+            rect.y0,
+        ]);
+        let device_to_image = (self.current_transform() * image_to_user).inverse();
+        let mut data = vec![0; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let p = device_to_image * piet::kurbo::Point::new(x as f64 + 0.5, y as f64 + 0.5);
+                let (ix, iy) = (p.x.floor(), p.y.floor());
+                if ix >= 0.0 && iy >= 0.0 && ix < mask.width as f64 && iy < mask.height as f64 {
+                    let pixel = mask.data[(iy as usize) * mask.width + (ix as usize)];
+                    data[(y * width + x) as usize] = coverage(pixel, mode);
+                }
+            }
+        }
+        self.push_mask(Mask {
+            width,
+            height,
+            data,
+        });
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        self.push_state(StateKind::MaskContent);
+        let dt = self.new_offscreen_target();
+        self.offscreens.push(Offscreen { dt, mask: None });
+        Ok(())
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        if self.ctx_stack.len() <= 1 || self.current_kind() != StateKind::MaskContent {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.pop_state();
+        // This is an unwrap because `begin_mask` pushed it.
+        let content = self.offscreens.pop().unwrap();
+        let data = content
+            .dt
+            .get_data()
+            .iter()
+            .map(|pixel| coverage(*pixel, mode))
+            .collect();
+        self.push_mask(Mask {
+            width: content.dt.width(),
+            height: content.dt.height(),
+            data,
+        });
+        self.update_transform();
+        Ok(())
     }
 
     fn text(&mut self) -> &mut Self::Text {
@@ -343,6 +914,29 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
         brush: &Self::Brush,
     ) {
         let pos = to_point(pos);
+        let image_source;
+        let brush = match *brush {
+            Brush::Source(ref source) => source,
+            Brush::Image(ref image) => {
+                image_source = image.source();
+                &image_source
+            }
+            Brush::BoundingBox(ref gradient) => {
+                let metrics = layout.font.font.font.metrics();
+                let scale = layout.font.size / metrics.units_per_em as f32;
+                let bbox = Rect::new(
+                    pos.x as f64,
+                    (pos.y - metrics.ascent * scale) as f64,
+                    (pos.x + layout.width()) as f64,
+                    (pos.y - metrics.descent * scale) as f64,
+                );
+                if let Some(gradient) = gradient.resolve_units(bbox) {
+                    let brush = Brush::Source(convert_gradient(gradient));
+                    self.draw_text(layout, RaqotePoint(pos), &brush);
+                }
+                return;
+            }
+        };
 
         let positions = layout
             .layout
@@ -358,32 +952,51 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
             .map(|glyph| glyph.glyph_id)
             .collect::<Vec<u32>>();
 
-        self.draw_target.draw_glyphs(
+        let options = self.draw_options();
+        self.target().draw_glyphs(
             &layout.font.font.font,
             layout.font.size,
             &glyphs,
             &positions,
             brush,
-            &DrawOptions::default(),
+            &options,
         );
     }
 
     fn save(&mut self) -> Result<(), Error> {
-        let new_state = CtxState {
-            transform: self.current_transform(),
-        };
-        self.ctx_stack.push(new_state);
+        self.push_state(StateKind::Save);
         Ok(())
     }
 
     fn restore(&mut self) -> Result<(), Error> {
-        if self.ctx_stack.len() <= 1 {
+        if self.ctx_stack.len() <= 1 || self.current_kind() != StateKind::Save {
             return Err(new_error(ErrorKind::StackUnbalance));
         }
         self.pop_state();
-        // Move this code into impl to avoid duplication with transform?
-        self.draw_target
-            .set_transform(&affine_to_transform(self.current_transform()));
+        self.update_transform();
+        Ok(())
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        // The layer clip goes in its own state, so that it is still in effect
+        // when the layer is composited, while clips inside the layer are not.
+        self.push_state(StateKind::Save);
+        if let Some(shape) = clip {
+            self.clip(shape, FillRule::NonZero);
+        }
+        self.target().push_layer(opacity as f32);
+        self.push_state(StateKind::Layer);
+        Ok(())
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        if self.ctx_stack.len() <= 2 || self.current_kind() != StateKind::Layer {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.pop_state();
+        self.target().pop_layer();
+        self.pop_state();
+        self.update_transform();
         Ok(())
     }
 
@@ -397,8 +1010,11 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
 
     fn transform(&mut self, transform: Affine) {
         self.ctx_stack.last_mut().unwrap().transform *= transform;
-        self.draw_target
-            .set_transform(&affine_to_transform(self.current_transform()));
+        self.update_transform();
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.ctx_stack.last_mut().unwrap().blend_mode = mode;
     }
 
     fn make_image(
@@ -408,7 +1024,12 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
         buf: &[u8],
         format: ImageFormat,
     ) -> Result<Self::Image, Error> {
+        format.check_buffer(width, height, buf)?;
         let mut image: Vec<u32> = Vec::new();
+        fn premul(x: u8, a: u8) -> u32 {
+            let y = (x as u16) * (a as u16);
+            ((y + (y >> 8) + 0x80) >> 8) as u32
+        }
 
         match format {
             ImageFormat::Rgb => {
@@ -429,10 +1050,6 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
                 }
             }
             ImageFormat::RgbaSeparate => {
-                fn premul(x: u8, a: u8) -> u32 {
-                    let y = (x as u16) * (a as u16);
-                    ((y + (y >> 8) + 0x80) >> 8) as u32
-                }
                 for i in buf.chunks(4) {
                     let a = i[3];
                     image.push(
@@ -443,6 +1060,37 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
                     )
                 }
             }
+            ImageFormat::BgraPremul => {
+                // The little endian bytes of raqote's ARGB words, so this is
+                // a plain copy on little endian machines.
+                image.extend(
+                    buf.chunks(4)
+                        .map(|i| u32::from_le_bytes([i[0], i[1], i[2], i[3]])),
+                );
+            }
+            ImageFormat::Gray8 => {
+                for &v in buf {
+                    let v = v as u32;
+                    image.push(0xff << 24 | (v << 16) | (v << 8) | v);
+                }
+            }
+            ImageFormat::Alpha8 => {
+                for &a in buf {
+                    image.push((a as u32) << 24);
+                }
+            }
+            ImageFormat::Rgba16 => {
+                for i in buf.chunks(8) {
+                    let c = |j: usize| narrow16(u16::from_ne_bytes([i[j], i[j + 1]]));
+                    let a = c(6);
+                    image.push(
+                        ((a as u32) << 24)
+                            | (premul(c(0), a) << 16)
+                            | (premul(c(2), a) << 8)
+                            | premul(c(4), a),
+                    )
+                }
+            }
             _ => return Err(new_error(ErrorKind::NotSupported)),
         };
 
@@ -459,6 +1107,7 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
         rect: impl Into<Rect>,
         _interp: InterpolationMode,
     ) {
+        // As for image brushes, raqote can only sample bilinearly.
         let rect = rect.into();
 
         let raqote_image = raqote::Image {
@@ -468,17 +1117,15 @@ impl<'a> RenderContext for RaqoteRenderContext<'a> {
         };
 
         let transform = transform_image_to_rect(rect, &raqote_image);
-        // let transform = Transform::create_translation(-rect.x0 as f32, -rect.y0 as f32);
 
         let path = shape_to_path(rect);
 
-        self.draw_target.fill(
+        let options = self.draw_options();
+        self.target().fill(
             &path,
-            //TODO figure out why scaling is off
             &Source::Image(raqote_image, ExtendMode::Repeat, transform),
-            &DrawOptions::default(),
+            &options,
         );
-        // self.draw_target.draw_image_at(rect.x0 as f32, rect.y0 as f32, &raqote_image, &DrawOptions::default());
     }
 }
 
@@ -559,14 +1206,18 @@ impl FontBuilder for RaqoteFontBuilder {
     type Out = RaqoteFont;
 
     fn build(self) -> Result<Self::Out, Error> {
+        let missing = || new_error(ErrorKind::MissingFont(self.family.clone()));
         let font = SystemSource::new()
             .select_best_match(
-                &[FamilyName::Title(self.family), FamilyName::SansSerif],
+                &[
+                    FamilyName::Title(self.family.clone()),
+                    FamilyName::SansSerif,
+                ],
                 &self.properties,
             )
-            .unwrap()
+            .map_err(|_| missing())?
             .load()
-            .unwrap();
+            .map_err(|_| missing())?;
 
         Ok(RaqoteFont {
             font: FontRef::new(font),
@@ -0,0 +1,41 @@
+//! Capture a test picture, for playback with the piet-replay tool.
+//!
+//! Writes the binary form to temp-capture.pietcap and the text form to
+//! temp-capture.json.
+
+use std::fs::File;
+
+use piet::{RecordingContext, RenderContext};
+use piet_replay::Capture;
+
+use piet_test::draw_test_picture;
+
+const TEXTURE_WIDTH: usize = 400;
+const TEXTURE_HEIGHT: usize = 200;
+
+const HIDPI: f64 = 2.0;
+
+fn main() {
+    let test_picture_number = std::env::args()
+        .skip(1)
+        .next()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut rc = RecordingContext::new();
+    draw_test_picture(&mut rc, test_picture_number).unwrap();
+    rc.finish().unwrap();
+    let list = rc.into_display_list();
+    let capture = Capture::new(&list, TEXTURE_WIDTH, TEXTURE_HEIGHT, HIDPI).unwrap();
+
+    let file = File::create("temp-capture.pietcap").expect("Couldn't create capture file");
+    capture.write_binary(file).unwrap();
+    let file = File::create("temp-capture.json").expect("Couldn't create capture file");
+    capture.write_json(file).unwrap();
+
+    // Both forms read back to the same capture.
+    for path in &["temp-capture.pietcap", "temp-capture.json"] {
+        let file = File::open(path).expect("Couldn't open capture file");
+        assert_eq!(Capture::read(file).unwrap(), capture);
+    }
+}
@@ -0,0 +1,791 @@
+//! The serialized types of a capture.
+//!
+//! These mirror the types of piet's display list, but are owned by this
+//! crate, so that the file format only changes when we decide it does.
+//! Geometry is stored as plain arrays: points as `[x, y]`, rectangles as
+//! `[x0, y0, x1, y1]` and affine transforms as their six coefficients.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use piet::kurbo::{self, BezPath, Shape};
+
+/// Define a mirror of a fieldless piet enum, with conversions both ways.
+macro_rules! mirror_enum {
+    ($(#[$attr:meta])* $name:ident { $($variant:ident),* $(,)* }) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+        pub enum $name {
+            $($variant),*
+        }
+
+        impl From<piet::$name> for $name {
+            fn from(x: piet::$name) -> $name {
+                match x {
+                    $(piet::$name::$variant => $name::$variant),*
+                }
+            }
+        }
+
+        impl From<$name> for piet::$name {
+            fn from(x: $name) -> piet::$name {
+                match x {
+                    $($name::$variant => piet::$name::$variant),*
+                }
+            }
+        }
+    };
+}
+
+mirror_enum!(ColorSpace {
+    Srgb,
+    LinearSrgb,
+    DisplayP3,
+});
+
+mirror_enum!(ExtendMode {
+    Pad,
+    Repeat,
+    Reflect,
+});
+
+mirror_enum!(GradientUnits {
+    UserSpace,
+    ObjectBoundingBox,
+});
+
+mirror_enum!(ColorInterpolation {
+    PremultipliedSrgb,
+    UnpremultipliedSrgb,
+    LinearRgb,
+});
+
+mirror_enum!(FillRule { NonZero, EvenOdd });
+
+mirror_enum!(LineJoin {
+    Miter,
+    Round,
+    Bevel,
+});
+
+mirror_enum!(LineCap {
+    Butt,
+    Round,
+    Square,
+});
+
+mirror_enum!(MaskMode { Alpha, Luminance });
+
+mirror_enum!(InterpolationMode {
+    NearestNeighbor,
+    Bilinear,
+});
+
+mirror_enum!(BlendMode {
+    Clear,
+    Src,
+    Dst,
+    SrcOver,
+    DstOver,
+    SrcIn,
+    DstIn,
+    SrcOut,
+    DstOut,
+    SrcAtop,
+    DstAtop,
+    Xor,
+    Add,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+});
+
+/// The pixel format of an embedded image.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    Rgb,
+    RgbaSeparate,
+    RgbaPremul,
+    BgraPremul,
+    Gray8,
+    Alpha8,
+    /// The samples are little endian, whatever machine made the capture.
+    Rgba16,
+}
+
+impl ImageFormat {
+    /// Convert pixels between native byte order and the order in captures.
+    ///
+    /// This only changes `Rgba16` pixels on big endian machines, and doing
+    /// it twice gives back the original.
+    pub(crate) fn swap_byte_order(self, data: &mut [u8]) {
+        if self == ImageFormat::Rgba16 && cfg!(target_endian = "big") {
+            for sample in data.chunks_mut(2) {
+                sample.swap(0, 1);
+            }
+        }
+    }
+
+    /// Convert a piet image format, if it can be stored.
+    pub fn from_piet(format: piet::ImageFormat) -> Option<ImageFormat> {
+        match format {
+            piet::ImageFormat::Rgb => Some(ImageFormat::Rgb),
+            piet::ImageFormat::RgbaSeparate => Some(ImageFormat::RgbaSeparate),
+            piet::ImageFormat::RgbaPremul => Some(ImageFormat::RgbaPremul),
+            piet::ImageFormat::BgraPremul => Some(ImageFormat::BgraPremul),
+            piet::ImageFormat::Gray8 => Some(ImageFormat::Gray8),
+            piet::ImageFormat::Alpha8 => Some(ImageFormat::Alpha8),
+            piet::ImageFormat::Rgba16 => Some(ImageFormat::Rgba16),
+            _ => None,
+        }
+    }
+}
+
+impl From<ImageFormat> for piet::ImageFormat {
+    fn from(format: ImageFormat) -> piet::ImageFormat {
+        match format {
+            ImageFormat::Rgb => piet::ImageFormat::Rgb,
+            ImageFormat::RgbaSeparate => piet::ImageFormat::RgbaSeparate,
+            ImageFormat::RgbaPremul => piet::ImageFormat::RgbaPremul,
+            ImageFormat::BgraPremul => piet::ImageFormat::BgraPremul,
+            ImageFormat::Gray8 => piet::ImageFormat::Gray8,
+            ImageFormat::Alpha8 => piet::ImageFormat::Alpha8,
+            ImageFormat::Rgba16 => piet::ImageFormat::Rgba16,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Color {
+    /// 8-bit components, packed as `0xRRGGBBAA`.
+    Rgba32(u32),
+    RgbaF32([f32; 4], ColorSpace),
+}
+
+impl<'a> From<&'a piet::Color> for Color {
+    fn from(color: &piet::Color) -> Color {
+        match *color {
+            piet::Color::Rgba32(rgba) => Color::Rgba32(rgba),
+            piet::Color::RgbaF32(rgba, space) => Color::RgbaF32(rgba, space.into()),
+        }
+    }
+}
+
+impl<'a> From<&'a Color> for piet::Color {
+    fn from(color: &Color) -> piet::Color {
+        match *color {
+            Color::Rgba32(rgba) => piet::Color::Rgba32(rgba),
+            Color::RgbaF32(rgba, space) => piet::Color::RgbaF32(rgba, space.into()),
+        }
+    }
+}
+
+/// An element of a path, with points as `[x, y]`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PathEl {
+    MoveTo([f64; 2]),
+    LineTo([f64; 2]),
+    QuadTo([f64; 2], [f64; 2]),
+    CurveTo([f64; 2], [f64; 2], [f64; 2]),
+    ClosePath,
+}
+
+pub type Path = Vec<PathEl>;
+
+fn point(p: kurbo::Vec2) -> [f64; 2] {
+    [p.x, p.y]
+}
+
+fn vec2(p: [f64; 2]) -> kurbo::Vec2 {
+    kurbo::Vec2::new(p[0], p[1])
+}
+
+fn path_point(p: kurbo::Point) -> [f64; 2] {
+    [p.x, p.y]
+}
+
+fn kurbo_point(p: [f64; 2]) -> kurbo::Point {
+    kurbo::Point::new(p[0], p[1])
+}
+
+fn rect(r: kurbo::Rect) -> [f64; 4] {
+    [r.x0, r.y0, r.x1, r.y1]
+}
+
+fn kurbo_rect(r: [f64; 4]) -> kurbo::Rect {
+    kurbo::Rect::new(r[0], r[1], r[2], r[3])
+}
+
+fn path(path: &BezPath) -> Path {
+    path.to_bez_path(0.0)
+        .map(|el| match el {
+            kurbo::PathEl::MoveTo(p) => PathEl::MoveTo(path_point(p)),
+            kurbo::PathEl::LineTo(p) => PathEl::LineTo(path_point(p)),
+            kurbo::PathEl::QuadTo(p1, p2) => PathEl::QuadTo(path_point(p1), path_point(p2)),
+            kurbo::PathEl::CurveTo(p1, p2, p3) => {
+                PathEl::CurveTo(path_point(p1), path_point(p2), path_point(p3))
+            }
+            kurbo::PathEl::ClosePath => PathEl::ClosePath,
+        })
+        .collect()
+}
+
+fn bez_path(path: &[PathEl]) -> BezPath {
+    let mut result = BezPath::new();
+    for el in path {
+        result.push(match *el {
+            PathEl::MoveTo(p) => kurbo::PathEl::MoveTo(kurbo_point(p)),
+            PathEl::LineTo(p) => kurbo::PathEl::LineTo(kurbo_point(p)),
+            PathEl::QuadTo(p1, p2) => kurbo::PathEl::QuadTo(kurbo_point(p1), kurbo_point(p2)),
+            PathEl::CurveTo(p1, p2, p3) => {
+                kurbo::PathEl::CurveTo(kurbo_point(p1), kurbo_point(p2), kurbo_point(p3))
+            }
+            PathEl::ClosePath => kurbo::PathEl::ClosePath,
+        });
+    }
+    result
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StrokeStyle {
+    pub line_join: Option<LineJoin>,
+    pub line_cap: Option<LineCap>,
+    /// The dash pattern and offset.
+    pub dash: Option<(Vec<f64>, f64)>,
+    pub miter_limit: Option<f64>,
+}
+
+impl<'a> From<&'a piet::StrokeStyle> for StrokeStyle {
+    fn from(style: &piet::StrokeStyle) -> StrokeStyle {
+        StrokeStyle {
+            line_join: style.line_join.map(Into::into),
+            line_cap: style.line_cap.map(Into::into),
+            dash: style.dash.clone(),
+            miter_limit: style.miter_limit,
+        }
+    }
+}
+
+impl<'a> From<&'a StrokeStyle> for piet::StrokeStyle {
+    fn from(style: &StrokeStyle) -> piet::StrokeStyle {
+        piet::StrokeStyle {
+            line_join: style.line_join.map(Into::into),
+            line_cap: style.line_cap.map(Into::into),
+            dash: style.dash.clone(),
+            miter_limit: style.miter_limit,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub pos: f32,
+    pub color: Color,
+}
+
+fn stops(stops: &[piet::GradientStop]) -> Vec<GradientStop> {
+    stops
+        .iter()
+        .map(|stop| GradientStop {
+            pos: stop.pos,
+            color: (&stop.color).into(),
+        })
+        .collect()
+}
+
+fn piet_stops(stops: &[GradientStop]) -> Vec<piet::GradientStop> {
+    stops
+        .iter()
+        .map(|stop| piet::GradientStop {
+            pos: stop.pos,
+            color: (&stop.color).into(),
+        })
+        .collect()
+}
+
+/// A gradient, with the same fields as the corresponding piet gradient.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Gradient {
+    Linear {
+        start: [f64; 2],
+        end: [f64; 2],
+        stops: Vec<GradientStop>,
+        extend: ExtendMode,
+        transform: Option<[f64; 6]>,
+        units: GradientUnits,
+        interpolation: ColorInterpolation,
+    },
+    Radial {
+        center: [f64; 2],
+        origin_offset: [f64; 2],
+        start_radius: f64,
+        radius: f64,
+        stops: Vec<GradientStop>,
+        extend: ExtendMode,
+        transform: Option<[f64; 6]>,
+        units: GradientUnits,
+        interpolation: ColorInterpolation,
+    },
+    Sweep {
+        center: [f64; 2],
+        start_angle: f64,
+        end_angle: f64,
+        stops: Vec<GradientStop>,
+        extend: ExtendMode,
+        transform: Option<[f64; 6]>,
+        units: GradientUnits,
+        interpolation: ColorInterpolation,
+    },
+}
+
+impl<'a> From<&'a piet::Gradient> for Gradient {
+    fn from(gradient: &piet::Gradient) -> Gradient {
+        match *gradient {
+            piet::Gradient::Linear(ref g) => Gradient::Linear {
+                start: point(g.start),
+                end: point(g.end),
+                stops: stops(&g.stops),
+                extend: g.extend.into(),
+                transform: g.transform.map(|a| a.as_coeffs()),
+                units: g.units.into(),
+                interpolation: g.interpolation.into(),
+            },
+            piet::Gradient::Radial(ref g) => Gradient::Radial {
+                center: point(g.center),
+                origin_offset: point(g.origin_offset),
+                start_radius: g.start_radius,
+                radius: g.radius,
+                stops: stops(&g.stops),
+                extend: g.extend.into(),
+                transform: g.transform.map(|a| a.as_coeffs()),
+                units: g.units.into(),
+                interpolation: g.interpolation.into(),
+            },
+            piet::Gradient::Sweep(ref g) => Gradient::Sweep {
+                center: point(g.center),
+                start_angle: g.start_angle,
+                end_angle: g.end_angle,
+                stops: stops(&g.stops),
+                extend: g.extend.into(),
+                transform: g.transform.map(|a| a.as_coeffs()),
+                units: g.units.into(),
+                interpolation: g.interpolation.into(),
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a Gradient> for piet::Gradient {
+    fn from(gradient: &Gradient) -> piet::Gradient {
+        match *gradient {
+            Gradient::Linear {
+                start,
+                end,
+                ref stops,
+                extend,
+                transform,
+                units,
+                interpolation,
+            } => piet::Gradient::Linear(piet::LinearGradient {
+                start: vec2(start),
+                end: vec2(end),
+                stops: piet_stops(stops),
+                extend: extend.into(),
+                transform: transform.map(kurbo::Affine::new),
+                units: units.into(),
+                interpolation: interpolation.into(),
+            }),
+            Gradient::Radial {
+                center,
+                origin_offset,
+                start_radius,
+                radius,
+                ref stops,
+                extend,
+                transform,
+                units,
+                interpolation,
+            } => piet::Gradient::Radial(piet::RadialGradient {
+                center: vec2(center),
+                origin_offset: vec2(origin_offset),
+                start_radius,
+                radius,
+                stops: piet_stops(stops),
+                extend: extend.into(),
+                transform: transform.map(kurbo::Affine::new),
+                units: units.into(),
+                interpolation: interpolation.into(),
+            }),
+            Gradient::Sweep {
+                center,
+                start_angle,
+                end_angle,
+                ref stops,
+                extend,
+                transform,
+                units,
+                interpolation,
+            } => piet::Gradient::Sweep(piet::SweepGradient {
+                center: vec2(center),
+                start_angle,
+                end_angle,
+                stops: piet_stops(stops),
+                extend: extend.into(),
+                transform: transform.map(kurbo::Affine::new),
+                units: units.into(),
+                interpolation: interpolation.into(),
+            }),
+        }
+    }
+}
+
+/// An embedded image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub format: ImageFormat,
+    /// The pixels, stored as base64 in the text form.
+    #[serde(serialize_with = "serialize_bytes")]
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub data: Vec<u8>,
+}
+
+fn serialize_bytes<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&base64::encode(data))
+    } else {
+        serializer.serialize_bytes(data)
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        let s = String::deserialize(deserializer)?;
+        base64::decode(&s).map_err(serde::de::Error::custom)
+    } else {
+        deserialize_byte_buf(deserializer)
+    }
+}
+
+/// Deserialize a byte buffer, as serialized by `serialize_bytes`.
+fn deserialize_byte_buf<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    struct BytesVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a byte buffer")
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(v)
+        }
+    }
+
+    deserializer.deserialize_byte_buf(BytesVisitor)
+}
+
+/// A brush. Image brushes refer to the image table by index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Brush {
+    Solid(Color),
+    Gradient(Gradient),
+    Image {
+        image: usize,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: [f64; 6],
+        interp: InterpolationMode,
+    },
+}
+
+/// Text, laid out again on replay with the named font.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextLayout {
+    pub font_name: String,
+    pub font_size: f64,
+    pub text: String,
+}
+
+/// A drawing operation, see [`piet::Command`].
+///
+/// [`piet::Command`]: ../piet/enum.Command.html
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    Clear(Color),
+    Stroke {
+        path: Path,
+        brush: usize,
+        width: f64,
+        style: Option<StrokeStyle>,
+    },
+    Fill {
+        path: Path,
+        brush: usize,
+        fill_rule: FillRule,
+    },
+    BlurredRect {
+        rect: [f64; 4],
+        blur_radius: f64,
+        brush: usize,
+    },
+    DrawShadow {
+        path: Path,
+        offset: [f64; 2],
+        blur_radius: f64,
+        color: Color,
+    },
+    Clip {
+        path: Path,
+        fill_rule: FillRule,
+    },
+    Mask {
+        image: usize,
+        rect: [f64; 4],
+        mode: MaskMode,
+    },
+    BeginMask,
+    EndMask(MaskMode),
+    DrawText {
+        layout: TextLayout,
+        pos: [f64; 2],
+        brush: usize,
+    },
+    Save,
+    Restore,
+    PushLayer {
+        opacity: f64,
+        clip: Option<Path>,
+    },
+    PopLayer,
+    Transform([f64; 6]),
+    SetBlendMode(BlendMode),
+    DrawImage {
+        image: usize,
+        rect: [f64; 4],
+        interp: InterpolationMode,
+    },
+}
+
+impl<'a> From<&'a piet::Command> for Command {
+    fn from(command: &piet::Command) -> Command {
+        match *command {
+            piet::Command::Clear(ref color) => Command::Clear(color.into()),
+            piet::Command::Stroke {
+                path: ref p,
+                brush,
+                width,
+                ref style,
+            } => Command::Stroke {
+                path: path(p),
+                brush,
+                width,
+                style: style.as_ref().map(Into::into),
+            },
+            piet::Command::Fill {
+                path: ref p,
+                brush,
+                fill_rule,
+            } => Command::Fill {
+                path: path(p),
+                brush,
+                fill_rule: fill_rule.into(),
+            },
+            piet::Command::BlurredRect {
+                rect: r,
+                blur_radius,
+                brush,
+            } => Command::BlurredRect {
+                rect: rect(r),
+                blur_radius,
+                brush,
+            },
+            piet::Command::DrawShadow {
+                path: ref p,
+                offset,
+                blur_radius,
+                ref color,
+            } => Command::DrawShadow {
+                path: path(p),
+                offset: point(offset),
+                blur_radius,
+                color: color.into(),
+            },
+            piet::Command::Clip {
+                path: ref p,
+                fill_rule,
+            } => Command::Clip {
+                path: path(p),
+                fill_rule: fill_rule.into(),
+            },
+            piet::Command::Mask {
+                image,
+                rect: r,
+                mode,
+            } => Command::Mask {
+                image,
+                rect: rect(r),
+                mode: mode.into(),
+            },
+            piet::Command::BeginMask => Command::BeginMask,
+            piet::Command::EndMask(mode) => Command::EndMask(mode.into()),
+            piet::Command::DrawText {
+                ref layout,
+                pos,
+                brush,
+            } => Command::DrawText {
+                layout: TextLayout {
+                    font_name: layout.font.name.clone(),
+                    font_size: layout.font.size,
+                    text: layout.text.clone(),
+                },
+                pos: point(pos),
+                brush,
+            },
+            piet::Command::Save => Command::Save,
+            piet::Command::Restore => Command::Restore,
+            piet::Command::PushLayer { opacity, ref clip } => Command::PushLayer {
+                opacity,
+                clip: clip.as_ref().map(path),
+            },
+            piet::Command::PopLayer => Command::PopLayer,
+            piet::Command::Transform(transform) => Command::Transform(transform.as_coeffs()),
+            piet::Command::SetBlendMode(mode) => Command::SetBlendMode(mode.into()),
+            piet::Command::DrawImage {
+                image,
+                rect: r,
+                interp,
+            } => Command::DrawImage {
+                image,
+                rect: rect(r),
+                interp: interp.into(),
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a Command> for piet::Command {
+    fn from(command: &Command) -> piet::Command {
+        match *command {
+            Command::Clear(ref color) => piet::Command::Clear(color.into()),
+            Command::Stroke {
+                ref path,
+                brush,
+                width,
+                ref style,
+            } => piet::Command::Stroke {
+                path: bez_path(path),
+                brush,
+                width,
+                style: style.as_ref().map(Into::into),
+            },
+            Command::Fill {
+                ref path,
+                brush,
+                fill_rule,
+            } => piet::Command::Fill {
+                path: bez_path(path),
+                brush,
+                fill_rule: fill_rule.into(),
+            },
+            Command::BlurredRect {
+                rect,
+                blur_radius,
+                brush,
+            } => piet::Command::BlurredRect {
+                rect: kurbo_rect(rect),
+                blur_radius,
+                brush,
+            },
+            Command::DrawShadow {
+                ref path,
+                offset,
+                blur_radius,
+                ref color,
+            } => piet::Command::DrawShadow {
+                path: bez_path(path),
+                offset: vec2(offset),
+                blur_radius,
+                color: color.into(),
+            },
+            Command::Clip {
+                ref path,
+                fill_rule,
+            } => piet::Command::Clip {
+                path: bez_path(path),
+                fill_rule: fill_rule.into(),
+            },
+            Command::Mask { image, rect, mode } => piet::Command::Mask {
+                image,
+                rect: kurbo_rect(rect),
+                mode: mode.into(),
+            },
+            Command::BeginMask => piet::Command::BeginMask,
+            Command::EndMask(mode) => piet::Command::EndMask(mode.into()),
+            Command::DrawText {
+                ref layout,
+                pos,
+                brush,
+            } => piet::Command::DrawText {
+                layout: piet::RecordedTextLayout {
+                    font: piet::RecordedFont {
+                        name: layout.font_name.clone(),
+                        size: layout.font_size,
+                    },
+                    text: layout.text.clone(),
+                },
+                pos: vec2(pos),
+                brush,
+            },
+            Command::Save => piet::Command::Save,
+            Command::Restore => piet::Command::Restore,
+            Command::PushLayer { opacity, ref clip } => piet::Command::PushLayer {
+                opacity,
+                clip: clip.as_ref().map(|clip| bez_path(clip)),
+            },
+            Command::PopLayer => piet::Command::PopLayer,
+            Command::Transform(coeffs) => piet::Command::Transform(kurbo::Affine::new(coeffs)),
+            Command::SetBlendMode(mode) => piet::Command::SetBlendMode(mode.into()),
+            Command::DrawImage {
+                image,
+                rect,
+                interp,
+            } => piet::Command::DrawImage {
+                image,
+                rect: kurbo_rect(rect),
+                interp: interp.into(),
+            },
+        }
+    }
+}
+
+impl Command {
+    /// The indices of the brush and image this command uses, if any.
+    pub fn resources(&self) -> (Option<usize>, Option<usize>) {
+        match *self {
+            Command::Stroke { brush, .. }
+            | Command::Fill { brush, .. }
+            | Command::BlurredRect { brush, .. }
+            | Command::DrawText { brush, .. } => (Some(brush), None),
+            Command::Mask { image, .. } | Command::DrawImage { image, .. } => (None, Some(image)),
+            _ => (None, None),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! A file format for captured piet drawing operations.
+//!
+//! A capture is a display list, recorded with piet's [`RecordingContext`],
+//! together with the size of the target it was drawn on. Images are embedded
+//! and text is stored as the font name, size and string, so a capture can be
+//! attached to a bug report and replayed on another machine.
+//!
+//! There are two forms of the format, which hold the same data:
+//!
+//! * The binary form starts with the 8 bytes of [`MAGIC`], followed by the
+//!   version as a little-endian `u32` and the capture encoded with bincode.
+//! * The text form is a JSON object with a `version` field alongside the
+//!   fields of the capture. Image data is base64-encoded.
+//!
+//! Any change that older readers can't understand bumps [`VERSION`]. Readers
+//! reject captures with a newer version than they know.
+//!
+//! [`RecordingContext`]: ../piet/struct.RecordingContext.html
+//! [`MAGIC`]: constant.MAGIC.html
+//! [`VERSION`]: constant.VERSION.html
+
+use std::fmt;
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use piet::{DisplayList, Error, RecordedBrushData, RecordedImageData};
+
+mod format;
+
+pub use crate::format::*;
+
+/// The first bytes of a capture in binary form.
+pub const MAGIC: &[u8; 8] = b"PIETCAP\0";
+
+/// The version of the format written by this crate.
+pub const VERSION: u32 = 2;
+
+/// A captured sequence of drawing operations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Capture {
+    /// The width of the target, in pixels.
+    pub width: usize,
+    /// The height of the target, in pixels.
+    pub height: usize,
+    /// The scale from user space to pixels.
+    pub pix_scale: f64,
+    pub images: Vec<Image>,
+    pub brushes: Vec<Brush>,
+    pub commands: Vec<Command>,
+}
+
+/// The text form, which carries the version alongside the capture.
+#[derive(Serialize)]
+struct Versioned<'a> {
+    version: u32,
+    #[serde(flatten)]
+    capture: &'a Capture,
+}
+
+/// A problem with the contents of a capture file.
+#[derive(Debug)]
+pub enum FormatError {
+    /// The file doesn't start with the magic bytes or a JSON object.
+    UnknownFormat,
+    /// The file was written by a newer version of the format.
+    UnsupportedVersion(u64),
+    /// A command or brush refers to a resource that doesn't exist.
+    InvalidIndex,
+    /// An image has a format that can't be stored.
+    UnsupportedImageFormat,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::UnknownFormat => write!(f, "Not a piet capture"),
+            FormatError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported capture version {}, expected {}", v, VERSION)
+            }
+            FormatError::InvalidIndex => write!(f, "Invalid resource index"),
+            FormatError::UnsupportedImageFormat => write!(f, "Unsupported image format"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+trait WrapError<T> {
+    fn wrap(self) -> Result<T, Error>;
+}
+
+impl<T, E: std::error::Error + Send + Sync + 'static> WrapError<T> for Result<T, E> {
+    fn wrap(self) -> Result<T, Error> {
+        self.map_err(|e| {
+            let e: Box<dyn std::error::Error + Send + Sync> = Box::new(e);
+            e.into()
+        })
+    }
+}
+
+impl Capture {
+    /// Capture a display list, drawn on a target of the given size.
+    pub fn new(
+        list: &DisplayList,
+        width: usize,
+        height: usize,
+        pix_scale: f64,
+    ) -> Result<Capture, Error> {
+        let images = list
+            .images
+            .iter()
+            .map(|image| {
+                let format = ImageFormat::from_piet(image.format)
+                    .ok_or(FormatError::UnsupportedImageFormat)
+                    .wrap()?;
+                let mut data = image.buf.clone();
+                format.swap_byte_order(&mut data);
+                Ok(Image {
+                    width: image.width,
+                    height: image.height,
+                    format,
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let brushes = list
+            .brushes
+            .iter()
+            .map(|brush| match *brush {
+                RecordedBrushData::Solid(ref color) => Brush::Solid(color.into()),
+                RecordedBrushData::Gradient(ref gradient) => Brush::Gradient(gradient.into()),
+                RecordedBrushData::Image {
+                    image,
+                    extend_x,
+                    extend_y,
+                    transform,
+                    interp,
+                } => Brush::Image {
+                    image,
+                    extend_x: extend_x.into(),
+                    extend_y: extend_y.into(),
+                    transform: transform.as_coeffs(),
+                    interp: interp.into(),
+                },
+            })
+            .collect();
+        let commands = list.commands.iter().map(Into::into).collect();
+        Ok(Capture {
+            width,
+            height,
+            pix_scale,
+            images,
+            brushes,
+            commands,
+        })
+    }
+
+    /// Convert the capture back to a display list, ready for replay.
+    ///
+    /// This checks that all resource indices are valid, so that replaying
+    /// the result can't panic.
+    pub fn to_display_list(&self) -> Result<DisplayList, Error> {
+        let n_images = self.images.len();
+        let n_brushes = self.brushes.len();
+        let brushes_valid = self.brushes.iter().all(|brush| match *brush {
+            Brush::Image { image, .. } => image < n_images,
+            _ => true,
+        });
+        let commands_valid = self.commands.iter().all(|command| {
+            let (brush, image) = command.resources();
+            brush.map(|i| i < n_brushes).unwrap_or(true)
+                && image.map(|i| i < n_images).unwrap_or(true)
+        });
+        if !brushes_valid || !commands_valid {
+            return Err(FormatError::InvalidIndex).wrap();
+        }
+        let images = self
+            .images
+            .iter()
+            .map(|image| {
+                let mut buf = image.data.clone();
+                image.format.swap_byte_order(&mut buf);
+                RecordedImageData {
+                    width: image.width,
+                    height: image.height,
+                    buf,
+                    format: image.format.into(),
+                }
+            })
+            .collect();
+        let brushes = self
+            .brushes
+            .iter()
+            .map(|brush| match *brush {
+                Brush::Solid(ref color) => RecordedBrushData::Solid(color.into()),
+                Brush::Gradient(ref gradient) => RecordedBrushData::Gradient(gradient.into()),
+                Brush::Image {
+                    image,
+                    extend_x,
+                    extend_y,
+                    transform,
+                    interp,
+                } => RecordedBrushData::Image {
+                    image,
+                    extend_x: extend_x.into(),
+                    extend_y: extend_y.into(),
+                    transform: piet::kurbo::Affine::new(transform),
+                    interp: interp.into(),
+                },
+            })
+            .collect();
+        let commands = self.commands.iter().map(Into::into).collect();
+        Ok(DisplayList {
+            images,
+            brushes,
+            commands,
+        })
+    }
+
+    /// Write the capture in binary form.
+    pub fn write_binary(&self, mut writer: impl Write) -> Result<(), Error> {
+        writer.write_all(MAGIC).wrap()?;
+        writer.write_all(&VERSION.to_le_bytes()).wrap()?;
+        bincode::serialize_into(writer, self).wrap()
+    }
+
+    /// Write the capture in text form.
+    pub fn write_json(&self, writer: impl Write) -> Result<(), Error> {
+        let versioned = Versioned {
+            version: VERSION,
+            capture: self,
+        };
+        serde_json::to_writer_pretty(writer, &versioned).wrap()
+    }
+
+    /// Read a capture in either form.
+    pub fn read(mut reader: impl Read) -> Result<Capture, Error> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).wrap()?;
+        if buf.starts_with(MAGIC) {
+            let rest = &buf[MAGIC.len()..];
+            if rest.len() < 4 {
+                return Err(FormatError::UnknownFormat).wrap();
+            }
+            let mut version = [0; 4];
+            version.copy_from_slice(&rest[..4]);
+            check_version(u32::from_le_bytes(version).into())?;
+            bincode::deserialize(&rest[4..]).wrap()
+        } else if buf.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            let value: serde_json::Value = serde_json::from_slice(&buf).wrap()?;
+            let version = value
+                .get("version")
+                .and_then(|v| v.as_u64())
+                .ok_or(FormatError::UnknownFormat)
+                .wrap()?;
+            check_version(version)?;
+            serde_json::from_value(value).wrap()
+        } else {
+            Err(FormatError::UnknownFormat).wrap()
+        }
+    }
+}
+
+fn check_version(version: u64) -> Result<(), Error> {
+    if version == 0 || version > VERSION.into() {
+        return Err(FormatError::UnsupportedVersion(version)).wrap();
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+//! Render a piet capture file to PNG.
+//!
+//! The backend is the one named by the `PIET_BACKEND` environment variable,
+//! among those compiled in with the `cairo`, `d2d` and `raqote` features of
+//! this crate. If it isn't set, it is the one piet-common selects.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+use piet::{replay, ImageFormat, RenderContext};
+use piet_common::AnyDevice;
+use piet_replay::Capture;
+
+const USAGE: &str = "usage: piet-replay <capture> <output>
+
+Renders the capture to <output> as PNG. If <output> ends in .json or .pietcap,
+the capture is converted to the text or binary form instead.";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    if let Err(e) = run(&args[0], &args[1]) {
+        eprintln!("piet-replay: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(input: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let capture = Capture::read(BufReader::new(File::open(input)?))?;
+    if output.ends_with(".json") {
+        capture.write_json(BufWriter::new(File::create(output)?))?;
+    } else if output.ends_with(".pietcap") {
+        capture.write_binary(BufWriter::new(File::create(output)?))?;
+    } else {
+        render(&capture, output)?;
+    }
+    Ok(())
+}
+
+fn render(capture: &Capture, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let list = capture.to_display_list()?;
+    let device = AnyDevice::new()?;
+    let mut bitmap = device.bitmap_target(capture.width, capture.height, capture.pix_scale)?;
+    let mut rc = bitmap.render_context();
+    // Commands the backend can't draw are skipped, which is worth knowing
+    // about but shouldn't stop us from looking at the rest.
+    if let Err(e) = replay(&list, &mut rc) {
+        eprintln!("piet-replay: warning: {}", e);
+    }
+    rc.finish()?;
+    std::mem::drop(rc);
+    let raw_pixels = bitmap.into_raw_pixels(ImageFormat::RgbaSeparate)?;
+    image::save_buffer(
+        output,
+        &raw_pixels,
+        capture.width as u32,
+        capture.height as u32,
+        image::ColorType::RGBA(8),
+    )?;
+    Ok(())
+}
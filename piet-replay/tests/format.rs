@@ -0,0 +1,90 @@
+//! Check that captures survive both forms of the file format.
+
+use piet::{RecordingContext, RenderContext};
+use piet_replay::{Brush, Capture, ExtendMode, InterpolationMode, MAGIC, VERSION};
+
+use piet_test::draw_test_picture;
+
+/// The number of test pictures in piet-test.
+const N_PICTURES: usize = 16;
+
+fn capture(number: usize) -> Capture {
+    let mut rc = RecordingContext::new();
+    draw_test_picture(&mut rc, number).unwrap();
+    rc.finish().unwrap();
+    let list = rc.into_display_list();
+    Capture::new(&list, 400, 200, 2.0).unwrap()
+}
+
+#[test]
+fn binary_round_trip() {
+    for number in 0..N_PICTURES {
+        let capture = capture(number);
+        let mut buf = Vec::new();
+        capture.write_binary(&mut buf).unwrap();
+        assert!(buf.starts_with(MAGIC));
+        assert_eq!(
+            Capture::read(&buf[..]).unwrap(),
+            capture,
+            "picture {}",
+            number
+        );
+    }
+}
+
+#[test]
+fn json_round_trip() {
+    for number in 0..N_PICTURES {
+        let capture = capture(number);
+        let mut buf = Vec::new();
+        capture.write_json(&mut buf).unwrap();
+        assert_eq!(
+            Capture::read(&buf[..]).unwrap(),
+            capture,
+            "picture {}",
+            number
+        );
+    }
+}
+
+#[test]
+fn newer_version_rejected() {
+    let capture = capture(0);
+
+    let mut binary = Vec::new();
+    capture.write_binary(&mut binary).unwrap();
+    binary[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert!(Capture::read(&binary[..]).is_err());
+
+    let mut json = Vec::new();
+    capture.write_json(&mut json).unwrap();
+    let mut value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    value["version"] = (VERSION + 1).into();
+    let json = serde_json::to_vec(&value).unwrap();
+    assert!(Capture::read(&json[..]).is_err());
+}
+
+#[test]
+fn invalid_brush_index() {
+    let mut capture = capture(0);
+    assert!(capture.to_display_list().is_ok());
+    capture.brushes.clear();
+    assert!(capture.to_display_list().is_err());
+
+    // A brush that refers to a missing image is caught too.
+    let mut capture = Capture {
+        images: Vec::new(),
+        brushes: Vec::new(),
+        commands: Vec::new(),
+        ..capture
+    };
+    assert!(capture.to_display_list().is_ok());
+    capture.brushes.push(Brush::Image {
+        image: 0,
+        extend_x: ExtendMode::Pad,
+        extend_y: ExtendMode::Pad,
+        transform: [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        interp: InterpolationMode::Bilinear,
+    });
+    assert!(capture.to_display_list().is_err());
+}
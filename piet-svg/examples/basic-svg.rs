@@ -0,0 +1,24 @@
+//! Basic example of rendering to SVG.
+
+use std::fs::File;
+
+use piet::RenderContext;
+use piet_svg::SvgRenderContext;
+
+use piet_test::draw_test_picture;
+
+const WIDTH: f64 = 400.0;
+const HEIGHT: f64 = 200.0;
+
+fn main() {
+    let test_picture_number = std::env::args()
+        .nth(1)
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut piet_context = SvgRenderContext::new(WIDTH, HEIGHT);
+    draw_test_picture(&mut piet_context, test_picture_number).unwrap();
+    piet_context.finish().unwrap();
+    let file = File::create("temp-svg.svg").expect("Couldn't create 'temp-svg.svg'");
+    piet_context.write(file).expect("Error writing SVG file");
+}
@@ -0,0 +1,932 @@
+//! The SVG backend for the Piet 2D graphics abstraction.
+//!
+//! Drawing operations are written out as elements of an SVG document, which
+//! can be saved with [`write`](struct.SvgRenderContext.html#method.write).
+//! Clips, masks and layers become groups, closed when the state is restored,
+//! and resources such as gradients and images go into `<defs>`.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use font_kit::family_name::FamilyName;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+
+use image::png::PNGEncoder;
+use image::ColorType;
+
+use piet::kurbo::{Affine, PathEl, Rect, Shape, Vec2};
+
+use piet::{
+    blur_extent, expand_gradient_stops, new_error, BlendMode, Color, ColorInterpolation,
+    DocumentTarget, Error, ErrorKind, ExtendMode, FillRule, Font, FontBuilder, Gradient,
+    GradientUnits, ImageFormat, InterpolationMode, LineCap, LineJoin, MaskMode, RenderContext,
+    RoundInto, StrokeStyle, Text, TextLayout, TextLayoutBuilder,
+};
+
+pub struct SvgRenderContext {
+    width: f64,
+    height: f64,
+    text: SvgText,
+    /// Definitions of gradients, patterns, filters, clip paths, masks and images.
+    defs: String,
+    /// The elements drawn so far, or the content of a mask while one is being
+    /// drawn.
+    body: String,
+    /// The bodies suspended by `begin_mask`.
+    bodies: Vec<String>,
+    state: State,
+    /// The saved states.
+    stack: Vec<(StateKind, State)>,
+    next_id: usize,
+    err: Result<(), Error>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum StateKind {
+    Save,
+    Layer,
+    MaskContent,
+}
+
+#[derive(Clone)]
+struct State {
+    transform: Affine,
+    blend_mode: BlendMode,
+    /// The number of groups opened by clips and masks in this state.
+    ///
+    /// Groups never have a transform, so the user space of the definitions
+    /// they refer to is the root coordinate system.
+    groups: usize,
+}
+
+#[derive(Clone)]
+pub enum Brush {
+    Solid(Color),
+    /// A gradient or pattern, by id.
+    Paint(String),
+}
+
+pub struct SvgImage {
+    id: String,
+    width: usize,
+    height: usize,
+}
+
+/// Text is laid out with system fonts, which are only used for measurement.
+pub struct SvgText;
+
+pub struct SvgFontBuilder {
+    family: String,
+    size: f64,
+}
+
+pub struct SvgFont {
+    family: String,
+    size: f64,
+    font: font_kit::font::Font,
+}
+
+pub struct SvgTextLayoutBuilder(SvgTextLayout);
+
+pub struct SvgTextLayout {
+    family: String,
+    size: f64,
+    text: String,
+    width: f64,
+}
+
+#[derive(Debug)]
+struct WrappedIoError(io::Error);
+
+impl fmt::Display for WrappedIoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SVG image encoding error: {}", self.0)
+    }
+}
+
+impl std::error::Error for WrappedIoError {}
+
+/// The tolerance for converting shapes to paths.
+const TOLERANCE: f64 = 1e-3;
+
+impl SvgRenderContext {
+    /// Create a context for a document of the given size.
+    ///
+    /// User space is initially the document's coordinate system, in which
+    /// one unit is one CSS pixel.
+    pub fn new(width: f64, height: f64) -> SvgRenderContext {
+        SvgRenderContext {
+            width,
+            height,
+            text: SvgText,
+            defs: String::new(),
+            body: String::new(),
+            bodies: Vec::new(),
+            state: State {
+                transform: Affine::scale(1.0),
+                blend_mode: BlendMode::SrcOver,
+                groups: 0,
+            },
+            stack: Vec::new(),
+            next_id: 0,
+            err: Ok(()),
+        }
+    }
+
+    /// Write the SVG document.
+    ///
+    /// Call [`finish`](#method.finish) first, as unbalanced saves leave the
+    /// document incomplete.
+    pub fn write(&self, mut writer: impl io::Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+             width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+            self.width, self.height
+        )?;
+        if !self.defs.is_empty() {
+            writeln!(writer, "<defs>\n{}</defs>", self.defs)?;
+        }
+        writer.write_all(self.body.as_bytes())?;
+        for _ in 0..self.state.groups {
+            writeln!(writer, "</g>")?;
+        }
+        writeln!(writer, "</svg>")
+    }
+
+    fn new_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    /// The attributes for the current transform, times `transform`, and blend mode.
+    fn common_attrs(&self, transform: Affine) -> String {
+        let mut attrs = transform_attr("transform", self.state.transform * transform);
+        if let Some(mode) = blend_mode_name(self.state.blend_mode) {
+            attrs.push_str(&format!(" style=\"mix-blend-mode:{}\"", mode));
+        }
+        attrs
+    }
+
+    /// Define a clip path, in the current user space.
+    fn clip_path(&mut self, shape: impl Shape, fill_rule: FillRule) -> String {
+        let id = self.new_id("clip");
+        self.defs.push_str(&format!(
+            "<clipPath id=\"{}\" clipPathUnits=\"userSpaceOnUse\">\
+             <path d=\"{}\"{}{}/></clipPath>\n",
+            id,
+            path_data(shape),
+            fill_rule_attr("clip-rule", fill_rule),
+            transform_attr("transform", self.state.transform),
+        ));
+        id
+    }
+
+    /// Open a group masked by `content`, in root coordinates.
+    fn push_mask(&mut self, content: &str, mode: MaskMode) {
+        let id = self.new_id("mask");
+        let mask_type = match mode {
+            MaskMode::Alpha => " style=\"mask-type:alpha\"",
+            MaskMode::Luminance => "",
+        };
+        self.defs.push_str(&format!(
+            "<mask id=\"{}\" maskUnits=\"userSpaceOnUse\" maskContentUnits=\"userSpaceOnUse\" \
+             x=\"0\" y=\"0\" width=\"{}\" height=\"{}\"{}>\n{}</mask>\n",
+            id, self.width, self.height, mask_type, content
+        ));
+        self.body.push_str(&format!("<g mask=\"url(#{})\">\n", id));
+        self.state.groups += 1;
+    }
+
+    /// Define a blur filter for content within `bounds`, in the user space
+    /// of the element using it.
+    fn blur_filter(&mut self, bounds: Rect, blur_radius: f64) -> String {
+        if blur_radius <= 0.0 {
+            return String::new();
+        }
+        let id = self.new_id("blur");
+        let extent = blur_extent(blur_radius);
+        let (x0, x1) = (bounds.x0.min(bounds.x1), bounds.x0.max(bounds.x1));
+        let (y0, y1) = (bounds.y0.min(bounds.y1), bounds.y0.max(bounds.y1));
+        self.defs.push_str(&format!(
+            "<filter id=\"{}\" filterUnits=\"userSpaceOnUse\" \
+             x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\
+             <feGaussianBlur stdDeviation=\"{}\"/></filter>\n",
+            id,
+            x0 - extent,
+            y0 - extent,
+            x1 - x0 + 2.0 * extent,
+            y1 - y0 + 2.0 * extent,
+            blur_radius
+        ));
+        format!(" filter=\"url(#{})\"", id)
+    }
+
+    fn save_state(&mut self, kind: StateKind) {
+        self.stack.push((kind, self.state.clone()));
+        self.state.groups = 0;
+    }
+
+    /// Restore a saved state, which must be of the given kind.
+    fn restore_state(&mut self, kind: StateKind) -> Result<(), Error> {
+        match self.stack.last() {
+            Some(&(k, _)) if k == kind => (),
+            _ => return Err(new_error(ErrorKind::StackUnbalance)),
+        }
+        for _ in 0..self.state.groups {
+            self.body.push_str("</g>\n");
+        }
+        self.state = self.stack.pop().unwrap().1;
+        Ok(())
+    }
+}
+
+impl RenderContext for SvgRenderContext {
+    type Point = Vec2;
+    type Coord = f64;
+    type Brush = Brush;
+    type Text = SvgText;
+    type TextLayout = SvgTextLayout;
+    type Image = SvgImage;
+
+    fn status(&mut self) -> Result<(), Error> {
+        std::mem::replace(&mut self.err, Ok(()))
+    }
+
+    fn clear(&mut self, color: Color) {
+        // Like the other backends, this ignores the transform but not clips.
+        self.body.push_str(&format!(
+            "<rect width=\"{}\" height=\"{}\"{}/>\n",
+            self.width,
+            self.height,
+            color_attrs("fill", "fill-opacity", &color.with_alpha(1.0))
+        ));
+    }
+
+    fn solid_brush(&mut self, color: Color) -> Brush {
+        Brush::Solid(color)
+    }
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<Brush, Error> {
+        if gradient.stops().is_empty() {
+            return Err(new_error(ErrorKind::InvalidGradient));
+        }
+        let (element, geometry, extend, transform, interpolation) = match gradient {
+            Gradient::Linear(ref linear) => {
+                let geometry = format!(
+                    " x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"",
+                    linear.start.x, linear.start.y, linear.end.x, linear.end.y
+                );
+                let (extend, interpolation) = (linear.extend, linear.interpolation);
+                (
+                    "linearGradient",
+                    geometry,
+                    extend,
+                    linear.transform,
+                    interpolation,
+                )
+            }
+            Gradient::Radial(ref radial) => {
+                let focus = radial.center + radial.origin_offset;
+                let mut geometry = format!(
+                    " cx=\"{}\" cy=\"{}\" r=\"{}\" fx=\"{}\" fy=\"{}\"",
+                    radial.center.x, radial.center.y, radial.radius, focus.x, focus.y
+                );
+                if radial.start_radius != 0.0 {
+                    geometry.push_str(&format!(" fr=\"{}\"", radial.start_radius));
+                }
+                let (extend, interpolation) = (radial.extend, radial.interpolation);
+                (
+                    "radialGradient",
+                    geometry,
+                    extend,
+                    radial.transform,
+                    interpolation,
+                )
+            }
+            // SVG has no sweep gradients.
+            Gradient::Sweep(_) => return Err(new_error(ErrorKind::NotSupported)),
+        };
+        let id = self.new_id("gradient");
+        let units = match gradient.units() {
+            GradientUnits::UserSpace => "userSpaceOnUse",
+            GradientUnits::ObjectBoundingBox => "objectBoundingBox",
+        };
+        let spread = match extend {
+            ExtendMode::Pad => "pad",
+            ExtendMode::Repeat => "repeat",
+            ExtendMode::Reflect => "reflect",
+        };
+        let transform = transform
+            .map(|t| transform_attr("gradientTransform", t))
+            .unwrap_or_default();
+        let mut def = format!(
+            "<{} id=\"{}\"{} gradientUnits=\"{}\" spreadMethod=\"{}\"{}>\n",
+            element, id, geometry, units, spread, transform
+        );
+        // SVG interpolates in sRGB with separate alpha.
+        let native = ColorInterpolation::UnpremultipliedSrgb;
+        for stop in expand_gradient_stops(gradient.stops(), interpolation, native) {
+            def.push_str(&format!(
+                "<stop offset=\"{}\"{}/>\n",
+                stop.pos,
+                color_attrs("stop-color", "stop-opacity", &stop.color)
+            ));
+        }
+        def.push_str(&format!("</{}>\n", element));
+        self.defs.push_str(&def);
+        Ok(Brush::Paint(id))
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &SvgImage,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<Brush, Error> {
+        // Patterns always repeat.
+        if extend_x != ExtendMode::Repeat || extend_y != ExtendMode::Repeat {
+            return Err(new_error(ErrorKind::NotSupported));
+        }
+        let id = self.new_id("pattern");
+        self.defs.push_str(&format!(
+            "<pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" height=\"{}\"{}>\
+             <use xlink:href=\"#{}\"{}/></pattern>\n",
+            id,
+            image.width,
+            image.height,
+            transform_attr("patternTransform", transform),
+            image.id,
+            interp_attr(interp)
+        ));
+        Ok(Brush::Paint(id))
+    }
+
+    fn stroke(
+        &mut self,
+        shape: impl Shape,
+        brush: &Brush,
+        width: impl RoundInto<f64>,
+        style: Option<&StrokeStyle>,
+    ) {
+        let width: f64 = width.round_into();
+        self.body.push_str(&format!(
+            "<path d=\"{}\" fill=\"none\"{} stroke-width=\"{}\"{}{}/>\n",
+            path_data(shape),
+            paint_attrs("stroke", brush),
+            width,
+            style.map(stroke_style_attrs).unwrap_or_default(),
+            self.common_attrs(Affine::scale(1.0))
+        ));
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &Brush, fill_rule: FillRule) {
+        self.body.push_str(&format!(
+            "<path d=\"{}\"{}{}{}/>\n",
+            path_data(shape),
+            paint_attrs("fill", brush),
+            fill_rule_attr("fill-rule", fill_rule),
+            self.common_attrs(Affine::scale(1.0))
+        ));
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &Brush) {
+        let filter = self.blur_filter(rect, blur_radius);
+        self.body.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{}{}{}/>\n",
+            rect.x0.min(rect.x1),
+            rect.y0.min(rect.y1),
+            rect.width().abs(),
+            rect.height().abs(),
+            paint_attrs("fill", brush),
+            filter,
+            self.common_attrs(Affine::scale(1.0))
+        ));
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        let filter = self.blur_filter(shape.bounding_box(), blur_radius);
+        self.body.push_str(&format!(
+            "<path d=\"{}\"{}{}{}/>\n",
+            path_data(shape),
+            color_attrs("fill", "fill-opacity", &color),
+            filter,
+            self.common_attrs(Affine::translate(offset))
+        ));
+    }
+
+    fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
+        let id = self.clip_path(shape, fill_rule);
+        self.body
+            .push_str(&format!("<g clip-path=\"url(#{})\">\n", id));
+        self.state.groups += 1;
+    }
+
+    fn mask(&mut self, mask: &SvgImage, rect: impl Into<Rect>, mode: MaskMode) {
+        let placement = self.state.transform * image_placement(mask, rect.into());
+        let content = format!(
+            "<use xlink:href=\"#{}\"{}/>\n",
+            mask.id,
+            transform_attr("transform", placement)
+        );
+        self.push_mask(&content, mode);
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        self.save_state(StateKind::MaskContent);
+        let body = std::mem::take(&mut self.body);
+        self.bodies.push(body);
+        Ok(())
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        self.restore_state(StateKind::MaskContent)?;
+        let body = self.bodies.pop().unwrap();
+        let content = std::mem::replace(&mut self.body, body);
+        self.push_mask(&content, mode);
+        Ok(())
+    }
+
+    fn text(&mut self) -> &mut SvgText {
+        &mut self.text
+    }
+
+    fn draw_text(&mut self, layout: &SvgTextLayout, pos: impl RoundInto<Vec2>, brush: &Brush) {
+        let pos: Vec2 = pos.round_into();
+        // The text length makes the rendered width match the layout, even if
+        // the viewer substitutes a different font.
+        let length = if layout.width > 0.0 {
+            format!(
+                " textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\"",
+                layout.width
+            )
+        } else {
+            String::new()
+        };
+        self.body.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{}\"{}{}{} \
+             xml:space=\"preserve\">{}</text>\n",
+            pos.x,
+            pos.y,
+            escape(&layout.family),
+            layout.size,
+            length,
+            paint_attrs("fill", brush),
+            self.common_attrs(Affine::scale(1.0)),
+            escape(&layout.text)
+        ));
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.save_state(StateKind::Save);
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        self.restore_state(StateKind::Save)
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        let clip = clip
+            .map(|shape| {
+                let id = self.clip_path(shape, FillRule::NonZero);
+                format!(" clip-path=\"url(#{})\"", id)
+            })
+            .unwrap_or_default();
+        self.save_state(StateKind::Layer);
+        self.body.push_str(&format!(
+            "<g opacity=\"{}\" style=\"isolation:isolate\"{}>\n",
+            opacity, clip
+        ));
+        self.state.groups = 1;
+        Ok(())
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        self.restore_state(StateKind::Layer)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if !self.stack.is_empty() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.status()
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        self.state.transform = self.state.transform * transform;
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        if mode != BlendMode::SrcOver && blend_mode_name(mode).is_none() {
+            // SVG only has the CSS blend modes, not the Porter-Duff operators.
+            self.err = Err(new_error(ErrorKind::NotSupported));
+            return;
+        }
+        self.state.blend_mode = mode;
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<SvgImage, Error> {
+        let (pixels, color_type) = match format {
+            ImageFormat::Rgb
+            | ImageFormat::RgbaSeparate
+            | ImageFormat::RgbaPremul
+            | ImageFormat::BgraPremul
+            | ImageFormat::Gray8
+            | ImageFormat::Alpha8
+            | ImageFormat::Rgba16 => {
+                format.check_buffer(width, height, buf)?;
+                match format {
+                    ImageFormat::Rgb => (buf.to_vec(), ColorType::RGB(8)),
+                    ImageFormat::RgbaSeparate => (buf.to_vec(), ColorType::RGBA(8)),
+                    ImageFormat::BgraPremul => {
+                        let mut rgba = unpremultiply(buf);
+                        for pixel in rgba.chunks_mut(4) {
+                            pixel.swap(0, 2);
+                        }
+                        (rgba, ColorType::RGBA(8))
+                    }
+                    ImageFormat::Gray8 => (buf.to_vec(), ColorType::Gray(8)),
+                    ImageFormat::Alpha8 => {
+                        let mut rgba = Vec::with_capacity(buf.len() * 4);
+                        for &a in buf {
+                            rgba.extend_from_slice(&[0, 0, 0, a]);
+                        }
+                        (rgba, ColorType::RGBA(8))
+                    }
+                    // PNG stores 16 bit samples big endian.
+                    ImageFormat::Rgba16 => {
+                        let mut rgba = Vec::with_capacity(buf.len());
+                        for c in buf.chunks(2) {
+                            let sample = u16::from_ne_bytes([c[0], c[1]]);
+                            rgba.extend_from_slice(&sample.to_be_bytes());
+                        }
+                        (rgba, ColorType::RGBA(16))
+                    }
+                    _ => (unpremultiply(buf), ColorType::RGBA(8)),
+                }
+            }
+            _ => return Err(new_error(ErrorKind::NotSupported)),
+        };
+        let mut png = Vec::new();
+        PNGEncoder::new(&mut png)
+            .encode(&pixels, width as u32, height as u32, color_type)
+            .map_err(|e| {
+                let e: Box<dyn std::error::Error + Send + Sync> = Box::new(WrappedIoError(e));
+                Error::from(e)
+            })?;
+        let id = self.new_id("image");
+        self.defs.push_str(&format!(
+            "<image id=\"{}\" width=\"{}\" height=\"{}\" \
+             xlink:href=\"data:image/png;base64,{}\"/>\n",
+            id,
+            width,
+            height,
+            base64::encode(&png)
+        ));
+        Ok(SvgImage { id, width, height })
+    }
+
+    fn draw_image(&mut self, image: &SvgImage, rect: impl Into<Rect>, interp: InterpolationMode) {
+        let placement = image_placement(image, rect.into());
+        self.body.push_str(&format!(
+            "<use xlink:href=\"#{}\"{}{}/>\n",
+            image.id,
+            interp_attr(interp),
+            self.common_attrs(placement)
+        ));
+    }
+}
+
+/// A document with one SVG file per page.
+///
+/// Page sizes are in CSS pixels.
+pub struct SvgDocument {
+    prefix: PathBuf,
+    /// The number of pages begun so far.
+    pages: usize,
+    page: Option<SvgRenderContext>,
+}
+
+impl SvgDocument {
+    /// Create a new document.
+    ///
+    /// Page `n`, counting from 1, is saved to `prefix` followed by `-n.svg`.
+    pub fn new(prefix: impl Into<PathBuf>) -> SvgDocument {
+        SvgDocument {
+            prefix: prefix.into(),
+            pages: 0,
+            page: None,
+        }
+    }
+}
+
+impl DocumentTarget for SvgDocument {
+    type Context<'a> = &'a mut SvgRenderContext;
+
+    fn begin_page(&mut self, size: Vec2) -> Result<&mut SvgRenderContext, Error> {
+        if self.page.is_some() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.pages += 1;
+        Ok(self
+            .page
+            .get_or_insert(SvgRenderContext::new(size.x, size.y)))
+    }
+
+    fn end_page(&mut self) -> Result<(), Error> {
+        let mut page = self
+            .page
+            .take()
+            .ok_or_else(|| new_error(ErrorKind::StackUnbalance))?;
+        page.finish()?;
+        let mut path = OsString::from(self.prefix.as_os_str());
+        path.push(format!("-{}.svg", self.pages));
+        File::create(path)
+            .and_then(|file| page.write(BufWriter::new(file)))
+            .map_err(|e| {
+                let e: Box<dyn std::error::Error + Send + Sync> = Box::new(e);
+                e.into()
+            })
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.page.is_some() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        Ok(())
+    }
+}
+
+/// The transform from image space, in which each pixel is a unit square,
+/// to `rect`.
+fn image_placement(image: &SvgImage, rect: Rect) -> Affine {
+    Affine::new([
+        rect.width() / image.width as f64,
+        0.0,
+        0.0,
+        rect.height() / image.height as f64,
+        rect.x0,
+        rect.y0,
+    ])
+}
+
+fn unpremultiply(buf: &[u8]) -> Vec<u8> {
+    let mut result = buf.to_vec();
+    for pixel in result.chunks_mut(4) {
+        let a = pixel[3] as u32;
+        if a != 0 {
+            for c in &mut pixel[..3] {
+                *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+            }
+        }
+    }
+    result
+}
+
+fn path_data(shape: impl Shape) -> String {
+    let mut d = String::new();
+    for el in shape.to_bez_path(TOLERANCE) {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        match el {
+            PathEl::MoveTo(p) => d.push_str(&format!("M{} {}", p.x, p.y)),
+            PathEl::LineTo(p) => d.push_str(&format!("L{} {}", p.x, p.y)),
+            PathEl::QuadTo(p1, p2) => d.push_str(&format!("Q{} {} {} {}", p1.x, p1.y, p2.x, p2.y)),
+            PathEl::CurveTo(p1, p2, p3) => d.push_str(&format!(
+                "C{} {} {} {} {} {}",
+                p1.x, p1.y, p2.x, p2.y, p3.x, p3.y
+            )),
+            PathEl::ClosePath => d.push('Z'),
+        }
+    }
+    d
+}
+
+/// A transform attribute, or nothing for the identity.
+fn transform_attr(name: &str, transform: Affine) -> String {
+    let c = transform.as_coeffs();
+    if c == [1.0, 0.0, 0.0, 1.0, 0.0, 0.0] {
+        return String::new();
+    }
+    format!(
+        " {}=\"matrix({} {} {} {} {} {})\"",
+        name, c[0], c[1], c[2], c[3], c[4], c[5]
+    )
+}
+
+fn fill_rule_attr(name: &str, fill_rule: FillRule) -> String {
+    match fill_rule {
+        FillRule::NonZero => String::new(),
+        FillRule::EvenOdd => format!(" {}=\"evenodd\"", name),
+    }
+}
+
+fn interp_attr(interp: InterpolationMode) -> &'static str {
+    match interp {
+        InterpolationMode::NearestNeighbor => " image-rendering=\"pixelated\"",
+        InterpolationMode::Bilinear => "",
+    }
+}
+
+/// The attributes for a color, with the opacity omitted when opaque.
+fn color_attrs(name: &str, opacity_name: &str, color: &Color) -> String {
+    let (r, g, b, a) = color.as_rgba();
+    let byte = |x: f64| (x * 255.0).round() as u8;
+    let mut attrs = format!(
+        " {}=\"#{:02x}{:02x}{:02x}\"",
+        name,
+        byte(r),
+        byte(g),
+        byte(b)
+    );
+    if a < 1.0 {
+        attrs.push_str(&format!(" {}=\"{}\"", opacity_name, a));
+    }
+    attrs
+}
+
+/// The attributes for painting `brush` as the fill or stroke.
+fn paint_attrs(name: &str, brush: &Brush) -> String {
+    match *brush {
+        Brush::Solid(ref color) => color_attrs(name, &format!("{}-opacity", name), color),
+        Brush::Paint(ref id) => format!(" {}=\"url(#{})\"", name, id),
+    }
+}
+
+fn stroke_style_attrs(style: &StrokeStyle) -> String {
+    let mut attrs = String::new();
+    if let Some(line_join) = style.line_join {
+        let value = match line_join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        };
+        attrs.push_str(&format!(" stroke-linejoin=\"{}\"", value));
+    }
+    if let Some(line_cap) = style.line_cap {
+        let value = match line_cap {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        };
+        attrs.push_str(&format!(" stroke-linecap=\"{}\"", value));
+    }
+    if let Some((ref dashes, offset)) = style.dash {
+        let dashes = dashes
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        attrs.push_str(&format!(
+            " stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\"",
+            dashes, offset
+        ));
+    }
+    if let Some(miter_limit) = style.miter_limit {
+        attrs.push_str(&format!(" stroke-miterlimit=\"{}\"", miter_limit));
+    }
+    attrs
+}
+
+/// The CSS name of a blend mode, for `mix-blend-mode`.
+fn blend_mode_name(mode: BlendMode) -> Option<&'static str> {
+    match mode {
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+        BlendMode::Overlay => Some("overlay"),
+        BlendMode::Darken => Some("darken"),
+        BlendMode::Lighten => Some("lighten"),
+        BlendMode::ColorDodge => Some("color-dodge"),
+        BlendMode::ColorBurn => Some("color-burn"),
+        BlendMode::HardLight => Some("hard-light"),
+        BlendMode::SoftLight => Some("soft-light"),
+        BlendMode::Difference => Some("difference"),
+        BlendMode::Exclusion => Some("exclusion"),
+        BlendMode::Hue => Some("hue"),
+        BlendMode::Saturation => Some("saturation"),
+        BlendMode::Color => Some("color"),
+        BlendMode::Luminosity => Some("luminosity"),
+        _ => None,
+    }
+}
+
+/// Escape text for use in XML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+impl Text for SvgText {
+    type Coord = f64;
+
+    type Font = SvgFont;
+    type FontBuilder = SvgFontBuilder;
+    type TextLayout = SvgTextLayout;
+    type TextLayoutBuilder = SvgTextLayoutBuilder;
+
+    fn new_font_by_name(
+        &mut self,
+        name: &str,
+        size: impl RoundInto<f64>,
+    ) -> Result<SvgFontBuilder, Error> {
+        Ok(SvgFontBuilder {
+            family: name.to_owned(),
+            size: size.round_into(),
+        })
+    }
+
+    fn new_text_layout(
+        &mut self,
+        font: &SvgFont,
+        text: &str,
+    ) -> Result<SvgTextLayoutBuilder, Error> {
+        let width = measure(font, text);
+        Ok(SvgTextLayoutBuilder(SvgTextLayout {
+            family: font.family.clone(),
+            size: font.size,
+            text: text.to_owned(),
+            width,
+        }))
+    }
+}
+
+/// The width of text, as the sum of the advances of its glyphs.
+///
+/// There is no shaping or kerning, so this is only close for simple scripts.
+fn measure(font: &SvgFont, text: &str) -> f64 {
+    let units_per_em = font.font.metrics().units_per_em as f64;
+    let advance: f64 = text
+        .chars()
+        .filter_map(|c| font.font.glyph_for_char(c))
+        .filter_map(|glyph| font.font.advance(glyph).ok())
+        .map(|advance| advance.x as f64)
+        .sum();
+    advance * font.size / units_per_em
+}
+
+impl FontBuilder for SvgFontBuilder {
+    type Out = SvgFont;
+
+    fn build(self) -> Result<SvgFont, Error> {
+        let missing = || new_error(ErrorKind::MissingFont(self.family.clone()));
+        let font = SystemSource::new()
+            .select_best_match(
+                &[
+                    FamilyName::Title(self.family.clone()),
+                    FamilyName::SansSerif,
+                ],
+                &Properties::new(),
+            )
+            .map_err(|_| missing())?
+            .load()
+            .map_err(|_| missing())?;
+        Ok(SvgFont {
+            family: self.family,
+            size: self.size,
+            font,
+        })
+    }
+}
+
+impl Font for SvgFont {}
+
+impl TextLayoutBuilder for SvgTextLayoutBuilder {
+    type Out = SvgTextLayout;
+
+    fn build(self) -> Result<SvgTextLayout, Error> {
+        Ok(self.0)
+    }
+}
+
+impl TextLayout for SvgTextLayout {
+    type Coord = f64;
+
+    fn width(&self) -> f64 {
+        self.width
+    }
+}
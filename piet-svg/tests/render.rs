@@ -0,0 +1,111 @@
+//! Check the SVG written for a small scene.
+
+use piet::kurbo::{Line, Rect, Vec2};
+use piet::{
+    Color, ErrorKind, FillRule, FontBuilder, Gradient, GradientStop, ImageFormat,
+    InterpolationMode, LinearGradient, RenderContext, StrokeStyle, Text, TextLayoutBuilder,
+};
+use piet_svg::SvgRenderContext;
+
+fn render(rc: &SvgRenderContext) -> String {
+    let mut buf = Vec::new();
+    rc.write(&mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+#[test]
+fn small_scene() {
+    let mut rc = SvgRenderContext::new(100.0, 100.0);
+    let stops = vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::WHITE,
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::BLACK,
+        },
+    ];
+    let gradient = rc
+        .gradient(Gradient::Linear(LinearGradient::new(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(100.0, 0.0),
+            stops,
+        )))
+        .unwrap();
+    rc.fill(
+        Rect::new(0.0, 0.0, 100.0, 50.0),
+        &gradient,
+        FillRule::NonZero,
+    );
+
+    rc.save().unwrap();
+    rc.clip(Rect::new(10.0, 10.0, 90.0, 90.0), FillRule::NonZero);
+    let black = rc.solid_brush(Color::BLACK);
+    let mut style = StrokeStyle::new();
+    style.set_dash(vec![4.0, 2.0], 0.0);
+    rc.stroke(
+        Line::new((0.0, 0.0), (100.0, 100.0)),
+        &black,
+        1.0,
+        Some(&style),
+    );
+    rc.restore().unwrap();
+
+    let font = rc
+        .text()
+        .new_font_by_name("Sans", 12.0)
+        .unwrap()
+        .build()
+        .unwrap();
+    let layout = rc
+        .text()
+        .new_text_layout(&font, "a < b & c")
+        .unwrap()
+        .build()
+        .unwrap();
+    rc.draw_text(&layout, Vec2::new(10.0, 80.0), &black);
+
+    let image = rc.make_image(2, 1, &[0, 255], ImageFormat::Alpha8).unwrap();
+    rc.draw_image(
+        &image,
+        Rect::new(0.0, 0.0, 20.0, 10.0),
+        InterpolationMode::Bilinear,
+    );
+    rc.finish().unwrap();
+
+    let svg = render(&rc);
+    assert!(svg.contains("<linearGradient"), "{}", svg);
+    assert!(svg.contains("<clipPath"), "{}", svg);
+    assert!(svg.contains("stroke-dasharray"), "{}", svg);
+    assert!(svg.contains(">a &lt; b &amp; c</text>"), "{}", svg);
+    assert!(
+        svg.contains("xlink:href=\"data:image/png;base64,"),
+        "{}",
+        svg
+    );
+    // Groups opened by clips are closed when the state is restored.
+    assert_eq!(svg.matches("<g ").count(), svg.matches("</g>").count());
+}
+
+#[test]
+fn unbalanced_save_restore() {
+    let mut rc = SvgRenderContext::new(10.0, 10.0);
+    match rc.restore() {
+        Err(e) => match e.kind() {
+            ErrorKind::StackUnbalance => (),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(()) => panic!("restore without save succeeded"),
+    }
+
+    let mut rc = SvgRenderContext::new(10.0, 10.0);
+    rc.save().unwrap();
+    match rc.finish() {
+        Err(e) => match e.kind() {
+            ErrorKind::StackUnbalance => (),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(()) => panic!("finish with a save outstanding succeeded"),
+    }
+}
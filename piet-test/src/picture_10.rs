@@ -0,0 +1,82 @@
+//! Sweep gradients.
+
+use std::f64::consts::PI;
+
+use piet::kurbo::{Circle, Rect, Vec2};
+
+use piet::{
+    Color, Error, ExtendMode, FillRule, Gradient, GradientStop, RenderContext, SweepGradient,
+};
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // A color wheel.
+    let hues = [
+        0xff_00_00, 0xff_ff_00, 0x00_ff_00, 0x00_ff_ff, 0x00_00_ff, 0xff_00_ff, 0xff_00_00,
+    ];
+    let stops = hues
+        .iter()
+        .enumerate()
+        .map(|(i, &rgb)| GradientStop {
+            pos: i as f32 / (hues.len() - 1) as f32,
+            color: Color::rgb24(rgb),
+        })
+        .collect();
+    let wheel = rc.gradient(Gradient::Sweep(SweepGradient::new(
+        Vec2::new(35.0, 35.0),
+        0.0,
+        2.0 * PI,
+        stops,
+    )))?;
+    rc.fill(Circle::new((35.0, 35.0), 30.0), &wheel, FillRule::NonZero);
+
+    // A gauge, covering part of the circle, padded on both sides.
+    let stops = vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::rgb24(0x00_c0_00),
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::rgb24(0xc0_00_00),
+        },
+    ];
+    let gauge = rc.gradient(Gradient::Sweep(SweepGradient::new(
+        Vec2::new(105.0, 35.0),
+        0.75 * PI,
+        2.25 * PI,
+        stops,
+    )))?;
+    rc.fill(Circle::new((105.0, 35.0), 30.0), &gauge, FillRule::NonZero);
+
+    // Repeated stripes with a hard stop.
+    let stops = vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::rgb24(0x00_00_00),
+        },
+        GradientStop {
+            pos: 0.5,
+            color: Color::rgb24(0x80_80_80),
+        },
+        GradientStop {
+            pos: 0.5,
+            color: Color::rgb24(0xff_ff_ff),
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::rgb24(0x00_00_00),
+        },
+    ];
+    let stripes = rc.gradient(Gradient::Sweep(SweepGradient {
+        extend: ExtendMode::Repeat,
+        ..SweepGradient::new(Vec2::new(175.0, 35.0), 0.0, 0.25 * PI, stops)
+    }))?;
+    rc.fill(
+        Rect::new(145.0, 5.0, 205.0, 65.0),
+        &stripes,
+        FillRule::NonZero,
+    );
+    Ok(())
+}
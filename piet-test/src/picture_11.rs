@@ -0,0 +1,51 @@
+//! Gradient transforms.
+
+use std::f64::consts::PI;
+
+use piet::kurbo::{Affine, Rect, Vec2};
+
+use piet::{
+    Color, Error, ExtendMode, FillRule, Gradient, GradientStop, LinearGradient, RadialGradient,
+    RenderContext,
+};
+
+fn make_stops() -> Vec<GradientStop> {
+    vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::rgb24(0xff_ff_ff),
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::rgb24(0x00_60_00),
+        },
+    ]
+}
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // An elliptical radial gradient, twice as wide as it is high.
+    let ellipse = rc.gradient(Gradient::Radial(RadialGradient {
+        transform: Some(Affine::new([60.0, 0.0, 0.0, 30.0, 65.0, 35.0])),
+        ..RadialGradient::new(Vec2::new(0.0, 0.0), 1.0, make_stops())
+    }))?;
+    rc.fill(
+        Rect::new(5.0, 5.0, 125.0, 65.0),
+        &ellipse,
+        FillRule::NonZero,
+    );
+
+    // A rotated linear gradient, repeating so that the rotation shows.
+    let rotated = rc.gradient(Gradient::Linear(LinearGradient {
+        extend: ExtendMode::Reflect,
+        transform: Some(Affine::translate((165.0, 35.0)) * Affine::rotate(PI / 6.0)),
+        ..LinearGradient::new(Vec2::new(0.0, 0.0), Vec2::new(15.0, 0.0), make_stops())
+    }))?;
+    rc.fill(
+        Rect::new(135.0, 5.0, 195.0, 65.0),
+        &rotated,
+        FillRule::NonZero,
+    );
+    Ok(())
+}
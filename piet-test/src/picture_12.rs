@@ -0,0 +1,52 @@
+//! Gradients in bounding box units.
+
+use piet::kurbo::{Circle, Rect, Vec2};
+
+use piet::{
+    Color, Error, FillRule, Gradient, GradientStop, GradientUnits, LinearGradient, RadialGradient,
+    RenderContext,
+};
+
+fn make_stops() -> Vec<GradientStop> {
+    vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::rgb24(0xff_c0_00),
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::rgb24(0x60_00_80),
+        },
+    ]
+}
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // One brush for each gradient, fitted to every shape it fills.
+    let linear = rc.gradient(Gradient::Linear(LinearGradient {
+        units: GradientUnits::ObjectBoundingBox,
+        ..LinearGradient::new(Vec2::new(0.0, 0.0), Vec2::new(1.0, 1.0), make_stops())
+    }))?;
+    let radial = rc.gradient(Gradient::Radial(RadialGradient {
+        units: GradientUnits::ObjectBoundingBox,
+        ..RadialGradient::new(Vec2::new(0.5, 0.5), 0.5, make_stops())
+    }))?;
+
+    let rects = [
+        Rect::new(5.0, 5.0, 25.0, 25.0),
+        Rect::new(30.0, 5.0, 90.0, 25.0),
+        Rect::new(95.0, 5.0, 115.0, 65.0),
+    ];
+    for rect in &rects {
+        rc.fill(*rect, &linear, FillRule::NonZero);
+    }
+    rc.fill(Circle::new((15.0, 45.0), 10.0), &radial, FillRule::NonZero);
+    rc.fill(
+        Rect::new(30.0, 30.0, 90.0, 60.0),
+        &radial,
+        FillRule::NonZero,
+    );
+    rc.stroke(Circle::new((150.0, 35.0), 25.0), &linear, 6.0, None);
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! Gradient color interpolation.
+
+use piet::kurbo::{Rect, Vec2};
+
+use piet::{
+    Color, ColorInterpolation, Error, FillRule, Gradient, GradientStop, LinearGradient,
+    RenderContext,
+};
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    let interpolations = [
+        ColorInterpolation::PremultipliedSrgb,
+        ColorInterpolation::UnpremultipliedSrgb,
+        ColorInterpolation::LinearRgb,
+    ];
+    let mut y = 5.0;
+    for &interpolation in &interpolations {
+        // Opaque red to transparent blue shows the difference between
+        // premultiplied and separate alpha; red to green shows linear light.
+        let pairs = [
+            (Color::rgb24(0xff_00_00), Color::rgba32(0x00_00_ff_00)),
+            (Color::rgb24(0xff_00_00), Color::rgb24(0x00_ff_00)),
+        ];
+        let mut x = 5.0;
+        for (start, end) in pairs.iter().cloned() {
+            let gradient = rc.gradient(Gradient::Linear(LinearGradient {
+                interpolation,
+                ..LinearGradient::new(
+                    Vec2::new(x, 0.0),
+                    Vec2::new(x + 100.0, 0.0),
+                    vec![
+                        GradientStop {
+                            pos: 0.0,
+                            color: start,
+                        },
+                        GradientStop {
+                            pos: 1.0,
+                            color: end,
+                        },
+                    ],
+                )
+            }))?;
+            rc.fill(
+                Rect::new(x, y, x + 100.0, y + 20.0),
+                &gradient,
+                FillRule::NonZero,
+            );
+            x += 110.0;
+        }
+        y += 25.0;
+    }
+    Ok(())
+}
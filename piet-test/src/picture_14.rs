@@ -0,0 +1,67 @@
+//! Image brushes.
+
+use piet::kurbo::{Affine, Circle, Rect};
+
+use piet::{Color, Error, ExtendMode, FillRule, ImageFormat, InterpolationMode, RenderContext};
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // A checkerboard behind translucent content.
+    let checker_data = [
+        0xcc, 0xcc, 0xcc, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xcc, 0xcc, 0xcc,
+        0xff,
+    ];
+    let checker = rc.make_image(2, 2, &checker_data, ImageFormat::RgbaSeparate)?;
+    let checker_brush = rc.image_brush(
+        &checker,
+        ExtendMode::Repeat,
+        ExtendMode::Repeat,
+        Affine::scale(8.0),
+        InterpolationMode::NearestNeighbor,
+    )?;
+    rc.fill(
+        Rect::new(5.0, 5.0, 85.0, 85.0),
+        &checker_brush,
+        FillRule::NonZero,
+    );
+    let red = rc.solid_brush(Color::rgba32(0xc0_00_00_80));
+    rc.fill(Circle::new((45.0, 45.0), 30.0), &red, FillRule::NonZero);
+
+    // A hatch pattern, stroked.
+    let mut hatch_data = vec![0; 8 * 8 * 4];
+    for i in 0..8 {
+        let ix = (i * 8 + i) * 4;
+        hatch_data[ix + 2] = 0x80;
+        hatch_data[ix + 3] = 0xff;
+    }
+    let hatch = rc.make_image(8, 8, &hatch_data, ImageFormat::RgbaSeparate)?;
+    let hatch_brush = rc.image_brush(
+        &hatch,
+        ExtendMode::Repeat,
+        ExtendMode::Repeat,
+        Affine::translate((95.0, 5.0)),
+        InterpolationMode::NearestNeighbor,
+    )?;
+    rc.stroke(Circle::new((135.0, 45.0), 30.0), &hatch_brush, 12.0, None);
+
+    // Reflection, which not all backends support.
+    let ramp_data = (0..16)
+        .flat_map(|x| vec![(x * 17) as u8, 0x40, 0x80, 0xff])
+        .collect::<Vec<u8>>();
+    let ramp = rc.make_image(16, 1, &ramp_data, ImageFormat::RgbaSeparate)?;
+    if let Ok(ramp_brush) = rc.image_brush(
+        &ramp,
+        ExtendMode::Reflect,
+        ExtendMode::Repeat,
+        Affine::translate((185.0, 5.0)),
+        InterpolationMode::Bilinear,
+    ) {
+        rc.fill(
+            Rect::new(185.0, 5.0, 265.0, 85.0),
+            &ramp_brush,
+            FillRule::NonZero,
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+//! Blurred rectangles and drop shadows.
+
+use piet::kurbo::{Affine, Circle, Rect, Vec2};
+
+use piet::{Color, Error, FillRule, RenderContext};
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // The analytic and general paths, which should match.
+    let black = rc.solid_brush(Color::BLACK);
+    rc.blurred_rect(Rect::new(15.0, 15.0, 65.0, 65.0), 5.0, &black);
+    rc.draw_shadow(
+        Rect::new(75.0, 15.0, 125.0, 65.0),
+        Vec2::ZERO,
+        5.0,
+        Color::BLACK,
+    );
+
+    // A card with a drop shadow.
+    let card = Circle::new((160.0, 40.0), 22.0);
+    rc.draw_shadow(card, Vec2::new(3.0, 4.0), 3.0, Color::rgba32(0x00_00_00_80));
+    let blue = rc.solid_brush(Color::rgb24(0x40_80_c0));
+    rc.fill(card, &blue, FillRule::NonZero);
+
+    // A rotated blurred rect takes the general path in some backends.
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((230.0, 40.0)) * Affine::rotate(0.4));
+        let red = rc.solid_brush(Color::rgb24(0xc0_20_20));
+        rc.blurred_rect(Rect::new(-20.0, -15.0, 20.0, 15.0), 2.0, &red);
+        Ok(())
+    })
+}
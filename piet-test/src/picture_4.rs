@@ -19,10 +19,8 @@ pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
         },
     ];
     let gradient = rc.gradient(Gradient::Radial(RadialGradient {
-        center: Vec2::new(30.0, 30.0),
         origin_offset: Vec2::new(10.0, 10.0),
-        radius: 30.0,
-        stops,
+        ..RadialGradient::new(Vec2::new(30.0, 30.0), 30.0, stops)
     }))?;
     rc.fill(
         Rect::new(0.0, 0.0, 60.0, 60.0),
@@ -39,11 +37,11 @@ pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
             color: Color::rgb24(0x00_00_00),
         },
     ];
-    let gradient2 = rc.gradient(Gradient::Linear(LinearGradient {
-        start: Vec2::new(0.0, 0.0),
-        end: Vec2::new(60.0, 0.0),
-        stops: stops2,
-    }))?;
+    let gradient2 = rc.gradient(Gradient::Linear(LinearGradient::new(
+        Vec2::new(0.0, 0.0),
+        Vec2::new(60.0, 0.0),
+        stops2,
+    )))?;
     rc.fill(
         Rect::new(0.0, 80.0, 60.0, 100.0),
         &gradient2,
@@ -0,0 +1,64 @@
+//! Blend modes.
+
+use piet::kurbo::{Affine, Rect};
+
+use piet::{BlendMode, Color, Error, FillRule, RenderContext};
+
+const MODES: &[BlendMode] = &[
+    BlendMode::Clear,
+    BlendMode::Src,
+    BlendMode::Dst,
+    BlendMode::SrcOver,
+    BlendMode::DstOver,
+    BlendMode::SrcIn,
+    BlendMode::DstIn,
+    BlendMode::SrcOut,
+    BlendMode::DstOut,
+    BlendMode::SrcAtop,
+    BlendMode::DstAtop,
+    BlendMode::Xor,
+    BlendMode::Add,
+    BlendMode::Multiply,
+    BlendMode::Screen,
+    BlendMode::Overlay,
+    BlendMode::Darken,
+    BlendMode::Lighten,
+    BlendMode::ColorDodge,
+    BlendMode::ColorBurn,
+    BlendMode::HardLight,
+    BlendMode::SoftLight,
+    BlendMode::Difference,
+    BlendMode::Exclusion,
+    BlendMode::Hue,
+    BlendMode::Saturation,
+    BlendMode::Color,
+    BlendMode::Luminosity,
+];
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    let dst_brush = rc.solid_brush(Color::rgba32(0xe0_40_20_ff));
+    let src_brush = rc.solid_brush(Color::rgba32(0x20_60_e0_c0));
+    for (i, &mode) in MODES.iter().enumerate() {
+        let x = 5.0 + 27.0 * ((i % 7) as f64);
+        let y = 5.0 + 24.0 * ((i / 7) as f64);
+        rc.with_save(|rc| {
+            rc.transform(Affine::translate((x, y)));
+            // Some operators are unbounded, so keep them inside the cell.
+            rc.clip(Rect::new(0.0, 0.0, 22.0, 20.0), FillRule::NonZero);
+            rc.fill(
+                Rect::new(0.0, 0.0, 14.0, 14.0),
+                &dst_brush,
+                FillRule::NonZero,
+            );
+            rc.set_blend_mode(mode);
+            rc.fill(
+                Rect::new(7.0, 5.0, 21.0, 19.0),
+                &src_brush,
+                FillRule::NonZero,
+            );
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Layers with group opacity.
+
+use piet::kurbo::{Affine, BezPath, Rect};
+
+use piet::{Color, Error, FillRule, RenderContext};
+
+fn draw_shapes(rc: &mut impl RenderContext, color: Color) {
+    let brush = rc.solid_brush(color);
+    rc.fill(Rect::new(0.0, 0.0, 40.0, 40.0), &brush, FillRule::NonZero);
+    rc.fill(Rect::new(20.0, 20.0, 60.0, 60.0), &brush, FillRule::NonZero);
+}
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // Alpha baked into the color: the overlap is darker.
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((5.0, 5.0)));
+        draw_shapes(rc, Color::rgba32(0x00_00_c0_80));
+        Ok(())
+    })?;
+
+    // Group opacity: the overlap is the same as the rest.
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((70.0, 5.0)));
+        rc.with_layer(0.5, None::<Rect>, |rc| {
+            draw_shapes(rc, Color::rgb24(0x00_00_c0));
+            Ok(())
+        })
+    })?;
+
+    // A clipped layer.
+    let mut triangle = BezPath::new();
+    triangle.move_to((0.0, 0.0));
+    triangle.line_to((60.0, 0.0));
+    triangle.line_to((0.0, 60.0));
+    triangle.close_path();
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((135.0, 5.0)));
+        rc.with_layer(0.5, Some(triangle), |rc| {
+            draw_shapes(rc, Color::rgb24(0xc0_00_00));
+            Ok(())
+        })
+    })?;
+    Ok(())
+}
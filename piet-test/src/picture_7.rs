@@ -0,0 +1,66 @@
+//! Image masks and masks from rendered content.
+
+use piet::kurbo::{Affine, Circle, Rect};
+
+use piet::{Color, Error, FillRule, ImageFormat, MaskMode, RenderContext};
+
+fn draw_stripes(rc: &mut impl RenderContext) {
+    let red = rc.solid_brush(Color::rgb24(0xc0_00_00));
+    let blue = rc.solid_brush(Color::rgb24(0x00_00_c0));
+    for i in 0..6 {
+        let x = i as f64 * 10.0;
+        let brush = if i % 2 == 0 { &red } else { &blue };
+        rc.fill(Rect::new(x, 0.0, x + 10.0, 60.0), brush, FillRule::NonZero);
+    }
+}
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+
+    // An alpha mask from an image with a horizontal alpha ramp.
+    let image_data = make_image_data(32, 32);
+    let image = rc.make_image(32, 32, &image_data, ImageFormat::RgbaSeparate)?;
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((5.0, 5.0)));
+        rc.mask(&image, Rect::new(0.0, 0.0, 60.0, 60.0), MaskMode::Alpha);
+        draw_stripes(rc);
+        Ok(())
+    })?;
+
+    // A luminance mask from the same image, which is bright at the top.
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((70.0, 5.0)));
+        rc.mask(&image, Rect::new(0.0, 0.0, 60.0, 60.0), MaskMode::Luminance);
+        draw_stripes(rc);
+        Ok(())
+    })?;
+
+    // A mask from rendered content.
+    rc.with_save(|rc| {
+        rc.transform(Affine::translate((135.0, 5.0)));
+        rc.begin_mask()?;
+        let white = rc.solid_brush(Color::WHITE);
+        let gray = rc.solid_brush(Color::rgb24(0x80_80_80));
+        rc.fill(Circle::new((20.0, 20.0), 20.0), &white, FillRule::NonZero);
+        rc.fill(Circle::new((40.0, 40.0), 20.0), &gray, FillRule::NonZero);
+        rc.end_mask(MaskMode::Luminance)?;
+        draw_stripes(rc);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn make_image_data(width: usize, height: usize) -> Vec<u8> {
+    let mut result = vec![0; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let ix = (y * width + x) * 4;
+            let luma = (255 - y * 255 / (height - 1)) as u8;
+            result[ix] = luma;
+            result[ix + 1] = luma;
+            result[ix + 2] = luma;
+            result[ix + 3] = (x * 255 / (width - 1)) as u8;
+        }
+    }
+    result
+}
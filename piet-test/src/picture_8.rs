@@ -0,0 +1,54 @@
+//! Gradient extend modes.
+
+use piet::kurbo::{Rect, Vec2};
+
+use piet::{
+    Color, Error, ExtendMode, FillRule, Gradient, GradientStop, LinearGradient, RadialGradient,
+    RenderContext,
+};
+
+fn make_stops() -> Vec<GradientStop> {
+    vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::rgb24(0x00_00_c0),
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::rgb24(0xff_ff_ff),
+        },
+    ]
+}
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    let mut x = 5.0;
+    for &extend in &[ExtendMode::Pad, ExtendMode::Repeat, ExtendMode::Reflect] {
+        // The gradient only covers the middle part of each rectangle.
+        let linear = rc.gradient(Gradient::Linear(LinearGradient {
+            extend,
+            ..LinearGradient::new(
+                Vec2::new(x + 20.0, 0.0),
+                Vec2::new(x + 40.0, 0.0),
+                make_stops(),
+            )
+        }))?;
+        rc.fill(
+            Rect::new(x, 5.0, x + 60.0, 35.0),
+            &linear,
+            FillRule::NonZero,
+        );
+
+        let radial = rc.gradient(Gradient::Radial(RadialGradient {
+            extend,
+            ..RadialGradient::new(Vec2::new(x + 30.0, 70.0), 10.0, make_stops())
+        }))?;
+        rc.fill(
+            Rect::new(x, 40.0, x + 60.0, 100.0),
+            &radial,
+            FillRule::NonZero,
+        );
+        x += 65.0;
+    }
+    Ok(())
+}
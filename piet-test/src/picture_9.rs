@@ -0,0 +1,48 @@
+//! Two-point conical radial gradients.
+
+use piet::kurbo::{Rect, Vec2};
+
+use piet::{Color, Error, FillRule, Gradient, GradientStop, RadialGradient, RenderContext};
+
+fn make_stops() -> Vec<GradientStop> {
+    vec![
+        GradientStop {
+            pos: 0.0,
+            color: Color::rgb24(0xff_ff_00),
+        },
+        GradientStop {
+            pos: 0.5,
+            color: Color::rgb24(0xc0_00_00),
+        },
+        GradientStop {
+            pos: 1.0,
+            color: Color::rgb24(0x00_00_40),
+        },
+    ]
+}
+
+pub fn draw<R: RenderContext>(rc: &mut R) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    // A focal point, a start circle inside the end circle, and a start
+    // circle overlapping the end circle.
+    let params = [
+        (Vec2::new(-15.0, -10.0), 0.0),
+        (Vec2::new(5.0, 5.0), 10.0),
+        (Vec2::new(20.0, 0.0), 15.0),
+    ];
+    let mut x = 5.0;
+    for &(origin_offset, start_radius) in &params {
+        let gradient = rc.gradient(Gradient::Radial(RadialGradient {
+            origin_offset,
+            start_radius,
+            ..RadialGradient::new(Vec2::new(x + 30.0, 35.0), 30.0, make_stops())
+        }))?;
+        rc.fill(
+            Rect::new(x, 5.0, x + 60.0, 65.0),
+            &gradient,
+            FillRule::NonZero,
+        );
+        x += 65.0;
+    }
+    Ok(())
+}
@@ -6,15 +6,17 @@ use std::fmt;
 use js_sys::{Float64Array, Reflect};
 use wasm_bindgen::{Clamped, JsCast, JsValue};
 use web_sys::{
-    CanvasGradient, CanvasRenderingContext2d, CanvasWindingRule, HtmlCanvasElement, ImageData,
-    Window,
+    CanvasGradient, CanvasPattern, CanvasRenderingContext2d, CanvasWindingRule, HtmlCanvasElement,
+    ImageData, Window,
 };
 
 use piet::kurbo::{Affine, PathEl, Rect, Shape, Vec2};
 
 use piet::{
-    Color, Error, Font, FontBuilder, Gradient, GradientStop, ImageFormat, InterpolationMode,
-    LineCap, LineJoin, RenderContext, RoundInto, StrokeStyle, Text, TextLayout, TextLayoutBuilder,
+    expand_gradient_stops, new_error, BlendMode, Color, ColorInterpolation, Error, ErrorKind,
+    ExtendMode, Font, FontBuilder, Gradient, GradientStop, GradientUnits, ImageFormat,
+    InterpolationMode, LineCap, LineJoin, MaskMode, RenderContext, RoundInto, StrokeStyle, Text,
+    TextLayout, TextLayoutBuilder,
 };
 
 pub struct WebRenderContext<'a> {
@@ -22,6 +24,10 @@ pub struct WebRenderContext<'a> {
     /// Used for creating image bitmaps and possibly other resources.
     window: &'a Window,
     err: Result<(), Error>,
+    /// For each saved state, whether it was pushed by `push_layer`.
+    ///
+    /// The canvas tracks the state itself, this is only for checking balance.
+    layer_stack: Vec<bool>,
 }
 
 impl<'a> WebRenderContext<'a> {
@@ -30,13 +36,19 @@ impl<'a> WebRenderContext<'a> {
             ctx,
             window,
             err: Ok(()),
+            layer_stack: Vec::new(),
         }
     }
 }
 
 pub enum Brush {
     Solid(u32),
-    Gradient(CanvasGradient),
+    /// A gradient, with the gradient transform, if any.
+    Gradient(CanvasGradient, Option<Affine>),
+    /// A gradient in bounding box units, resolved when the brush is used.
+    BoundingBox(Gradient),
+    /// An image pattern, with the transform from image space to user space.
+    Pattern(CanvasPattern, Affine),
 }
 
 #[derive(Clone)]
@@ -78,8 +90,11 @@ enum FontStyle {
     Oblique(Option<f64>),
 }
 
+/// A JavaScript error, as a message.
+///
+/// `JsValue` can't be sent across threads, so it is formatted eagerly.
 #[derive(Debug)]
-struct WrappedJs(JsValue);
+struct WrappedJs(String);
 
 trait WrapError<T> {
     fn wrap(self) -> Result<T, Error>;
@@ -89,7 +104,7 @@ impl std::error::Error for WrappedJs {}
 
 impl fmt::Display for WrappedJs {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Canvas error: {:?}", self.0)
+        write!(f, "Canvas error: {}", self.0)
     }
 }
 
@@ -98,7 +113,8 @@ impl fmt::Display for WrappedJs {
 impl<T> WrapError<T> for Result<T, JsValue> {
     fn wrap(self) -> Result<T, Error> {
         self.map_err(|e| {
-            let e: Box<dyn std::error::Error> = Box::new(WrappedJs(e));
+            let e: Box<dyn std::error::Error + Send + Sync> =
+                Box::new(WrappedJs(format!("{:?}", e)));
             e.into()
         })
     }
@@ -127,6 +143,41 @@ fn convert_line_join(line_join: LineJoin) -> &'static str {
     }
 }
 
+/// Canvas composite operations are a superset of the blend modes, except that
+/// `clear` and `destination` were dropped from the spec.
+fn convert_blend_mode(mode: BlendMode) -> Option<&'static str> {
+    let op = match mode {
+        BlendMode::Clear | BlendMode::Dst => return None,
+        BlendMode::Src => "copy",
+        BlendMode::SrcOver => "source-over",
+        BlendMode::DstOver => "destination-over",
+        BlendMode::SrcIn => "source-in",
+        BlendMode::DstIn => "destination-in",
+        BlendMode::SrcOut => "source-out",
+        BlendMode::DstOut => "destination-out",
+        BlendMode::SrcAtop => "source-atop",
+        BlendMode::DstAtop => "destination-atop",
+        BlendMode::Xor => "xor",
+        BlendMode::Add => "lighter",
+        BlendMode::Multiply => "multiply",
+        BlendMode::Screen => "screen",
+        BlendMode::Overlay => "overlay",
+        BlendMode::Darken => "darken",
+        BlendMode::Lighten => "lighten",
+        BlendMode::ColorDodge => "color-dodge",
+        BlendMode::ColorBurn => "color-burn",
+        BlendMode::HardLight => "hard-light",
+        BlendMode::SoftLight => "soft-light",
+        BlendMode::Difference => "difference",
+        BlendMode::Exclusion => "exclusion",
+        BlendMode::Hue => "hue",
+        BlendMode::Saturation => "saturation",
+        BlendMode::Color => "color",
+        BlendMode::Luminosity => "luminosity",
+    };
+    Some(op)
+}
+
 impl<'a> RenderContext for WebRenderContext<'a> {
     /// wasm-bindgen doesn't have a native Point type, so use kurbo's.
     type Point = Vec2;
@@ -151,33 +202,87 @@ impl<'a> RenderContext for WebRenderContext<'a> {
     }
 
     fn gradient(&mut self, gradient: Gradient) -> Result<Brush, Error> {
+        if gradient.stops().is_empty() {
+            return Err(new_error(ErrorKind::InvalidGradient));
+        }
+        if gradient.units() == GradientUnits::ObjectBoundingBox {
+            return Ok(Brush::BoundingBox(gradient));
+        }
         match gradient {
             Gradient::Linear(linear) => {
                 let (x0, y0) = (linear.start.x, linear.start.y);
                 let (x1, y1) = (linear.end.x, linear.end.y);
+                // Canvas gradients always pad, so the extend mode is ignored.
                 let mut lg = self.ctx.create_linear_gradient(x0, y0, x1, y1);
-                set_gradient_stops(&mut lg, &linear.stops);
-                Ok(Brush::Gradient(lg))
+                set_gradient_stops(&mut lg, &linear.stops, linear.interpolation);
+                Ok(Brush::Gradient(lg, linear.transform))
             }
             Gradient::Radial(radial) => {
                 let (xc, yc) = (radial.center.x, radial.center.y);
                 let (xo, yo) = (radial.origin_offset.x, radial.origin_offset.y);
-                let r = radial.radius;
+                let (r0, r) = (radial.start_radius, radial.radius);
                 let mut rg = self
                     .ctx
-                    .create_radial_gradient(xc + xo, yc + yo, 0.0, xc, yc, r)
+                    .create_radial_gradient(xc + xo, yc + yo, r0, xc, yc, r)
                     .wrap()?;
-                set_gradient_stops(&mut rg, &radial.stops);
-                Ok(Brush::Gradient(rg))
+                set_gradient_stops(&mut rg, &radial.stops, radial.interpolation);
+                Ok(Brush::Gradient(rg, radial.transform))
             }
+            Gradient::Sweep(_) => Err(new_error(ErrorKind::NotSupported)),
+        }
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &Self::Image,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        _interp: InterpolationMode,
+    ) -> Result<Brush, Error> {
+        // Canvas patterns can repeat, or be transparent outside the image.
+        if extend_x != ExtendMode::Repeat || extend_y != ExtendMode::Repeat {
+            return Err(new_error(ErrorKind::NotSupported));
         }
+        let pattern = self
+            .ctx
+            .create_pattern_with_html_canvas_element(&image.inner, "repeat")
+            .wrap()?
+            .ok_or_else(|| new_error(ErrorKind::NotSupported))?;
+        Ok(Brush::Pattern(pattern, transform))
     }
 
     fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: piet::FillRule) {
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            if let Some(brush) = self.resolve_gradient(gradient, shape.bounding_box()) {
+                self.fill(shape, &brush, fill_rule);
+            }
+            return;
+        }
         self.set_path(shape);
         self.set_brush(brush, true);
-        self.ctx
-            .fill_with_canvas_winding_rule(convert_fill_rule(fill_rule));
+        // Canvas gradients have no transform, but the path is already in
+        // device space, so transforming the context transforms the gradient.
+        if let Some(transform) = brush_transform(brush) {
+            self.ctx.save();
+            self.transform(transform);
+            self.ctx
+                .fill_with_canvas_winding_rule(convert_fill_rule(fill_rule));
+            self.ctx.restore();
+        } else {
+            self.ctx
+                .fill_with_canvas_winding_rule(convert_fill_rule(fill_rule));
+        }
+    }
+
+    fn blurred_rect(&mut self, _rect: Rect, _blur_radius: f64, _brush: &Brush) {
+        // TODO: use canvas shadows, with the rect itself drawn out of view.
+        self.err = Err(new_error(ErrorKind::NotSupported));
+    }
+
+    fn draw_shadow(&mut self, _shape: impl Shape, _offset: Vec2, _blur_radius: f64, _color: Color) {
+        // TODO: use canvas shadows, with the shape itself drawn out of view.
+        self.err = Err(new_error(ErrorKind::NotSupported));
     }
 
     fn clip(&mut self, shape: impl Shape, fill_rule: piet::FillRule) {
@@ -186,6 +291,19 @@ impl<'a> RenderContext for WebRenderContext<'a> {
             .clip_with_canvas_winding_rule(convert_fill_rule(fill_rule));
     }
 
+    fn mask(&mut self, _mask: &Self::Image, _rect: impl Into<Rect>, _mode: MaskMode) {
+        // Canvas 2D has no masks.
+        self.err = Err(new_error(ErrorKind::NotSupported));
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        Err(new_error(ErrorKind::NotSupported))
+    }
+
+    fn end_mask(&mut self, _mode: MaskMode) -> Result<(), Error> {
+        Err(new_error(ErrorKind::NotSupported))
+    }
+
     fn stroke(
         &mut self,
         shape: impl Shape,
@@ -193,6 +311,17 @@ impl<'a> RenderContext for WebRenderContext<'a> {
         width: impl RoundInto<Self::Coord>,
         style: Option<&StrokeStyle>,
     ) {
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            if let Some(brush) = self.resolve_gradient(gradient, shape.bounding_box()) {
+                self.stroke(shape, &brush, width, style);
+            }
+            return;
+        }
+        if brush_transform(brush).is_some() {
+            // The trick used for fills would also transform the stroke.
+            self.err = Err(new_error(ErrorKind::NotSupported));
+            return;
+        }
         self.set_path(shape);
         self.set_stroke(width.round_into(), style);
         self.set_brush(brush, false);
@@ -209,9 +338,23 @@ impl<'a> RenderContext for WebRenderContext<'a> {
         pos: impl RoundInto<Self::Point>,
         brush: &Self::Brush,
     ) {
+        let pos: Vec2 = pos.round_into();
+        if let Brush::BoundingBox(ref gradient) = *brush {
+            // Canvas has no font metrics, so approximate them.
+            let size = layout.font.size;
+            let bbox = Rect::new(
+                pos.x,
+                pos.y - 0.8 * size,
+                pos.x + layout.width(),
+                pos.y + 0.2 * size,
+            );
+            if let Some(brush) = self.resolve_gradient(gradient, bbox) {
+                self.draw_text(layout, pos, &brush);
+            }
+            return;
+        }
         self.ctx.set_font(&layout.font.get_font_string());
         self.set_brush(brush, true);
-        let pos = pos.round_into();
         if let Err(e) = self.ctx.fill_text(&layout.text, pos.x, pos.y).wrap() {
             self.err = Err(e);
         }
@@ -219,15 +362,46 @@ impl<'a> RenderContext for WebRenderContext<'a> {
 
     fn save(&mut self) -> Result<(), Error> {
         self.ctx.save();
+        self.layer_stack.push(false);
         Ok(())
     }
 
     fn restore(&mut self) -> Result<(), Error> {
+        if self.layer_stack.last() != Some(&false) {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.layer_stack.pop();
+        self.ctx.restore();
+        Ok(())
+    }
+
+    /// Canvas has no isolated groups, so this is an approximation that
+    /// multiplies the global alpha by the opacity. Unlike the other back-ends,
+    /// overlapping shapes inside the layer blend with each other.
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        self.ctx.save();
+        self.layer_stack.push(true);
+        if let Some(shape) = clip {
+            self.clip(shape, piet::FillRule::NonZero);
+        }
+        let alpha = self.ctx.global_alpha();
+        self.ctx.set_global_alpha(alpha * opacity);
+        Ok(())
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        if self.layer_stack.last() != Some(&true) {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.layer_stack.pop();
         self.ctx.restore();
         Ok(())
     }
 
     fn finish(&mut self) -> Result<(), Error> {
+        if !self.layer_stack.is_empty() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
         self.status()
     }
 
@@ -236,6 +410,17 @@ impl<'a> RenderContext for WebRenderContext<'a> {
         let _ = self.ctx.transform(a[0], a[1], a[2], a[3], a[4], a[5]);
     }
 
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        match convert_blend_mode(mode) {
+            Some(op) => {
+                if let Err(e) = self.ctx.set_global_composite_operation(op).wrap() {
+                    self.err = Err(e);
+                }
+            }
+            None => self.err = Err(new_error(ErrorKind::NotSupported)),
+        }
+    }
+
     fn make_image(
         &mut self,
         width: usize,
@@ -243,6 +428,7 @@ impl<'a> RenderContext for WebRenderContext<'a> {
         buf: &[u8],
         format: ImageFormat,
     ) -> Result<Self::Image, Error> {
+        format.check_buffer(width, height, buf)?;
         let document = self.window.document().unwrap();
         let element = document.create_element("canvas").unwrap();
         let canvas = element.dyn_into::<HtmlCanvasElement>().unwrap();
@@ -323,6 +509,15 @@ impl<'a> RenderContext for WebRenderContext<'a> {
     }
 }
 
+/// The transform of a brush, which is applied when filling.
+fn brush_transform(brush: &Brush) -> Option<Affine> {
+    match *brush {
+        Brush::Gradient(_, transform) => transform,
+        Brush::Pattern(_, transform) => Some(transform),
+        _ => None,
+    }
+}
+
 fn format_color(rgba: u32) -> String {
     let rgb = rgba >> 8;
     let a = rgba & 0xff;
@@ -339,8 +534,15 @@ fn format_color(rgba: u32) -> String {
     }
 }
 
-fn set_gradient_stops(dst: &mut CanvasGradient, src: &[GradientStop]) {
-    for stop in src {
+/// Browsers interpolate canvas gradients in sRGB with separate alpha.
+const NATIVE_INTERPOLATION: ColorInterpolation = ColorInterpolation::UnpremultipliedSrgb;
+
+fn set_gradient_stops(
+    dst: &mut CanvasGradient,
+    src: &[GradientStop],
+    interpolation: ColorInterpolation,
+) {
+    for stop in &expand_gradient_stops(src, interpolation, NATIVE_INTERPOLATION) {
         // TODO: maybe get error?
         let rgba = stop.color.as_rgba32();
         let _ = dst.add_color_stop(stop.pos, &format_color(rgba));
@@ -399,13 +601,36 @@ impl<'a> WebRenderContext<'a> {
                     self.ctx.set_stroke_style(&JsValue::from_str(&color_str));
                 }
             }
-            Brush::Gradient(ref gradient) => {
+            Brush::Gradient(ref gradient, _) => {
                 if is_fill {
                     self.ctx.set_fill_style(&JsValue::from(gradient));
                 } else {
                     self.ctx.set_stroke_style(&JsValue::from(gradient));
                 }
             }
+            Brush::Pattern(ref pattern, _) => {
+                if is_fill {
+                    self.ctx.set_fill_style(&JsValue::from(pattern));
+                } else {
+                    self.ctx.set_stroke_style(&JsValue::from(pattern));
+                }
+            }
+            // Resolved before getting here.
+            Brush::BoundingBox(_) => unreachable!(),
+        }
+    }
+
+    /// Resolve a gradient in bounding box units to a brush.
+    ///
+    /// Returns `None` if nothing should be drawn.
+    fn resolve_gradient(&mut self, gradient: &Gradient, bbox: Rect) -> Option<Brush> {
+        let gradient = gradient.resolve_units(bbox)?;
+        match self.gradient(gradient) {
+            Ok(brush) => Some(brush),
+            Err(e) => {
+                self.err = Err(e);
+                None
+            }
         }
     }
 
@@ -0,0 +1,78 @@
+//! Compositing operators and blend modes.
+
+/// A compositing operator or blend mode.
+///
+/// The first group of variants are the Porter-Duff compositing operators,
+/// which decide how the coverage of the source (the thing being drawn) and
+/// the destination (what is already on the surface) are combined. The second
+/// group are the separable and non-separable blend modes, which composite
+/// like [`SrcOver`](#variant.SrcOver) but mix the colors where source and
+/// destination overlap. The semantics follow the [Compositing and Blending]
+/// spec.
+///
+/// [Compositing and Blending]: https://www.w3.org/TR/compositing-1/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// Clear the destination, regardless of the source.
+    Clear,
+    /// Replace the destination with the source.
+    Src,
+    /// Leave the destination untouched.
+    Dst,
+    /// Draw the source over the destination. This is the default.
+    SrcOver,
+    /// Draw the source behind the destination.
+    DstOver,
+    /// Keep the source where the destination is present.
+    SrcIn,
+    /// Keep the destination where the source is present.
+    DstIn,
+    /// Keep the source where the destination is absent.
+    SrcOut,
+    /// Keep the destination where the source is absent.
+    DstOut,
+    /// Draw the source over the destination, only where the destination is present.
+    SrcAtop,
+    /// Draw the destination over the source, only where the source is present.
+    DstAtop,
+    /// Keep source and destination only where they don't overlap.
+    Xor,
+    /// Add source and destination, saturating.
+    Add,
+    /// Multiply the colors.
+    Multiply,
+    /// The inverse of multiplying the inverted colors.
+    Screen,
+    /// Multiply or screen, depending on the destination color.
+    Overlay,
+    /// Keep the darker of the two colors.
+    Darken,
+    /// Keep the lighter of the two colors.
+    Lighten,
+    /// Brighten the destination to reflect the source.
+    ColorDodge,
+    /// Darken the destination to reflect the source.
+    ColorBurn,
+    /// Multiply or screen, depending on the source color.
+    HardLight,
+    /// Darken or lighten, depending on the source color.
+    SoftLight,
+    /// The absolute difference of the two colors.
+    Difference,
+    /// Like `Difference`, but with lower contrast.
+    Exclusion,
+    /// The hue of the source with the saturation and luminosity of the destination.
+    Hue,
+    /// The saturation of the source with the hue and luminosity of the destination.
+    Saturation,
+    /// The hue and saturation of the source with the luminosity of the destination.
+    Color,
+    /// The luminosity of the source with the hue and saturation of the destination.
+    Luminosity,
+}
+
+impl Default for BlendMode {
+    fn default() -> BlendMode {
+        BlendMode::SrcOver
+    }
+}
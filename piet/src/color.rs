@@ -1,13 +1,48 @@
 //! A simple representation of color
 
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::{new_error, Error, ErrorKind};
+
 /// A datatype representing color.
 ///
-/// Currently this is only a 32 bit RGBA value, but it will likely
-/// extend to some form of wide-gamut colorspace, and in the meantime
-/// is useful for giving programs proper type.
-#[derive(Clone)]
+/// Colors are either a 32 bit RGBA value, which is always sRGB, or floating
+/// point components in one of a few color spaces, including the wide-gamut
+/// Display P3. Backends that accept floating point colors use the full
+/// precision; others quantize to 8 bits per channel.
+///
+/// Colors compare equal when they have the same sRGB components, whatever
+/// their representation, so `Color::rgb_f32(1.0, 0.0, 0.0)` equals `Color::RED`.
+/// NaN components count as zero.
+///
+/// Colors can be parsed from CSS color strings, see the `FromStr` impl.
+#[derive(Clone, Debug)]
 pub enum Color {
     Rgba32(u32),
+    /// Floating point components with separate alpha, in the given space.
+    ///
+    /// Components aren't clamped, so a color converted from a wider space
+    /// may fall outside the range 0.0 to 1.0. Backends clip such colors to
+    /// the sRGB gamut when drawing.
+    RgbaF32([f32; 4], ColorSpace),
+}
+
+/// The color space of a floating point color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// The sRGB color space, with its nonlinear transfer function.
+    Srgb,
+    /// The sRGB primaries, with linear components.
+    LinearSrgb,
+    /// The Display P3 color space, with the sRGB transfer function.
+    DisplayP3,
+}
+
+impl Default for ColorSpace {
+    fn default() -> ColorSpace {
+        ColorSpace::Srgb
+    }
 }
 
 impl Color {
@@ -23,8 +58,9 @@ impl Color {
 
     /// Create a color from four floating point values, each in the range 0.0 to 1.0.
     ///
-    /// The interpretation is the same as rgba32, and no greater precision is
-    /// (currently) assumed.
+    /// The interpretation is the same as rgba32, and the result is quantized
+    /// to 8 bits per channel. Use [`rgba_f32`](#method.rgba_f32) to keep full
+    /// precision.
     pub fn rgba<F: Into<f64>>(r: F, g: F, b: F, a: F) -> Color {
         let r = (r.into().max(0.0).min(1.0) * 255.0).round() as u32;
         let g = (g.into().max(0.0).min(1.0) * 255.0).round() as u32;
@@ -35,8 +71,9 @@ impl Color {
 
     /// Create a color from three floating point values, each in the range 0.0 to 1.0.
     ///
-    /// The interpretation is the same as rgb24, and no greater precision is
-    /// (currently) assumed.
+    /// The interpretation is the same as rgb24, and the result is quantized
+    /// to 8 bits per channel. Use [`rgb_f32`](#method.rgb_f32) to keep full
+    /// precision.
     pub fn rgb<F: Into<f64>>(r: F, g: F, b: F) -> Color {
         let r = (r.into().max(0.0).min(1.0) * 255.0).round() as u32;
         let g = (g.into().max(0.0).min(1.0) * 255.0).round() as u32;
@@ -44,16 +81,557 @@ impl Color {
         Color::rgba32((r << 24) | (g << 16) | (b << 8) | 0xff)
     }
 
+    /// Create an sRGB color from four floating point values, each in the
+    /// range 0.0 to 1.0, keeping full precision.
+    pub fn rgba_f32<F: Into<f64>>(r: F, g: F, b: F, a: F) -> Color {
+        let clamp = |x: F| x.into().max(0.0).min(1.0);
+        Color::rgba_in(ColorSpace::Srgb, clamp(r), clamp(g), clamp(b), clamp(a))
+    }
+
+    /// Create an opaque sRGB color from three floating point values, each in
+    /// the range 0.0 to 1.0, keeping full precision.
+    pub fn rgb_f32<F: Into<f64>>(r: F, g: F, b: F) -> Color {
+        Color::rgba_f32(r.into(), g.into(), b.into(), 1.0)
+    }
+
+    /// Create a color from floating point components in the given color space.
+    ///
+    /// The components aren't clamped, see [`RgbaF32`](#variant.RgbaF32), but
+    /// NaN components are replaced by zero.
+    pub fn rgba_in<F: Into<f64>>(space: ColorSpace, r: F, g: F, b: F, a: F) -> Color {
+        let c = [r.into(), g.into(), b.into(), a.into()];
+        let f = |x: f64| if x.is_nan() { 0.0 } else { x as f32 };
+        Color::RgbaF32([f(c[0]), f(c[1]), f(c[2]), f(c[3])], space)
+    }
+
+    /// The color space of the color.
+    pub fn space(&self) -> ColorSpace {
+        match *self {
+            Color::Rgba32(_) => ColorSpace::Srgb,
+            Color::RgbaF32(_, space) => space,
+        }
+    }
+
+    /// Convert the color to floating point components in the given space.
+    pub fn to_space(&self, space: ColorSpace) -> Color {
+        let (c, from) = match *self {
+            Color::Rgba32(rgba) => {
+                let byte = |shift: u32| ((rgba >> shift) & 255) as f64 / 255.0;
+                ([byte(24), byte(16), byte(8), byte(0)], ColorSpace::Srgb)
+            }
+            Color::RgbaF32(c, from) => {
+                let c = [c[0] as f64, c[1] as f64, c[2] as f64, c[3] as f64];
+                (c, from)
+            }
+        };
+        let c = if from == space {
+            c
+        } else {
+            from_linear_srgb(to_linear_srgb(c, from), space)
+        };
+        Color::rgba_in(space, c[0], c[1], c[2], c[3])
+    }
+
+    /// Convert the color to sRGB components, each in the range 0.0 to 1.0.
+    ///
+    /// Colors outside the sRGB gamut are clipped.
+    pub fn as_rgba(&self) -> (f64, f64, f64, f64) {
+        match self.to_space(ColorSpace::Srgb) {
+            Color::RgbaF32(c, _) => {
+                let clip = |x: f32| (x as f64).max(0.0).min(1.0);
+                (clip(c[0]), clip(c[1]), clip(c[2]), clip(c[3]))
+            }
+            // This is unreachable because `to_space` always gives floats.
+            Color::Rgba32(_) => unreachable!(),
+        }
+    }
+
     /// Convert a color value to a 32-bit rgba value.
     pub fn as_rgba32(&self) -> u32 {
         match *self {
             Color::Rgba32(rgba) => rgba,
+            Color::RgbaF32(..) => {
+                let (r, g, b, a) = self.as_rgba();
+                let byte = |x: f64| (x * 255.0).round() as u32;
+                (byte(r) << 24) | (byte(g) << 16) | (byte(b) << 8) | byte(a)
+            }
         }
     }
 
-    /// Opaque white.
-    pub const WHITE: Color = Color::rgba32(0xff_ff_ff_ff);
+    /// Create a color from hue, saturation and lightness.
+    ///
+    /// The hue is in degrees, and the other values are in the range 0.0 to
+    /// 1.0. The result is sRGB, following the CSS `hsl()` function.
+    pub fn from_hsl(h: f64, s: f64, l: f64, a: f64) -> Color {
+        let h = (h - 360.0 * (h / 360.0).floor()) / 30.0;
+        let s = s.max(0.0).min(1.0);
+        let l = l.max(0.0).min(1.0);
+        let chroma = s * l.min(1.0 - l);
+        let f = |n: f64| {
+            let k = (n + h) % 12.0;
+            l - chroma * (k - 3.0).min(9.0 - k).min(1.0).max(-1.0)
+        };
+        Color::rgba_f32(f(0.0), f(8.0), f(4.0), a)
+    }
+
+    /// Convert the color to hue, saturation, lightness and alpha.
+    ///
+    /// This is the inverse of [`from_hsl`](#method.from_hsl). The hue of a
+    /// gray is zero.
+    pub fn to_hsl(&self) -> (f64, f64, f64, f64) {
+        let (r, g, b, a) = self.as_rgba();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = 0.5 * (max + min);
+        let delta = max - min;
+        if delta == 0.0 {
+            return (0.0, 0.0, l, a);
+        }
+        let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            (g - b) / delta
+        } else if max == g {
+            (b - r) / delta + 2.0
+        } else {
+            (r - g) / delta + 4.0
+        };
+        let h = 60.0 * h;
+        (if h < 0.0 { h + 360.0 } else { h }, s, l, a)
+    }
+
+    /// The same color, with the alpha replaced.
+    pub fn with_alpha(&self, a: f64) -> Color {
+        let a = a.max(0.0).min(1.0);
+        match *self {
+            Color::Rgba32(rgba) => Color::rgba32((rgba & !0xff) | (a * 255.0).round() as u32),
+            Color::RgbaF32(c, space) => Color::rgba_in(space, c[0], c[1], c[2], a as f32),
+        }
+    }
+
+    /// Interpolate between two colors.
+    ///
+    /// At `t = 0.0` the result is `self`, and at `t = 1.0` it is `other`.
+    /// The interpolation is in sRGB with premultiplied alpha, the default
+    /// for gradients, so that fading to transparent doesn't change hue.
+    pub fn lerp(&self, other: &Color, t: f64) -> Color {
+        let (r0, g0, b0, a0) = self.as_rgba();
+        let (r1, g1, b1, a1) = other.as_rgba();
+        let a = a0 + t * (a1 - a0);
+        if a <= 0.0 {
+            return Color::TRANSPARENT;
+        }
+        let mix = |x0: f64, x1: f64| (x0 * a0 + t * (x1 * a1 - x0 * a0)) / a;
+        Color::rgba_f32(mix(r0, r1), mix(g0, g1), mix(b0, b1), a)
+    }
+
+    /// Fully transparent black.
+    pub const TRANSPARENT: Color = Color::rgba32(0);
+}
+
+impl Color {
+    /// The bits of the sRGB components, which equality and hashing use.
+    fn canonical_bits(&self) -> [u32; 4] {
+        match self.to_space(ColorSpace::Srgb) {
+            Color::RgbaF32(c, _) => {
+                // `to_space` has replaced NaNs, and adding zero turns negative
+                // zero positive, so equal components have equal bits.
+                let bits = |x: f32| (x + 0.0).to_bits();
+                [bits(c[0]), bits(c[1]), bits(c[2]), bits(c[3])]
+            }
+            // This is unreachable because `to_space` always gives floats.
+            Color::Rgba32(_) => unreachable!(),
+        }
+    }
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Color) -> bool {
+        match (self, other) {
+            (Color::Rgba32(a), Color::Rgba32(b)) => a == b,
+            _ => self.canonical_bits() == other.canonical_bits(),
+        }
+    }
+}
+
+impl Eq for Color {}
+
+impl Hash for Color {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_bits().hash(state);
+    }
+}
+
+/// Parse a CSS color.
+///
+/// This accepts hex colors (`#rgb`, `#rgba`, `#rrggbb` and `#rrggbbaa`),
+/// the `rgb()`, `rgba()`, `hsl()` and `hsla()` functions, with either
+/// commas or spaces between the arguments, and the CSS named colors.
+/// Parsing is case-insensitive.
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Color, Error> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.starts_with('#') {
+            return parse_hex(&s[1..]);
+        }
+        if let Some(color) = named_color(&s) {
+            return Ok(color);
+        }
+        let open = s.find('(').ok_or_else(invalid_color)?;
+        if !s.ends_with(')') {
+            return Err(invalid_color());
+        }
+        let args = s[open + 1..s.len() - 1].replace(|c: char| c == ',' || c == '/', " ");
+        let args = args.split_whitespace().collect::<Vec<_>>();
+        let alpha = match args.len() {
+            3 => 1.0,
+            4 => parse_alpha(args[3])?,
+            _ => return Err(invalid_color()),
+        };
+        match s[..open].trim() {
+            "rgb" | "rgba" => Ok(Color::rgba(
+                parse_channel(args[0])?,
+                parse_channel(args[1])?,
+                parse_channel(args[2])?,
+                alpha,
+            )),
+            "hsl" | "hsla" => Ok(Color::from_hsl(
+                parse_hue(args[0])?,
+                parse_percentage(args[1])?,
+                parse_percentage(args[2])?,
+                alpha,
+            )),
+            _ => Err(invalid_color()),
+        }
+    }
+}
+
+fn invalid_color() -> Error {
+    new_error(ErrorKind::InvalidInput)
+}
+
+fn parse_hex(hex: &str) -> Result<Color, Error> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid_color());
+    }
+    let value = u32::from_str_radix(hex, 16).map_err(|_| invalid_color())?;
+    let nibble = |i: u32| ((value >> (4 * i)) & 15) * 17;
+    let rgba = match hex.len() {
+        3 => (nibble(2) << 24) | (nibble(1) << 16) | (nibble(0) << 8) | 0xff,
+        4 => (nibble(3) << 24) | (nibble(2) << 16) | (nibble(1) << 8) | nibble(0),
+        6 => (value << 8) | 0xff,
+        8 => value,
+        _ => return Err(invalid_color()),
+    };
+    Ok(Color::rgba32(rgba))
+}
+
+fn parse_number(s: &str) -> Result<f64, Error> {
+    match s.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        _ => Err(invalid_color()),
+    }
+}
+
+/// Parse a percentage, as a fraction.
+fn parse_percentage(s: &str) -> Result<f64, Error> {
+    if s.ends_with('%') {
+        Ok(parse_number(&s[..s.len() - 1])? / 100.0)
+    } else {
+        Err(invalid_color())
+    }
+}
+
+/// Parse an rgb channel, which is either a number up to 255 or a percentage.
+fn parse_channel(s: &str) -> Result<f64, Error> {
+    if s.ends_with('%') {
+        parse_percentage(s)
+    } else {
+        Ok(parse_number(s)? / 255.0)
+    }
+}
+
+/// Parse an alpha value, which is either a number up to 1 or a percentage.
+fn parse_alpha(s: &str) -> Result<f64, Error> {
+    if s.ends_with('%') {
+        parse_percentage(s)
+    } else {
+        parse_number(s)
+    }
+}
+
+/// Parse a hue, in degrees.
+fn parse_hue(s: &str) -> Result<f64, Error> {
+    if s.ends_with("deg") {
+        parse_number(&s[..s.len() - 3])
+    } else {
+        parse_number(s)
+    }
+}
+
+macro_rules! named_colors {
+    ($($(#[$attr:meta])* $name:ident = $css:literal, $rgb:expr;)*) => {
+        /// The CSS named colors.
+        impl Color {
+            $(
+                $(#[$attr])*
+                pub const $name: Color = Color::rgb24($rgb);
+            )*
+        }
+
+        /// Look up a CSS named color, which must be lowercase.
+        fn named_color(name: &str) -> Option<Color> {
+            match name {
+                $($css => Some(Color::$name),)*
+                "transparent" => Some(Color::TRANSPARENT),
+                _ => None,
+            }
+        }
+    };
+}
 
+named_colors! {
+    ALICE_BLUE = "aliceblue", 0xf0_f8_ff;
+    ANTIQUE_WHITE = "antiquewhite", 0xfa_eb_d7;
+    AQUA = "aqua", 0x00_ff_ff;
+    AQUAMARINE = "aquamarine", 0x7f_ff_d4;
+    AZURE = "azure", 0xf0_ff_ff;
+    BEIGE = "beige", 0xf5_f5_dc;
+    BISQUE = "bisque", 0xff_e4_c4;
     /// Opaque black.
-    pub const BLACK: Color = Color::rgba32(0x00_00_00_ff);
+    BLACK = "black", 0x00_00_00;
+    BLANCHED_ALMOND = "blanchedalmond", 0xff_eb_cd;
+    BLUE = "blue", 0x00_00_ff;
+    BLUE_VIOLET = "blueviolet", 0x8a_2b_e2;
+    BROWN = "brown", 0xa5_2a_2a;
+    BURLYWOOD = "burlywood", 0xde_b8_87;
+    CADET_BLUE = "cadetblue", 0x5f_9e_a0;
+    CHARTREUSE = "chartreuse", 0x7f_ff_00;
+    CHOCOLATE = "chocolate", 0xd2_69_1e;
+    CORAL = "coral", 0xff_7f_50;
+    CORNFLOWER_BLUE = "cornflowerblue", 0x64_95_ed;
+    CORNSILK = "cornsilk", 0xff_f8_dc;
+    CRIMSON = "crimson", 0xdc_14_3c;
+    CYAN = "cyan", 0x00_ff_ff;
+    DARK_BLUE = "darkblue", 0x00_00_8b;
+    DARK_CYAN = "darkcyan", 0x00_8b_8b;
+    DARK_GOLDENROD = "darkgoldenrod", 0xb8_86_0b;
+    DARK_GRAY = "darkgray", 0xa9_a9_a9;
+    DARK_GREEN = "darkgreen", 0x00_64_00;
+    DARK_GREY = "darkgrey", 0xa9_a9_a9;
+    DARK_KHAKI = "darkkhaki", 0xbd_b7_6b;
+    DARK_MAGENTA = "darkmagenta", 0x8b_00_8b;
+    DARK_OLIVE_GREEN = "darkolivegreen", 0x55_6b_2f;
+    DARK_ORANGE = "darkorange", 0xff_8c_00;
+    DARK_ORCHID = "darkorchid", 0x99_32_cc;
+    DARK_RED = "darkred", 0x8b_00_00;
+    DARK_SALMON = "darksalmon", 0xe9_96_7a;
+    DARK_SEA_GREEN = "darkseagreen", 0x8f_bc_8f;
+    DARK_SLATE_BLUE = "darkslateblue", 0x48_3d_8b;
+    DARK_SLATE_GRAY = "darkslategray", 0x2f_4f_4f;
+    DARK_SLATE_GREY = "darkslategrey", 0x2f_4f_4f;
+    DARK_TURQUOISE = "darkturquoise", 0x00_ce_d1;
+    DARK_VIOLET = "darkviolet", 0x94_00_d3;
+    DEEP_PINK = "deeppink", 0xff_14_93;
+    DEEP_SKY_BLUE = "deepskyblue", 0x00_bf_ff;
+    DIM_GRAY = "dimgray", 0x69_69_69;
+    DIM_GREY = "dimgrey", 0x69_69_69;
+    DODGER_BLUE = "dodgerblue", 0x1e_90_ff;
+    FIREBRICK = "firebrick", 0xb2_22_22;
+    FLORAL_WHITE = "floralwhite", 0xff_fa_f0;
+    FOREST_GREEN = "forestgreen", 0x22_8b_22;
+    FUCHSIA = "fuchsia", 0xff_00_ff;
+    GAINSBORO = "gainsboro", 0xdc_dc_dc;
+    GHOST_WHITE = "ghostwhite", 0xf8_f8_ff;
+    GOLD = "gold", 0xff_d7_00;
+    GOLDENROD = "goldenrod", 0xda_a5_20;
+    GRAY = "gray", 0x80_80_80;
+    GREEN = "green", 0x00_80_00;
+    GREEN_YELLOW = "greenyellow", 0xad_ff_2f;
+    GREY = "grey", 0x80_80_80;
+    HONEYDEW = "honeydew", 0xf0_ff_f0;
+    HOT_PINK = "hotpink", 0xff_69_b4;
+    INDIAN_RED = "indianred", 0xcd_5c_5c;
+    INDIGO = "indigo", 0x4b_00_82;
+    IVORY = "ivory", 0xff_ff_f0;
+    KHAKI = "khaki", 0xf0_e6_8c;
+    LAVENDER = "lavender", 0xe6_e6_fa;
+    LAVENDER_BLUSH = "lavenderblush", 0xff_f0_f5;
+    LAWN_GREEN = "lawngreen", 0x7c_fc_00;
+    LEMON_CHIFFON = "lemonchiffon", 0xff_fa_cd;
+    LIGHT_BLUE = "lightblue", 0xad_d8_e6;
+    LIGHT_CORAL = "lightcoral", 0xf0_80_80;
+    LIGHT_CYAN = "lightcyan", 0xe0_ff_ff;
+    LIGHT_GOLDENROD_YELLOW = "lightgoldenrodyellow", 0xfa_fa_d2;
+    LIGHT_GRAY = "lightgray", 0xd3_d3_d3;
+    LIGHT_GREEN = "lightgreen", 0x90_ee_90;
+    LIGHT_GREY = "lightgrey", 0xd3_d3_d3;
+    LIGHT_PINK = "lightpink", 0xff_b6_c1;
+    LIGHT_SALMON = "lightsalmon", 0xff_a0_7a;
+    LIGHT_SEA_GREEN = "lightseagreen", 0x20_b2_aa;
+    LIGHT_SKY_BLUE = "lightskyblue", 0x87_ce_fa;
+    LIGHT_SLATE_GRAY = "lightslategray", 0x77_88_99;
+    LIGHT_SLATE_GREY = "lightslategrey", 0x77_88_99;
+    LIGHT_STEEL_BLUE = "lightsteelblue", 0xb0_c4_de;
+    LIGHT_YELLOW = "lightyellow", 0xff_ff_e0;
+    LIME = "lime", 0x00_ff_00;
+    // The `_32` looks like a suffix to clippy.
+    #[allow(clippy::mistyped_literal_suffixes)]
+    LIME_GREEN = "limegreen", 0x32_cd_32;
+    LINEN = "linen", 0xfa_f0_e6;
+    MAGENTA = "magenta", 0xff_00_ff;
+    MAROON = "maroon", 0x80_00_00;
+    MEDIUM_AQUAMARINE = "mediumaquamarine", 0x66_cd_aa;
+    MEDIUM_BLUE = "mediumblue", 0x00_00_cd;
+    MEDIUM_ORCHID = "mediumorchid", 0xba_55_d3;
+    MEDIUM_PURPLE = "mediumpurple", 0x93_70_db;
+    MEDIUM_SEA_GREEN = "mediumseagreen", 0x3c_b3_71;
+    MEDIUM_SLATE_BLUE = "mediumslateblue", 0x7b_68_ee;
+    MEDIUM_SPRING_GREEN = "mediumspringgreen", 0x00_fa_9a;
+    MEDIUM_TURQUOISE = "mediumturquoise", 0x48_d1_cc;
+    MEDIUM_VIOLET_RED = "mediumvioletred", 0xc7_15_85;
+    MIDNIGHT_BLUE = "midnightblue", 0x19_19_70;
+    MINT_CREAM = "mintcream", 0xf5_ff_fa;
+    MISTY_ROSE = "mistyrose", 0xff_e4_e1;
+    MOCCASIN = "moccasin", 0xff_e4_b5;
+    NAVAJO_WHITE = "navajowhite", 0xff_de_ad;
+    NAVY = "navy", 0x00_00_80;
+    OLD_LACE = "oldlace", 0xfd_f5_e6;
+    OLIVE = "olive", 0x80_80_00;
+    OLIVE_DRAB = "olivedrab", 0x6b_8e_23;
+    ORANGE = "orange", 0xff_a5_00;
+    ORANGE_RED = "orangered", 0xff_45_00;
+    ORCHID = "orchid", 0xda_70_d6;
+    PALE_GOLDENROD = "palegoldenrod", 0xee_e8_aa;
+    PALE_GREEN = "palegreen", 0x98_fb_98;
+    PALE_TURQUOISE = "paleturquoise", 0xaf_ee_ee;
+    PALE_VIOLET_RED = "palevioletred", 0xdb_70_93;
+    PAPAYA_WHIP = "papayawhip", 0xff_ef_d5;
+    PEACH_PUFF = "peachpuff", 0xff_da_b9;
+    PERU = "peru", 0xcd_85_3f;
+    PINK = "pink", 0xff_c0_cb;
+    PLUM = "plum", 0xdd_a0_dd;
+    POWDER_BLUE = "powderblue", 0xb0_e0_e6;
+    PURPLE = "purple", 0x80_00_80;
+    REBECCA_PURPLE = "rebeccapurple", 0x66_33_99;
+    RED = "red", 0xff_00_00;
+    ROSY_BROWN = "rosybrown", 0xbc_8f_8f;
+    ROYAL_BLUE = "royalblue", 0x41_69_e1;
+    SADDLE_BROWN = "saddlebrown", 0x8b_45_13;
+    SALMON = "salmon", 0xfa_80_72;
+    SANDY_BROWN = "sandybrown", 0xf4_a4_60;
+    SEA_GREEN = "seagreen", 0x2e_8b_57;
+    SEASHELL = "seashell", 0xff_f5_ee;
+    SIENNA = "sienna", 0xa0_52_2d;
+    SILVER = "silver", 0xc0_c0_c0;
+    SKY_BLUE = "skyblue", 0x87_ce_eb;
+    SLATE_BLUE = "slateblue", 0x6a_5a_cd;
+    SLATE_GRAY = "slategray", 0x70_80_90;
+    SLATE_GREY = "slategrey", 0x70_80_90;
+    SNOW = "snow", 0xff_fa_fa;
+    SPRING_GREEN = "springgreen", 0x00_ff_7f;
+    STEEL_BLUE = "steelblue", 0x46_82_b4;
+    TAN = "tan", 0xd2_b4_8c;
+    TEAL = "teal", 0x00_80_80;
+    THISTLE = "thistle", 0xd8_bf_d8;
+    TOMATO = "tomato", 0xff_63_47;
+    TURQUOISE = "turquoise", 0x40_e0_d0;
+    VIOLET = "violet", 0xee_82_ee;
+    WHEAT = "wheat", 0xf5_de_b3;
+    /// Opaque white.
+    WHITE = "white", 0xff_ff_ff;
+    WHITE_SMOKE = "whitesmoke", 0xf5_f5_f5;
+    YELLOW = "yellow", 0xff_ff_00;
+    #[allow(clippy::mistyped_literal_suffixes)]
+    YELLOW_GREEN = "yellowgreen", 0x9a_cd_32;
+}
+
+/// The transform from linear Display P3 to linear sRGB.
+const P3_TO_SRGB: [[f64; 3]; 3] = [
+    [1.224_940_176, -0.224_940_176, 0.0],
+    [-0.042_056_955, 1.042_056_955, 0.0],
+    [-0.019_637_555, -0.078_636_046, 1.098_273_600],
+];
+
+/// The transform from linear sRGB to linear Display P3.
+const SRGB_TO_P3: [[f64; 3]; 3] = [
+    [0.822_461_969, 0.177_538_031, 0.0],
+    [0.033_194_199, 0.966_805_801, 0.0],
+    [0.017_082_631, 0.072_397_441, 0.910_519_929],
+];
+
+/// Convert components in a space to linear sRGB, keeping alpha.
+fn to_linear_srgb(c: [f64; 4], space: ColorSpace) -> [f64; 4] {
+    match space {
+        ColorSpace::Srgb => [
+            srgb_to_linear(c[0]),
+            srgb_to_linear(c[1]),
+            srgb_to_linear(c[2]),
+            c[3],
+        ],
+        ColorSpace::LinearSrgb => c,
+        ColorSpace::DisplayP3 => {
+            let linear = [
+                srgb_to_linear(c[0]),
+                srgb_to_linear(c[1]),
+                srgb_to_linear(c[2]),
+            ];
+            let rgb = mul_matrix(&P3_TO_SRGB, linear);
+            [rgb[0], rgb[1], rgb[2], c[3]]
+        }
+    }
+}
+
+/// Convert linear sRGB components to a space, keeping alpha.
+fn from_linear_srgb(c: [f64; 4], space: ColorSpace) -> [f64; 4] {
+    match space {
+        ColorSpace::Srgb => [
+            linear_to_srgb(c[0]),
+            linear_to_srgb(c[1]),
+            linear_to_srgb(c[2]),
+            c[3],
+        ],
+        ColorSpace::LinearSrgb => c,
+        ColorSpace::DisplayP3 => {
+            let rgb = mul_matrix(&SRGB_TO_P3, [c[0], c[1], c[2]]);
+            [
+                linear_to_srgb(rgb[0]),
+                linear_to_srgb(rgb[1]),
+                linear_to_srgb(rgb[2]),
+                c[3],
+            ]
+        }
+    }
+}
+
+fn mul_matrix(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    let row = |r: &[f64; 3]| r[0] * v[0] + r[1] * v[1] + r[2] * v[2];
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+/// The sRGB transfer function, inverted.
+///
+/// This is extended to negative values by symmetry, for out of gamut colors.
+pub(crate) fn srgb_to_linear(x: f64) -> f64 {
+    if x < 0.0 {
+        -srgb_to_linear(-x)
+    } else if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The sRGB transfer function.
+///
+/// This is extended to negative values by symmetry, for out of gamut colors.
+pub(crate) fn linear_to_srgb(x: f64) -> f64 {
+    if x < 0.0 {
+        -linear_to_srgb(-x)
+    } else if x <= 0.003_130_8 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
 }
@@ -0,0 +1,44 @@
+//! Documents with multiple pages.
+
+use kurbo::Vec2;
+
+use crate::{Error, RenderContext};
+
+/// A target for drawing a document with multiple pages.
+///
+/// Each page is drawn with a fresh render context, returned by
+/// [`begin_page`](#tymethod.begin_page). When the page is drawn, finish the
+/// context, drop it and call [`end_page`](#tymethod.end_page). For example:
+///
+/// ```ignore
+/// for page in pages {
+///     let mut rc = document.begin_page((612.0, 792.0).into())?;
+///     page.draw(&mut rc)?;
+///     rc.finish()?;
+///     drop(rc);
+///     document.end_page()?;
+/// }
+/// document.finish()?;
+/// ```
+///
+/// Beginning a page while one is open, or ending a page that wasn't begun,
+/// is a stack unbalance error.
+pub trait DocumentTarget {
+    /// The render context for drawing a page.
+    type Context<'a>: RenderContext
+    where
+        Self: 'a;
+
+    /// Begin a new page.
+    ///
+    /// The `size` is the width and height of the page, in the units of the
+    /// target. User space initially has its origin at the top left of the
+    /// page, and nothing carries over from the previous page.
+    fn begin_page(&mut self, size: Vec2) -> Result<Self::Context<'_>, Error>;
+
+    /// Finish the current page.
+    fn end_page(&mut self) -> Result<(), Error>;
+
+    /// Finish the document, writing any output that is still pending.
+    fn finish(self) -> Result<(), Error>;
+}
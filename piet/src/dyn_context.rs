@@ -0,0 +1,530 @@
+//! An object-safe version of `RenderContext`.
+
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use kurbo::{Affine, BezPath, Rect, Shape, Vec2};
+
+use crate::{
+    new_error, BlendMode, Color, Error, ErrorKind, ExtendMode, FillRule, FontBuilder, Gradient,
+    ImageFormat, InterpolationMode, MaskMode, RenderContext, StrokeStyle, Text, TextLayout,
+    TextLayoutBuilder,
+};
+
+const TOLERANCE: f64 = 1e-3;
+
+/// An object-safe version of `Shape`, for the arguments of
+/// [`DynRenderContext`](trait.DynRenderContext.html).
+///
+/// This is implemented for every `Shape`, so `&rect` can be passed as a
+/// `&dyn DynShape`.
+pub trait DynShape {
+    /// Convert to a Bézier path, approximating curves to within `tolerance`.
+    fn dyn_bez_path(&self, tolerance: f64) -> BezPath;
+}
+
+impl<S: Shape> DynShape for S {
+    fn dyn_bez_path(&self, tolerance: f64) -> BezPath {
+        let mut path = BezPath::new();
+        for el in self.to_bez_path(tolerance) {
+            path.push(el);
+        }
+        path
+    }
+}
+
+/// A brush of a [`DynRenderContext`](trait.DynRenderContext.html).
+///
+/// Handles are only valid with the context that created them, until they
+/// are released.
+#[derive(Clone, Debug)]
+pub struct DynBrush(Key);
+
+/// An image of a [`DynRenderContext`](trait.DynRenderContext.html).
+#[derive(Clone, Debug)]
+pub struct DynImage(Key);
+
+/// A font of a [`DynRenderContext`](trait.DynRenderContext.html).
+#[derive(Clone, Debug)]
+pub struct DynFont(Key);
+
+/// A text layout of a [`DynRenderContext`](trait.DynRenderContext.html).
+#[derive(Clone, Debug)]
+pub struct DynTextLayout {
+    key: Key,
+    width: f64,
+}
+
+impl DynTextLayout {
+    /// The width of the layout.
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+}
+
+/// A version of [`RenderContext`](trait.RenderContext.html) that can be used
+/// as a trait object.
+///
+/// The methods are the same as those of `RenderContext`, with concrete
+/// argument types in place of generic ones, and handles in place of the
+/// associated types. Text resources are created directly by the context,
+/// without builders.
+///
+/// Any `RenderContext` can be used as a `DynRenderContext` by wrapping it in
+/// a [`DynAdapter`](struct.DynAdapter.html). This lets drawing code that is
+/// compiled separately from the backend draw through a
+/// `&mut dyn DynRenderContext`.
+pub trait DynRenderContext {
+    fn status(&mut self) -> Result<(), Error>;
+
+    fn solid_brush(&mut self, color: Color) -> DynBrush;
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<DynBrush, Error>;
+
+    fn image_brush(
+        &mut self,
+        image: &DynImage,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<DynBrush, Error>;
+
+    fn clear(&mut self, color: Color);
+
+    fn stroke(
+        &mut self,
+        shape: &dyn DynShape,
+        brush: &DynBrush,
+        width: f64,
+        style: Option<&StrokeStyle>,
+    );
+
+    fn fill(&mut self, shape: &dyn DynShape, brush: &DynBrush, fill_rule: FillRule);
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &DynBrush);
+
+    fn draw_shadow(&mut self, shape: &dyn DynShape, offset: Vec2, blur_radius: f64, color: Color);
+
+    fn clip(&mut self, shape: &dyn DynShape, fill_rule: FillRule);
+
+    fn mask(&mut self, mask: &DynImage, rect: Rect, mode: MaskMode);
+
+    fn begin_mask(&mut self) -> Result<(), Error>;
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error>;
+
+    /// Create a font, as `new_font_by_name` and `build` on the `Text` of a
+    /// `RenderContext` do.
+    fn new_font(&mut self, name: &str, size: f64) -> Result<DynFont, Error>;
+
+    /// Create a text layout, as `new_text_layout` and `build` on the `Text`
+    /// of a `RenderContext` do.
+    fn new_text_layout(&mut self, font: &DynFont, text: &str) -> Result<DynTextLayout, Error>;
+
+    fn draw_text(&mut self, layout: &DynTextLayout, pos: Vec2, brush: &DynBrush);
+
+    fn save(&mut self) -> Result<(), Error>;
+
+    fn restore(&mut self) -> Result<(), Error>;
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<&dyn DynShape>) -> Result<(), Error>;
+
+    fn pop_layer(&mut self) -> Result<(), Error>;
+
+    fn finish(&mut self) -> Result<(), Error>;
+
+    fn transform(&mut self, transform: Affine);
+
+    fn set_blend_mode(&mut self, mode: BlendMode);
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<DynImage, Error>;
+
+    fn draw_image(&mut self, image: &DynImage, rect: Rect, interp: InterpolationMode);
+
+    /// Free the resources behind a brush. Using the handle afterwards is an
+    /// error.
+    fn release_brush(&mut self, brush: &DynBrush);
+
+    /// Free the resources behind an image. Brushes made from it stay valid.
+    fn release_image(&mut self, image: &DynImage);
+
+    /// Free the resources behind a font. Layouts made with it stay valid.
+    fn release_font(&mut self, font: &DynFont);
+
+    /// Free the resources behind a text layout.
+    fn release_text_layout(&mut self, layout: &DynTextLayout);
+}
+
+impl<'a> dyn DynRenderContext + 'a {
+    /// Do graphics operations with the context state saved and then restored.
+    ///
+    /// See [`RenderContext::with_save`](trait.RenderContext.html#method.with_save).
+    pub fn with_save(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.save()?;
+        // Always try to restore the stack, even if `f` errored.
+        f(self).and(self.restore())
+    }
+
+    /// Do graphics operations in a layer.
+    ///
+    /// See [`RenderContext::with_layer`](trait.RenderContext.html#method.with_layer).
+    pub fn with_layer(
+        &mut self,
+        opacity: f64,
+        clip: Option<&dyn DynShape>,
+        f: impl FnOnce(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.push_layer(opacity, clip)?;
+        // Always try to pop the layer, even if `f` errored.
+        f(self).and(self.pop_layer())
+    }
+}
+
+/// The source of `DynAdapter` ids, so handles can't be used with another
+/// adapter.
+static NEXT_ADAPTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Where a handle's resource lives in its adapter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Key {
+    adapter: usize,
+    index: usize,
+    /// Bumped each time a slot is reused, so released handles stay invalid.
+    generation: u64,
+}
+
+struct Slot<T> {
+    generation: u64,
+    value: Option<T>,
+}
+
+/// Resources behind handles, reusing the slots of released ones.
+struct Arena<T> {
+    adapter: usize,
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+}
+
+impl<T> Arena<T> {
+    fn new(adapter: usize) -> Arena<T> {
+        Arena {
+            adapter,
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, value: T) -> Key {
+        let index = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.generation += 1;
+                slot.value = Some(value);
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                self.slots.len() - 1
+            }
+        };
+        Key {
+            adapter: self.adapter,
+            index,
+            generation: self.slots[index].generation,
+        }
+    }
+
+    fn get(&self, key: Key) -> Option<&T> {
+        if key.adapter != self.adapter {
+            return None;
+        }
+        self.slots
+            .get(key.index)
+            .filter(|slot| slot.generation == key.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    /// Drop the value behind a key, returning whether there was one.
+    fn remove(&mut self, key: Key) -> bool {
+        if self.get(key).is_none() {
+            return false;
+        }
+        self.slots[key.index].value = None;
+        self.free.push(key.index);
+        true
+    }
+
+    fn clear(&mut self) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.value.take().is_some() {
+                self.free.push(index);
+            }
+        }
+    }
+}
+
+/// A `DynRenderContext` that draws on a `RenderContext`.
+///
+/// The adapter owns the backend resources behind the handles it hands out.
+/// They live until they are released with the `release_*` methods, or all at
+/// once with [`clear_resources`](#method.clear_resources), or until the
+/// adapter is dropped; drawing code that makes resources in a loop should
+/// release them to keep memory bounded.
+///
+/// Using a handle that the adapter didn't create, or one that has been
+/// released, is an [`InvalidInput`](enum.ErrorKind.html#variant.InvalidInput)
+/// error, reported by the next `status` or `finish` for methods that don't
+/// return a `Result`.
+pub struct DynAdapter<R: RenderContext> {
+    inner: R,
+    brushes: Arena<R::Brush>,
+    images: Arena<R::Image>,
+    fonts: Arena<<R::Text as Text>::Font>,
+    layouts: Arena<R::TextLayout>,
+    err: Result<(), Error>,
+}
+
+impl<R: RenderContext> DynAdapter<R> {
+    /// Wrap a render context.
+    pub fn new(inner: R) -> DynAdapter<R> {
+        let id = NEXT_ADAPTER.fetch_add(1, Ordering::Relaxed);
+        DynAdapter {
+            inner,
+            brushes: Arena::new(id),
+            images: Arena::new(id),
+            fonts: Arena::new(id),
+            layouts: Arena::new(id),
+            err: Ok(()),
+        }
+    }
+
+    /// The wrapped render context.
+    pub fn inner(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the render context.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Release every resource, invalidating all handles handed out so far.
+    pub fn clear_resources(&mut self) {
+        self.brushes.clear();
+        self.images.clear();
+        self.fonts.clear();
+        self.layouts.clear();
+    }
+
+    fn add_brush(&mut self, brush: R::Brush) -> DynBrush {
+        DynBrush(self.brushes.insert(brush))
+    }
+}
+
+fn invalid_handle() -> Error {
+    new_error(ErrorKind::InvalidInput)
+}
+
+/// Record an invalid handle, keeping only the first error.
+fn note_invalid(err: &mut Result<(), Error>) {
+    if err.is_ok() {
+        *err = Err(invalid_handle());
+    }
+}
+
+impl<R: RenderContext> DynRenderContext for DynAdapter<R> {
+    fn status(&mut self) -> Result<(), Error> {
+        let err = mem::replace(&mut self.err, Ok(()));
+        err.and(self.inner.status())
+    }
+
+    fn solid_brush(&mut self, color: Color) -> DynBrush {
+        let brush = self.inner.solid_brush(color);
+        self.add_brush(brush)
+    }
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<DynBrush, Error> {
+        let brush = self.inner.gradient(gradient)?;
+        Ok(self.add_brush(brush))
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &DynImage,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<DynBrush, Error> {
+        let image = self.images.get(image.0).ok_or_else(invalid_handle)?;
+        let brush = self
+            .inner
+            .image_brush(image, extend_x, extend_y, transform, interp)?;
+        Ok(self.add_brush(brush))
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.inner.clear(color);
+    }
+
+    fn stroke(
+        &mut self,
+        shape: &dyn DynShape,
+        brush: &DynBrush,
+        width: f64,
+        style: Option<&StrokeStyle>,
+    ) {
+        let path = shape.dyn_bez_path(TOLERANCE);
+        match self.brushes.get(brush.0) {
+            Some(brush) => self.inner.stroke(&path, brush, width, style),
+            None => note_invalid(&mut self.err),
+        }
+    }
+
+    fn fill(&mut self, shape: &dyn DynShape, brush: &DynBrush, fill_rule: FillRule) {
+        let path = shape.dyn_bez_path(TOLERANCE);
+        match self.brushes.get(brush.0) {
+            Some(brush) => self.inner.fill(&path, brush, fill_rule),
+            None => note_invalid(&mut self.err),
+        }
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &DynBrush) {
+        match self.brushes.get(brush.0) {
+            Some(brush) => self.inner.blurred_rect(rect, blur_radius, brush),
+            None => note_invalid(&mut self.err),
+        }
+    }
+
+    fn draw_shadow(&mut self, shape: &dyn DynShape, offset: Vec2, blur_radius: f64, color: Color) {
+        let path = shape.dyn_bez_path(TOLERANCE);
+        self.inner.draw_shadow(&path, offset, blur_radius, color);
+    }
+
+    fn clip(&mut self, shape: &dyn DynShape, fill_rule: FillRule) {
+        let path = shape.dyn_bez_path(TOLERANCE);
+        self.inner.clip(&path, fill_rule);
+    }
+
+    fn mask(&mut self, mask: &DynImage, rect: Rect, mode: MaskMode) {
+        match self.images.get(mask.0) {
+            Some(mask) => self.inner.mask(mask, rect, mode),
+            None => note_invalid(&mut self.err),
+        }
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        self.inner.begin_mask()
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        self.inner.end_mask(mode)
+    }
+
+    fn new_font(&mut self, name: &str, size: f64) -> Result<DynFont, Error> {
+        let font = self.inner.text().new_font_by_name(name, size)?.build()?;
+        Ok(DynFont(self.fonts.insert(font)))
+    }
+
+    fn new_text_layout(&mut self, font: &DynFont, text: &str) -> Result<DynTextLayout, Error> {
+        let font = self.fonts.get(font.0).ok_or_else(invalid_handle)?;
+        let layout = self.inner.text().new_text_layout(font, text)?.build()?;
+        let width = layout.width().into();
+        Ok(DynTextLayout {
+            key: self.layouts.insert(layout),
+            width,
+        })
+    }
+
+    fn draw_text(&mut self, layout: &DynTextLayout, pos: Vec2, brush: &DynBrush) {
+        match (self.layouts.get(layout.key), self.brushes.get(brush.0)) {
+            (Some(layout), Some(brush)) => self.inner.draw_text(layout, pos, brush),
+            _ => note_invalid(&mut self.err),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.inner.save()
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        self.inner.restore()
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<&dyn DynShape>) -> Result<(), Error> {
+        let clip = clip.map(|shape| shape.dyn_bez_path(TOLERANCE));
+        self.inner.push_layer(opacity, clip.as_ref())
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        self.inner.pop_layer()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let err = mem::replace(&mut self.err, Ok(()));
+        err.and(self.inner.finish())
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        self.inner.transform(transform);
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.inner.set_blend_mode(mode);
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<DynImage, Error> {
+        let image = self.inner.make_image(width, height, buf, format)?;
+        Ok(DynImage(self.images.insert(image)))
+    }
+
+    fn draw_image(&mut self, image: &DynImage, rect: Rect, interp: InterpolationMode) {
+        match self.images.get(image.0) {
+            Some(image) => self.inner.draw_image(image, rect, interp),
+            None => note_invalid(&mut self.err),
+        }
+    }
+
+    fn release_brush(&mut self, brush: &DynBrush) {
+        if !self.brushes.remove(brush.0) {
+            note_invalid(&mut self.err);
+        }
+    }
+
+    fn release_image(&mut self, image: &DynImage) {
+        if !self.images.remove(image.0) {
+            note_invalid(&mut self.err);
+        }
+    }
+
+    fn release_font(&mut self, font: &DynFont) {
+        if !self.fonts.remove(font.0) {
+            note_invalid(&mut self.err);
+        }
+    }
+
+    fn release_text_layout(&mut self, layout: &DynTextLayout) {
+        if !self.layouts.remove(layout.key) {
+            note_invalid(&mut self.err);
+        }
+    }
+}
@@ -3,6 +3,9 @@
 use std::fmt;
 
 /// An error that can occur while rendering 2D graphics.
+///
+/// Errors are `Send` and `Sync`, so they can be moved across threads. Use
+/// [`kind`](#method.kind) to find out what went wrong.
 #[derive(Debug)]
 pub struct Error(Box<ErrorKind>);
 
@@ -11,7 +14,30 @@ pub enum ErrorKind {
     InvalidInput,
     NotSupported,
     StackUnbalance,
-    BackendError(Box<dyn std::error::Error>),
+    /// No font could be found for the requested family.
+    MissingFont(String),
+    /// The length of an image buffer doesn't match its size and format.
+    InvalidImageData {
+        /// The expected length of the buffer, in bytes.
+        expected: usize,
+        /// The actual length of the buffer, in bytes.
+        actual: usize,
+    },
+    /// A call on a [`ValidatingContext`](struct.ValidatingContext.html) had
+    /// invalid arguments.
+    InvalidCall {
+        /// The name of the method.
+        method: &'static str,
+        /// What was wrong with the arguments.
+        reason: String,
+    },
+    /// A gradient can't be drawn, for example because it has no stops.
+    InvalidGradient,
+    /// The backend couldn't be initialized, for example because a system
+    /// library or device is missing.
+    BackendUnavailable(Box<dyn std::error::Error + Send + Sync>),
+    /// An error reported by the backend while drawing.
+    BackendError(Box<dyn std::error::Error + Send + Sync>),
     #[doc(hidden)]
     _NonExhaustive,
 }
@@ -21,12 +47,33 @@ pub fn new_error(kind: ErrorKind) -> Error {
     Error(Box::new(kind))
 }
 
+impl Error {
+    /// The kind of error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self.0 {
             ErrorKind::InvalidInput => write!(f, "Invalid input"),
             ErrorKind::NotSupported => write!(f, "Option not supported"),
             ErrorKind::StackUnbalance => write!(f, "Stack unbalanced"),
+            ErrorKind::MissingFont(ref family) => write!(f, "Missing font: {}", family),
+            ErrorKind::InvalidImageData { expected, actual } => write!(
+                f,
+                "Invalid image data: expected {} bytes, got {}",
+                expected, actual
+            ),
+            ErrorKind::InvalidCall { method, ref reason } => {
+                write!(f, "Invalid call to {}: {}", method, reason)
+            }
+            ErrorKind::InvalidGradient => write!(f, "Invalid gradient"),
+            ErrorKind::BackendUnavailable(ref e) => {
+                write!(f, "Backend unavailable: ")?;
+                e.fmt(f)
+            }
             ErrorKind::BackendError(ref e) => {
                 write!(f, "Backend error: ")?;
                 e.fmt(f)
@@ -36,10 +83,19 @@ impl fmt::Display for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self.0 {
+            ErrorKind::BackendUnavailable(ref e) | ErrorKind::BackendError(ref e) => {
+                Some(e.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
 
-impl From<Box<dyn std::error::Error>> for Error {
-    fn from(e: Box<dyn std::error::Error>) -> Error {
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(e: Box<dyn std::error::Error + Send + Sync>) -> Error {
         new_error(ErrorKind::BackendError(e))
     }
 }
@@ -1,20 +1,76 @@
 //! Gradient specifications.
 
-use kurbo::Vec2;
+use kurbo::{Affine, Rect, Vec2};
 
+use crate::color::{linear_to_srgb, srgb_to_linear};
 use crate::Color;
 
 /// Specification of a gradient.
-#[derive(Clone)]
+///
+/// All gradient specifications have an optional transform, which maps the
+/// coordinates of the gradient to user space. This allows radial gradients
+/// to be elliptical, and all gradients to be rotated or skewed independently
+/// of the geometry they fill. It is applied at fill time by the web backend,
+/// which doesn't support it when stroking.
+///
+/// They also have [units](enum.GradientUnits.html), so that one brush can be
+/// used for shapes of different sizes.
+#[derive(Clone, Debug)]
 pub enum Gradient {
     /// A linear gradient.
     Linear(LinearGradient),
     /// A radial gradient.
     Radial(RadialGradient),
+    /// A sweep gradient.
+    Sweep(SweepGradient),
+}
+
+impl Gradient {
+    /// The units of the gradient.
+    pub fn units(&self) -> GradientUnits {
+        match self {
+            Gradient::Linear(linear) => linear.units,
+            Gradient::Radial(radial) => radial.units,
+            Gradient::Sweep(sweep) => sweep.units,
+        }
+    }
+
+    /// The stops of the gradient.
+    pub fn stops(&self) -> &[GradientStop] {
+        match self {
+            Gradient::Linear(linear) => &linear.stops,
+            Gradient::Radial(radial) => &radial.stops,
+            Gradient::Sweep(sweep) => &sweep.stops,
+        }
+    }
+
+    /// Resolve the units of the gradient, for drawing something with the given
+    /// bounding box.
+    ///
+    /// The result is in user space units, with the bounding box folded into
+    /// its transform. This is `None` if nothing should be drawn.
+    pub fn resolve_units(&self, bbox: Rect) -> Option<Gradient> {
+        let mut gradient = self.clone();
+        let (units, transform) = match gradient {
+            Gradient::Linear(ref mut linear) => (&mut linear.units, &mut linear.transform),
+            Gradient::Radial(ref mut radial) => (&mut radial.units, &mut radial.transform),
+            Gradient::Sweep(ref mut sweep) => (&mut sweep.units, &mut sweep.transform),
+        };
+        if *units == GradientUnits::ObjectBoundingBox {
+            if bbox.width() <= 0.0 || bbox.height() <= 0.0 {
+                return None;
+            }
+            let bbox_transform =
+                Affine::new([bbox.width(), 0.0, 0.0, bbox.height(), bbox.x0, bbox.y0]);
+            *transform = Some(bbox_transform * transform.unwrap_or_default());
+            *units = GradientUnits::UserSpace;
+        }
+        Some(gradient)
+    }
 }
 
 /// Specification of a linear gradient.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct LinearGradient {
     /// The start point (corresponding to pos 0.0).
     pub start: Vec2,
@@ -24,29 +80,298 @@ pub struct LinearGradient {
     ///
     /// There must be at least two for the gradient to be valid.
     pub stops: Vec<GradientStop>,
+    /// How the gradient is extended beyond the start and end points.
+    pub extend: ExtendMode,
+    /// The transform from gradient coordinates to user space, if any.
+    ///
+    /// With bounding box units, this is applied before mapping the unit
+    /// square to the bounding box.
+    pub transform: Option<Affine>,
+    /// The units of the coordinates.
+    pub units: GradientUnits,
+    /// The color space for interpolating between stops.
+    pub interpolation: ColorInterpolation,
 }
 
 /// Specification of a radial gradient.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct RadialGradient {
     /// The center.
     pub center: Vec2,
     /// The offset of the origin relative to the center.
+    ///
+    /// With a nonzero offset, this is a two-point conical gradient, going
+    /// from the start circle around the origin to the end circle around the
+    /// center.
     pub origin_offset: Vec2,
+    /// The radius of the start circle.
+    ///
+    /// The circle with this radius from the origin corresponds to pos 0.0.
+    /// This is usually 0.0, which makes the origin the focal point. Not
+    /// supported by the Direct2D backend.
+    pub start_radius: f64,
     /// The radius.
     ///
     /// The circle with this radius from the center corresponds to pos 1.0.
-    // TODO: investigate elliptical radius
+    /// Use the transform for an elliptical gradient.
     pub radius: f64,
     /// The stops (see similar field in [`LinearGradient`](#struct.LinearGradient.html)).
     pub stops: Vec<GradientStop>,
+    /// How the gradient is extended beyond the circle.
+    pub extend: ExtendMode,
+    /// The transform from gradient coordinates to user space, if any.
+    ///
+    /// With bounding box units, this is applied before mapping the unit
+    /// square to the bounding box.
+    pub transform: Option<Affine>,
+    /// The units of the coordinates.
+    pub units: GradientUnits,
+    /// The color space for interpolating between stops.
+    pub interpolation: ColorInterpolation,
+}
+
+/// Specification of a sweep gradient, also known as a conic gradient.
+///
+/// The color varies with the angle around the center. Angles are in radians,
+/// measured from the positive x axis towards the positive y axis, and the
+/// angle of a point is taken in the range 0 to 2π.
+///
+/// Not supported by the Direct2D and web backends.
+#[derive(Clone, Debug)]
+pub struct SweepGradient {
+    /// The center.
+    pub center: Vec2,
+    /// The start angle (corresponding to pos 0.0).
+    pub start_angle: f64,
+    /// The end angle (corresponding to pos 1.0).
+    pub end_angle: f64,
+    /// The stops (see similar field in [`LinearGradient`](#struct.LinearGradient.html)).
+    pub stops: Vec<GradientStop>,
+    /// How the gradient is extended beyond the start and end angles.
+    pub extend: ExtendMode,
+    /// The transform from gradient coordinates to user space, if any.
+    ///
+    /// With bounding box units, this is applied before mapping the unit
+    /// square to the bounding box.
+    pub transform: Option<Affine>,
+    /// The units of the coordinates.
+    pub units: GradientUnits,
+    /// The color space for interpolating between stops.
+    pub interpolation: ColorInterpolation,
+}
+
+impl LinearGradient {
+    /// Create a linear gradient, with the default extend mode, units and
+    /// interpolation, and no transform.
+    pub fn new(start: Vec2, end: Vec2, stops: Vec<GradientStop>) -> LinearGradient {
+        LinearGradient {
+            start,
+            end,
+            stops,
+            extend: ExtendMode::default(),
+            transform: None,
+            units: GradientUnits::default(),
+            interpolation: ColorInterpolation::default(),
+        }
+    }
+}
+
+impl RadialGradient {
+    /// Create a radial gradient, with the origin at the center, a start
+    /// radius of zero, the default extend mode, units and interpolation, and
+    /// no transform.
+    pub fn new(center: Vec2, radius: f64, stops: Vec<GradientStop>) -> RadialGradient {
+        RadialGradient {
+            center,
+            origin_offset: Vec2::new(0.0, 0.0),
+            start_radius: 0.0,
+            radius,
+            stops,
+            extend: ExtendMode::default(),
+            transform: None,
+            units: GradientUnits::default(),
+            interpolation: ColorInterpolation::default(),
+        }
+    }
+}
+
+impl SweepGradient {
+    /// Create a sweep gradient, with the default extend mode, units and
+    /// interpolation, and no transform.
+    pub fn new(
+        center: Vec2,
+        start_angle: f64,
+        end_angle: f64,
+        stops: Vec<GradientStop>,
+    ) -> SweepGradient {
+        SweepGradient {
+            center,
+            start_angle,
+            end_angle,
+            stops,
+            extend: ExtendMode::default(),
+            transform: None,
+            units: GradientUnits::default(),
+            interpolation: ColorInterpolation::default(),
+        }
+    }
+}
+
+/// The coordinate system of a gradient specification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientUnits {
+    /// User space, at the time the gradient is used. This is the default.
+    UserSpace,
+    /// Relative to the bounding box of what is being drawn.
+    ///
+    /// (0, 0) is the top left of the bounding box and (1, 1) is the bottom
+    /// right, like SVG's `objectBoundingBox`. The bounding box of a shape is
+    /// its geometry, not including the stroke width; the bounding box of
+    /// text is the width of the layout and the ascent and descent of the font.
+    /// Nothing is drawn if the bounding box is empty.
+    ObjectBoundingBox,
+}
+
+impl Default for GradientUnits {
+    fn default() -> GradientUnits {
+        GradientUnits::UserSpace
+    }
+}
+
+/// The color space in which a gradient interpolates between its stops.
+///
+/// Backends implement the spaces they don't support natively by adding
+/// stops, see [`expand_gradient_stops`](fn.expand_gradient_stops.html).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorInterpolation {
+    /// sRGB with premultiplied alpha, as in CSS. This is the default.
+    PremultipliedSrgb,
+    /// sRGB with separate alpha, as in SVG.
+    UnpremultipliedSrgb,
+    /// Linear-light RGB with premultiplied alpha.
+    LinearRgb,
+}
+
+impl Default for ColorInterpolation {
+    fn default() -> ColorInterpolation {
+        ColorInterpolation::PremultipliedSrgb
+    }
+}
+
+/// How a gradient is extended outside the range of its stops.
+///
+/// Note that the web backend only supports `Pad`, as canvas gradients can't
+/// repeat; the other modes fall back to it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtendMode {
+    /// Extend the color of the nearest stop. This is the default.
+    Pad,
+    /// Repeat the gradient.
+    Repeat,
+    /// Repeat the gradient, reversing every other repetition.
+    Reflect,
+}
+
+impl Default for ExtendMode {
+    fn default() -> ExtendMode {
+        ExtendMode::Pad
+    }
 }
 
 /// Specification of a gradient stop.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct GradientStop {
     /// The coordinate of the stop.
     pub pos: f32,
     /// The color at that stop.
     pub color: Color,
 }
+
+/// The number of intervals each expanded stop interval is divided into.
+const EXPANSION_STEPS: usize = 16;
+
+/// Expand gradient stops for interpolation in a color space other than the
+/// one the backend uses natively.
+///
+/// Stops are added in the intervals where the two spaces give different
+/// results, so that interpolating the result in `native` closely approximates
+/// interpolating the original stops in `interpolation`.
+pub fn expand_gradient_stops(
+    stops: &[GradientStop],
+    interpolation: ColorInterpolation,
+    native: ColorInterpolation,
+) -> Vec<GradientStop> {
+    let mut result = Vec::with_capacity(stops.len());
+    for (i, stop) in stops.iter().enumerate() {
+        if i > 0 {
+            let prev = &stops[i - 1];
+            if needs_expansion(prev, stop, interpolation, native) {
+                let c0 = to_interpolation_space(&prev.color, interpolation);
+                let c1 = to_interpolation_space(&stop.color, interpolation);
+                for j in 1..EXPANSION_STEPS {
+                    let t = j as f64 / EXPANSION_STEPS as f64;
+                    let mut c = c0;
+                    for (x, x1) in c.iter_mut().zip(&c1) {
+                        *x += t * (x1 - *x);
+                    }
+                    result.push(GradientStop {
+                        pos: prev.pos + (t as f32) * (stop.pos - prev.pos),
+                        color: from_interpolation_space(c, interpolation),
+                    });
+                }
+            }
+        }
+        result.push(stop.clone());
+    }
+    result
+}
+
+fn needs_expansion(
+    s0: &GradientStop,
+    s1: &GradientStop,
+    interpolation: ColorInterpolation,
+    native: ColorInterpolation,
+) -> bool {
+    let (rgba0, rgba1) = (s0.color.as_rgba(), s1.color.as_rgba());
+    if interpolation == native || rgba0 == rgba1 || s0.pos == s1.pos {
+        return false;
+    }
+    if interpolation == ColorInterpolation::LinearRgb || native == ColorInterpolation::LinearRgb {
+        return true;
+    }
+    // Premultiplied and separate alpha only differ when alpha changes.
+    rgba0.3 != rgba1.3
+}
+
+/// Convert a color to components that interpolate linearly in the space.
+fn to_interpolation_space(color: &Color, interpolation: ColorInterpolation) -> [f64; 4] {
+    let (r, g, b, a) = color.as_rgba();
+    let mut c = [r, g, b, a];
+    if interpolation == ColorInterpolation::LinearRgb {
+        for x in &mut c[..3] {
+            *x = srgb_to_linear(*x);
+        }
+    }
+    if interpolation != ColorInterpolation::UnpremultipliedSrgb {
+        let alpha = c[3];
+        for x in &mut c[..3] {
+            *x *= alpha;
+        }
+    }
+    c
+}
+
+fn from_interpolation_space(mut c: [f64; 4], interpolation: ColorInterpolation) -> Color {
+    if interpolation != ColorInterpolation::UnpremultipliedSrgb && c[3] > 0.0 {
+        let alpha = c[3];
+        for x in &mut c[..3] {
+            *x /= alpha;
+        }
+    }
+    if interpolation == ColorInterpolation::LinearRgb {
+        for x in &mut c[..3] {
+            *x = linear_to_srgb(*x);
+        }
+    }
+    Color::rgba_f32(c[0], c[1], c[2], c[3])
+}
@@ -2,18 +2,30 @@
 
 pub use kurbo;
 
+mod blend;
+mod blur;
 mod color;
 mod conv;
+mod document;
+mod dyn_context;
 mod error;
 mod gradient;
+mod recording;
 mod render_context;
 mod shapes;
 mod text;
+mod validate;
 
+pub use crate::blend::*;
+pub use crate::blur::*;
 pub use crate::color::*;
 pub use crate::conv::*;
+pub use crate::document::*;
+pub use crate::dyn_context::*;
 pub use crate::error::*;
 pub use crate::gradient::*;
+pub use crate::recording::*;
 pub use crate::render_context::*;
 pub use crate::shapes::*;
 pub use crate::text::*;
+pub use crate::validate::*;
@@ -0,0 +1,621 @@
+//! A render context that records a display list, for playback later.
+
+use kurbo::{Affine, BezPath, Rect, Shape, Vec2};
+
+use crate::{
+    new_error, BlendMode, Color, Error, ErrorKind, ExtendMode, FillRule, Font, FontBuilder,
+    Gradient, ImageFormat, InterpolationMode, MaskMode, RenderContext, RoundInto, StrokeStyle,
+    Text, TextLayout, TextLayoutBuilder,
+};
+
+/// The tolerance for converting shapes to Bézier paths.
+const TOLERANCE: f64 = 1e-3;
+
+/// A recorded sequence of drawing operations.
+///
+/// Resources are stored in tables, and commands refer to them by index.
+/// Build one with a [`RecordingContext`](struct.RecordingContext.html), and
+/// play it back on any render context with [`replay`](fn.replay.html).
+#[derive(Clone, Debug, Default)]
+pub struct DisplayList {
+    pub images: Vec<RecordedImageData>,
+    pub brushes: Vec<RecordedBrushData>,
+    pub commands: Vec<Command>,
+}
+
+/// The pixels of an image, as passed to `make_image`.
+#[derive(Clone, Debug)]
+pub struct RecordedImageData {
+    pub width: usize,
+    pub height: usize,
+    pub buf: Vec<u8>,
+    pub format: ImageFormat,
+}
+
+/// The description of a brush.
+#[derive(Clone, Debug)]
+pub enum RecordedBrushData {
+    Solid(Color),
+    Gradient(Gradient),
+    Image {
+        /// The index of the image.
+        image: usize,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    },
+}
+
+/// A recorded drawing operation.
+///
+/// Brushes and images are indices into the tables of the display list.
+#[derive(Clone, Debug)]
+pub enum Command {
+    Clear(Color),
+    Stroke {
+        path: BezPath,
+        brush: usize,
+        width: f64,
+        style: Option<StrokeStyle>,
+    },
+    Fill {
+        path: BezPath,
+        brush: usize,
+        fill_rule: FillRule,
+    },
+    BlurredRect {
+        rect: Rect,
+        blur_radius: f64,
+        brush: usize,
+    },
+    DrawShadow {
+        path: BezPath,
+        offset: Vec2,
+        blur_radius: f64,
+        color: Color,
+    },
+    Clip {
+        path: BezPath,
+        fill_rule: FillRule,
+    },
+    Mask {
+        image: usize,
+        rect: Rect,
+        mode: MaskMode,
+    },
+    BeginMask,
+    EndMask(MaskMode),
+    DrawText {
+        layout: RecordedTextLayout,
+        pos: Vec2,
+        brush: usize,
+    },
+    Save,
+    Restore,
+    PushLayer {
+        opacity: f64,
+        clip: Option<BezPath>,
+    },
+    PopLayer,
+    Transform(Affine),
+    SetBlendMode(BlendMode),
+    DrawImage {
+        image: usize,
+        rect: Rect,
+        interp: InterpolationMode,
+    },
+}
+
+/// A render context that records drawing operations into a display list.
+///
+/// Text is recorded by content, the font name and size plus the string, and
+/// laid out again on playback. As there is no backend to measure it while
+/// recording, layout widths are estimates.
+pub struct RecordingContext {
+    list: DisplayList,
+    text: RecordingText,
+    /// The kinds of saved states, to check that the stack is balanced.
+    stack: Vec<Saved>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Saved {
+    Save,
+    Layer,
+    MaskContent,
+}
+
+/// A brush in a recording, referring to the display list's brush table.
+#[derive(Clone, Debug)]
+pub struct RecordedBrush(usize);
+
+/// An image in a recording, referring to the display list's image table.
+#[derive(Clone, Debug)]
+pub struct RecordedImage(usize);
+
+pub struct RecordingText;
+
+pub struct RecordedFontBuilder(RecordedFont);
+
+/// A font, by name and size.
+#[derive(Clone, Debug)]
+pub struct RecordedFont {
+    pub name: String,
+    pub size: f64,
+}
+
+pub struct RecordedTextLayoutBuilder(RecordedTextLayout);
+
+/// A text layout, by content.
+///
+/// A recording has no fonts to measure with, so the width is a placeholder
+/// of half an em per character. Layout that measures text while recording
+/// bakes that estimate into the display list, rather than the width the
+/// text has on replay.
+#[derive(Clone, Debug)]
+pub struct RecordedTextLayout {
+    pub font: RecordedFont,
+    pub text: String,
+}
+
+impl RecordingContext {
+    pub fn new() -> RecordingContext {
+        RecordingContext {
+            list: DisplayList::default(),
+            text: RecordingText,
+            stack: Vec::new(),
+        }
+    }
+
+    /// The operations recorded so far.
+    pub fn display_list(&self) -> &DisplayList {
+        &self.list
+    }
+
+    /// Finish recording, returning the display list.
+    pub fn into_display_list(self) -> DisplayList {
+        self.list
+    }
+
+    fn push(&mut self, command: Command) {
+        self.list.commands.push(command);
+    }
+
+    fn add_brush(&mut self, brush: RecordedBrushData) -> RecordedBrush {
+        self.list.brushes.push(brush);
+        RecordedBrush(self.list.brushes.len() - 1)
+    }
+
+    /// Pop a saved state, which must be of the given kind.
+    fn pop_state(&mut self, kind: Saved) -> Result<(), Error> {
+        if self.stack.last() != Some(&kind) {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+impl Default for RecordingContext {
+    fn default() -> RecordingContext {
+        RecordingContext::new()
+    }
+}
+
+fn to_bez_path(shape: impl Shape) -> BezPath {
+    let mut path = BezPath::new();
+    for el in shape.to_bez_path(TOLERANCE) {
+        path.push(el);
+    }
+    path
+}
+
+impl RenderContext for RecordingContext {
+    type Point = Vec2;
+    type Coord = f64;
+    type Brush = RecordedBrush;
+    type Text = RecordingText;
+    type TextLayout = RecordedTextLayout;
+    type Image = RecordedImage;
+
+    fn status(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn solid_brush(&mut self, color: Color) -> RecordedBrush {
+        self.add_brush(RecordedBrushData::Solid(color))
+    }
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<RecordedBrush, Error> {
+        Ok(self.add_brush(RecordedBrushData::Gradient(gradient)))
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &RecordedImage,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<RecordedBrush, Error> {
+        Ok(self.add_brush(RecordedBrushData::Image {
+            image: image.0,
+            extend_x,
+            extend_y,
+            transform,
+            interp,
+        }))
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.push(Command::Clear(color));
+    }
+
+    fn stroke(
+        &mut self,
+        shape: impl Shape,
+        brush: &RecordedBrush,
+        width: impl RoundInto<f64>,
+        style: Option<&StrokeStyle>,
+    ) {
+        self.push(Command::Stroke {
+            path: to_bez_path(shape),
+            brush: brush.0,
+            width: width.round_into(),
+            style: style.cloned(),
+        });
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &RecordedBrush, fill_rule: FillRule) {
+        self.push(Command::Fill {
+            path: to_bez_path(shape),
+            brush: brush.0,
+            fill_rule,
+        });
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &RecordedBrush) {
+        self.push(Command::BlurredRect {
+            rect,
+            blur_radius,
+            brush: brush.0,
+        });
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        self.push(Command::DrawShadow {
+            path: to_bez_path(shape),
+            offset,
+            blur_radius,
+            color,
+        });
+    }
+
+    fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
+        self.push(Command::Clip {
+            path: to_bez_path(shape),
+            fill_rule,
+        });
+    }
+
+    fn mask(&mut self, mask: &RecordedImage, rect: impl Into<Rect>, mode: MaskMode) {
+        self.push(Command::Mask {
+            image: mask.0,
+            rect: rect.into(),
+            mode,
+        });
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        self.stack.push(Saved::MaskContent);
+        self.push(Command::BeginMask);
+        Ok(())
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        self.pop_state(Saved::MaskContent)?;
+        self.push(Command::EndMask(mode));
+        Ok(())
+    }
+
+    fn text(&mut self) -> &mut RecordingText {
+        &mut self.text
+    }
+
+    fn draw_text(
+        &mut self,
+        layout: &RecordedTextLayout,
+        pos: impl RoundInto<Vec2>,
+        brush: &RecordedBrush,
+    ) {
+        self.push(Command::DrawText {
+            layout: layout.clone(),
+            pos: pos.round_into(),
+            brush: brush.0,
+        });
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.stack.push(Saved::Save);
+        self.push(Command::Save);
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        self.pop_state(Saved::Save)?;
+        self.push(Command::Restore);
+        Ok(())
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        self.stack.push(Saved::Layer);
+        self.push(Command::PushLayer {
+            opacity,
+            clip: clip.map(to_bez_path),
+        });
+        Ok(())
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        self.pop_state(Saved::Layer)?;
+        self.push(Command::PopLayer);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if !self.stack.is_empty() {
+            return Err(new_error(ErrorKind::StackUnbalance));
+        }
+        Ok(())
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        self.push(Command::Transform(transform));
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.push(Command::SetBlendMode(mode));
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<RecordedImage, Error> {
+        self.list.images.push(RecordedImageData {
+            width,
+            height,
+            buf: buf.to_vec(),
+            format,
+        });
+        Ok(RecordedImage(self.list.images.len() - 1))
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &RecordedImage,
+        rect: impl Into<Rect>,
+        interp: InterpolationMode,
+    ) {
+        self.push(Command::DrawImage {
+            image: image.0,
+            rect: rect.into(),
+            interp,
+        });
+    }
+}
+
+impl Text for RecordingText {
+    type FontBuilder = RecordedFontBuilder;
+    type Font = RecordedFont;
+    type TextLayoutBuilder = RecordedTextLayoutBuilder;
+    type TextLayout = RecordedTextLayout;
+    type Coord = f64;
+
+    fn new_font_by_name(
+        &mut self,
+        name: &str,
+        size: impl RoundInto<f64>,
+    ) -> Result<RecordedFontBuilder, Error> {
+        Ok(RecordedFontBuilder(RecordedFont {
+            name: name.to_owned(),
+            size: size.round_into(),
+        }))
+    }
+
+    fn new_text_layout(
+        &mut self,
+        font: &RecordedFont,
+        text: &str,
+    ) -> Result<RecordedTextLayoutBuilder, Error> {
+        Ok(RecordedTextLayoutBuilder(RecordedTextLayout {
+            font: font.clone(),
+            text: text.to_owned(),
+        }))
+    }
+}
+
+impl FontBuilder for RecordedFontBuilder {
+    type Out = RecordedFont;
+
+    fn build(self) -> Result<RecordedFont, Error> {
+        Ok(self.0)
+    }
+}
+
+impl Font for RecordedFont {}
+
+impl TextLayoutBuilder for RecordedTextLayoutBuilder {
+    type Out = RecordedTextLayout;
+
+    fn build(self) -> Result<RecordedTextLayout, Error> {
+        Ok(self.0)
+    }
+}
+
+impl TextLayout for RecordedTextLayout {
+    type Coord = f64;
+
+    /// A placeholder width, at half an em per character. See the type docs.
+    fn width(&self) -> f64 {
+        0.5 * self.font.size * self.text.chars().count() as f64
+    }
+}
+
+/// Play back a display list on a render context.
+///
+/// Brushes and images the target can't create, for example gradients a
+/// backend doesn't support, are skipped along with the commands using them,
+/// as are commands with an invalid resource index. The first such error is
+/// returned after playing back everything else.
+pub fn replay(list: &DisplayList, rc: &mut impl RenderContext) -> Result<(), Error> {
+    let mut first_err = Ok(());
+    let images = list
+        .images
+        .iter()
+        .map(|image| {
+            note(
+                &mut first_err,
+                rc.make_image(image.width, image.height, &image.buf, image.format),
+            )
+        })
+        .collect::<Vec<_>>();
+    let brushes = list
+        .brushes
+        .iter()
+        .map(|brush| match *brush {
+            RecordedBrushData::Solid(ref color) => Some(rc.solid_brush(color.clone())),
+            RecordedBrushData::Gradient(ref gradient) => {
+                note(&mut first_err, rc.gradient(gradient.clone()))
+            }
+            RecordedBrushData::Image {
+                image,
+                extend_x,
+                extend_y,
+                transform,
+                interp,
+            } => lookup(&mut first_err, &images, image).and_then(|image| {
+                note(
+                    &mut first_err,
+                    rc.image_brush(image, extend_x, extend_y, transform, interp),
+                )
+            }),
+        })
+        .collect::<Vec<_>>();
+    for command in &list.commands {
+        match *command {
+            Command::Clear(ref color) => rc.clear(color.clone()),
+            Command::Stroke {
+                ref path,
+                brush,
+                width,
+                ref style,
+            } => {
+                if let Some(brush) = lookup(&mut first_err, &brushes, brush) {
+                    rc.stroke(path, brush, width, style.as_ref());
+                }
+            }
+            Command::Fill {
+                ref path,
+                brush,
+                fill_rule,
+            } => {
+                if let Some(brush) = lookup(&mut first_err, &brushes, brush) {
+                    rc.fill(path, brush, fill_rule);
+                }
+            }
+            Command::BlurredRect {
+                rect,
+                blur_radius,
+                brush,
+            } => {
+                if let Some(brush) = lookup(&mut first_err, &brushes, brush) {
+                    rc.blurred_rect(rect, blur_radius, brush);
+                }
+            }
+            Command::DrawShadow {
+                ref path,
+                offset,
+                blur_radius,
+                ref color,
+            } => rc.draw_shadow(path, offset, blur_radius, color.clone()),
+            Command::Clip {
+                ref path,
+                fill_rule,
+            } => rc.clip(path, fill_rule),
+            Command::Mask { image, rect, mode } => {
+                if let Some(image) = lookup(&mut first_err, &images, image) {
+                    rc.mask(image, rect, mode);
+                }
+            }
+            Command::BeginMask => note(&mut first_err, rc.begin_mask()).unwrap_or(()),
+            Command::EndMask(mode) => note(&mut first_err, rc.end_mask(mode)).unwrap_or(()),
+            Command::DrawText {
+                ref layout,
+                pos,
+                brush,
+            } => {
+                let text_layout = rc
+                    .text()
+                    .new_font_by_name(&layout.font.name, layout.font.size)
+                    .and_then(|builder| builder.build())
+                    .and_then(|font| rc.text().new_text_layout(&font, &layout.text))
+                    .and_then(|builder| builder.build());
+                if let (Some(text_layout), Some(brush)) = (
+                    note(&mut first_err, text_layout),
+                    lookup(&mut first_err, &brushes, brush),
+                ) {
+                    rc.draw_text(&text_layout, pos, brush);
+                }
+            }
+            Command::Save => note(&mut first_err, rc.save()).unwrap_or(()),
+            Command::Restore => note(&mut first_err, rc.restore()).unwrap_or(()),
+            Command::PushLayer { opacity, ref clip } => {
+                note(&mut first_err, rc.push_layer(opacity, clip.as_ref())).unwrap_or(())
+            }
+            Command::PopLayer => note(&mut first_err, rc.pop_layer()).unwrap_or(()),
+            Command::Transform(transform) => rc.transform(transform),
+            Command::SetBlendMode(mode) => rc.set_blend_mode(mode),
+            Command::DrawImage {
+                image,
+                rect,
+                interp,
+            } => {
+                if let Some(image) = lookup(&mut first_err, &images, image) {
+                    rc.draw_image(image, rect, interp);
+                }
+            }
+        }
+    }
+    first_err
+}
+
+/// Look up a resource by index, keeping an error if the index is invalid.
+///
+/// The fields of a display list are public, so the indices can't be trusted.
+fn lookup<'a, T>(
+    first_err: &mut Result<(), Error>,
+    resources: &'a [Option<T>],
+    index: usize,
+) -> Option<&'a T> {
+    match resources.get(index) {
+        Some(resource) => resource.as_ref(),
+        None => note(first_err, Err(new_error(ErrorKind::InvalidInput))),
+    }
+}
+
+/// Keep the first error, converting the result to an option.
+fn note<T>(first_err: &mut Result<(), Error>, result: Result<T, Error>) -> Option<T> {
+    match result {
+        Ok(x) => Some(x),
+        Err(e) => {
+            if first_err.is_ok() {
+                *first_err = Err(e);
+            }
+            None
+        }
+    }
+}
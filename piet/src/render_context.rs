@@ -3,11 +3,12 @@
 use kurbo::{Affine, Rect, Shape, Vec2};
 
 use crate::{
-    Color, Error, FillRule, Gradient, RoundFrom, RoundInto, StrokeStyle, Text, TextLayout,
+    new_error, BlendMode, Color, Error, ErrorKind, ExtendMode, FillRule, Gradient, RoundFrom,
+    RoundInto, StrokeStyle, Text, TextLayout,
 };
 
 /// A requested interpolation mode for drawing images.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum InterpolationMode {
     /// Don't interpolate, use nearest neighbor.
     NearestNeighbor,
@@ -15,6 +16,17 @@ pub enum InterpolationMode {
     Bilinear,
 }
 
+/// How the pixels of a mask are turned into coverage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskMode {
+    /// Use the alpha channel.
+    Alpha,
+    /// Use the luminance of the color, multiplied by alpha.
+    ///
+    /// This is the same as the default `mask-type` of SVG masks.
+    Luminance,
+}
+
 /// The pixel format for bitmap images.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ImageFormat {
@@ -24,18 +36,61 @@ pub enum ImageFormat {
     RgbaSeparate,
     /// 4 bytes per pixel, in RGBA order, with premultiplied alpha.
     RgbaPremul,
+    /// 4 bytes per pixel, in BGRA order, with premultiplied alpha.
+    ///
+    /// This is the byte order of cairo's and raqote's native pixels on little
+    /// endian machines, so those backends can copy it without a swizzle.
+    BgraPremul,
+    /// 1 byte per pixel, gray level only. The image is opaque.
+    Gray8,
+    /// 1 byte per pixel, alpha only. The color is black.
+    Alpha8,
+    /// 8 bytes per pixel, in RGBA order, with separate alpha.
+    ///
+    /// Each channel is a `u16` in native byte order, as in a `&[u16]` buffer.
+    Rgba16,
     /// More formats may be added later.
     #[doc(hidden)]
     _NonExhaustive,
 }
 
 impl ImageFormat {
+    /// The number of bytes in each pixel.
+    ///
+    /// This is 0 for the hidden placeholder variant, which no backend accepts.
     pub fn bytes_per_pixel(&self) -> usize {
         match *self {
+            ImageFormat::Gray8 | ImageFormat::Alpha8 => 1,
             ImageFormat::Rgb => 3,
-            ImageFormat::RgbaPremul | ImageFormat::RgbaSeparate => 4,
-            _ => panic!(),
+            ImageFormat::RgbaPremul | ImageFormat::RgbaSeparate | ImageFormat::BgraPremul => 4,
+            ImageFormat::Rgba16 => 8,
+            ImageFormat::_NonExhaustive => 0,
+        }
+    }
+
+    /// The length in bytes of an image of the given size, or `None` if it
+    /// overflows.
+    pub fn buffer_len(&self, width: usize, height: usize) -> Option<usize> {
+        width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(self.bytes_per_pixel()))
+    }
+
+    /// Check that an image buffer has the right length for its size.
+    ///
+    /// This is `InvalidImageData` if the length is wrong, or `InvalidInput`
+    /// if the size is too large to address.
+    pub fn check_buffer(&self, width: usize, height: usize, buf: &[u8]) -> Result<(), Error> {
+        let expected = self
+            .buffer_len(width, height)
+            .ok_or_else(|| new_error(ErrorKind::InvalidInput))?;
+        if buf.len() != expected {
+            return Err(new_error(ErrorKind::InvalidImageData {
+                expected,
+                actual: buf.len(),
+            }));
         }
+        Ok(())
     }
 }
 
@@ -94,8 +149,31 @@ pub trait RenderContext {
     /// Create a new gradient brush.
     fn gradient(&mut self, gradient: Gradient) -> Result<Self::Brush, Error>;
 
+    /// Create a new brush that paints an image.
+    ///
+    /// The `transform` maps image coordinates, in which each pixel is a unit
+    /// square, to user space. Outside the image, it is extended horizontally
+    /// by `extend_x` and vertically by `extend_y`; `Repeat` tiles it.
+    ///
+    /// Not all backends support all combinations of extend modes; notably,
+    /// the web backend only supports `Repeat`, and the Direct2D backend
+    /// doesn't support image brushes yet.
+    ///
+    /// `interp` is a hint: the raqote backend always samples images
+    /// bilinearly.
+    fn image_brush(
+        &mut self,
+        image: &Self::Image,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<Self::Brush, Error>;
+
     /// Clear the canvas with the given color.
     ///
+    /// The pixels are replaced, ignoring the current blend mode.
+    ///
     /// Note: only opaque colors are meaningful.
     fn clear(&mut self, color: Color);
 
@@ -114,12 +192,51 @@ pub trait RenderContext {
     // should have a convention, geometry first.
     fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: FillRule);
 
+    /// Fill a rectangle, blurred by a Gaussian.
+    ///
+    /// The `blur_radius` is the standard deviation of the Gaussian, in user
+    /// space units. This is a fast path for the common case of shadows of
+    /// rectangular widgets, which backends can compute analytically.
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &Self::Brush);
+
+    /// Draw the drop shadow of a shape.
+    ///
+    /// The coverage of the shape is blurred by a Gaussian with standard
+    /// deviation `blur_radius`, displaced by `offset`, and filled with
+    /// `color`. The shape itself is not drawn.
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color);
+
     /// Clip to a shape.
     ///
     /// All subsequent drawing operations up to the next [`restore`](#method.restore)
     /// are clipped by the shape.
     fn clip(&mut self, shape: impl Shape, fill_rule: FillRule);
 
+    /// Mask by an image.
+    ///
+    /// All subsequent drawing operations up to the next [`restore`](#method.restore)
+    /// are multiplied by the coverage derived from `mask`, which is placed in
+    /// `rect` as with [`draw_image`](#method.draw_image). Coverage is zero
+    /// outside of `rect`.
+    ///
+    /// Like [layers](#method.push_layer), the masked drawing is isolated and
+    /// composited onto the surface when the state is restored.
+    fn mask(&mut self, mask: &Self::Image, rect: impl Into<Rect>, mode: MaskMode);
+
+    /// Begin rendering the content of a mask.
+    ///
+    /// All drawing operations up to the matching [`end_mask`](#method.end_mask)
+    /// go into an offscreen instead of the surface. This saves the context
+    /// state, which is restored by `end_mask`.
+    fn begin_mask(&mut self) -> Result<(), Error>;
+
+    /// Finish rendering the content of a mask, and apply it.
+    ///
+    /// The offscreen rendered since [`begin_mask`](#method.begin_mask) is
+    /// used to mask all subsequent drawing operations up to the next
+    /// [`restore`](#method.restore), in the same way as [`mask`](#method.mask).
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error>;
+
     fn text(&mut self) -> &mut Self::Text;
 
     /// Draw a text layout.
@@ -141,8 +258,8 @@ pub trait RenderContext {
     /// Prefer [`with_save`](#method.with_save) if possible, as that statically
     /// enforces balance of save/restore pairs.
     ///
-    /// The context state currently consists of a clip region and an affine
-    /// transform, but is expected to grow in the near future.
+    /// The context state currently consists of a clip region, masks, an affine
+    /// transform and a blend mode, but is expected to grow in the near future.
     fn save(&mut self) -> Result<(), Error>;
 
     /// Restore the context state.
@@ -161,6 +278,40 @@ pub trait RenderContext {
         f(self).and(self.restore())
     }
 
+    /// Begin drawing into a layer.
+    ///
+    /// All drawing operations up to the matching [`pop_layer`](#method.pop_layer)
+    /// are rendered into an isolated offscreen, which is then composited onto
+    /// the surface in a single step with the given `opacity`. Overlapping
+    /// shapes inside the layer therefore don't blend with each other. If
+    /// `clip` is given, the layer is clipped to that shape.
+    ///
+    /// This also saves the context state, as [`save`](#method.save) does.
+    /// The state is restored by `pop_layer`, and calling `restore` while a
+    /// layer is the innermost saved state is a stack unbalance error.
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error>;
+
+    /// Finish drawing into a layer.
+    ///
+    /// Composite the layer pushed by [`push_layer`](#method.push_layer) and
+    /// restore the context state. See that method for details.
+    fn pop_layer(&mut self) -> Result<(), Error>;
+
+    /// Do graphics operations in a layer.
+    ///
+    /// Equivalent to [`push_layer`](#method.push_layer), calling `f`, then
+    /// [`pop_layer`](#method.pop_layer). See those methods for more details.
+    fn with_layer(
+        &mut self,
+        opacity: f64,
+        clip: Option<impl Shape>,
+        f: impl FnOnce(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.push_layer(opacity, clip)?;
+        // Always try to pop the layer, even if `f` errored.
+        f(self).and(self.pop_layer())
+    }
+
     /// Finish any pending operations.
     ///
     /// This will generally be called by a shell after all user drawing
@@ -174,6 +325,13 @@ pub trait RenderContext {
     /// until a [`restore`](#method.restore) operation.
     fn transform(&mut self, transform: Affine);
 
+    /// Set the blend mode.
+    ///
+    /// The blend mode applies to all subsequent fill, stroke, text and image
+    /// drawing operations, up to the next [`restore`](#method.restore). The
+    /// initial blend mode is [`SrcOver`](enum.BlendMode.html#variant.SrcOver).
+    fn set_blend_mode(&mut self, mode: BlendMode);
+
     /// Create a new image from a pixel buffer.
     fn make_image(
         &mut self,
@@ -189,3 +347,139 @@ pub trait RenderContext {
     /// aspect ratios don't match.
     fn draw_image(&mut self, image: &Self::Image, rect: impl Into<Rect>, interp: InterpolationMode);
 }
+
+/// A mutable reference to a render context is a render context, which draws
+/// on the referenced one.
+impl<'a, R: RenderContext> RenderContext for &'a mut R {
+    type Point = R::Point;
+    type Coord = R::Coord;
+    type Brush = R::Brush;
+    type Text = R::Text;
+    type TextLayout = R::TextLayout;
+    type Image = R::Image;
+
+    fn status(&mut self) -> Result<(), Error> {
+        (**self).status()
+    }
+
+    fn solid_brush(&mut self, color: Color) -> Self::Brush {
+        (**self).solid_brush(color)
+    }
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<Self::Brush, Error> {
+        (**self).gradient(gradient)
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &Self::Image,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<Self::Brush, Error> {
+        (**self).image_brush(image, extend_x, extend_y, transform, interp)
+    }
+
+    fn clear(&mut self, color: Color) {
+        (**self).clear(color)
+    }
+
+    fn stroke(
+        &mut self,
+        shape: impl Shape,
+        brush: &Self::Brush,
+        width: impl RoundInto<Self::Coord>,
+        style: Option<&StrokeStyle>,
+    ) {
+        (**self).stroke(shape, brush, width, style)
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: FillRule) {
+        (**self).fill(shape, brush, fill_rule)
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &Self::Brush) {
+        (**self).blurred_rect(rect, blur_radius, brush)
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        (**self).draw_shadow(shape, offset, blur_radius, color)
+    }
+
+    fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
+        (**self).clip(shape, fill_rule)
+    }
+
+    fn mask(&mut self, mask: &Self::Image, rect: impl Into<Rect>, mode: MaskMode) {
+        (**self).mask(mask, rect, mode)
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        (**self).begin_mask()
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        (**self).end_mask(mode)
+    }
+
+    fn text(&mut self) -> &mut Self::Text {
+        (**self).text()
+    }
+
+    fn draw_text(
+        &mut self,
+        layout: &Self::TextLayout,
+        pos: impl RoundInto<Self::Point>,
+        brush: &Self::Brush,
+    ) {
+        (**self).draw_text(layout, pos, brush)
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        (**self).save()
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        (**self).restore()
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        (**self).push_layer(opacity, clip)
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        (**self).pop_layer()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        (**self).finish()
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        (**self).transform(transform)
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        (**self).set_blend_mode(mode)
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<Self::Image, Error> {
+        (**self).make_image(width, height, buf, format)
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &Self::Image,
+        rect: impl Into<Rect>,
+        interp: InterpolationMode,
+    ) {
+        (**self).draw_image(image, rect, interp)
+    }
+}
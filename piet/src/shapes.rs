@@ -1,7 +1,7 @@
 //! Options for drawing paths.
 
 /// A fill rule for resolving winding numbers.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum FillRule {
     /// Fill everything with a non-zero winding number.
     NonZero,
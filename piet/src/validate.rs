@@ -0,0 +1,421 @@
+//! A render context wrapper that validates its arguments.
+
+use kurbo::{Affine, PathEl, Rect, Shape, Vec2};
+
+use crate::{
+    new_error, BlendMode, Color, Error, ErrorKind, ExtendMode, FillRule, Gradient, ImageFormat,
+    InterpolationMode, MaskMode, RenderContext, RoundInto, StrokeStyle,
+};
+
+/// A render context that checks the arguments of every call.
+///
+/// Bugs in drawing code, like NaN coordinates or image buffers of the wrong
+/// length, tend to show up as silent garbage or panics deep in a backend.
+/// This wrapper checks the arguments before passing each call on to the
+/// wrapped context. Invalid drawing calls are skipped, and the first one is
+/// reported as an [`InvalidCall`](enum.ErrorKind.html#variant.InvalidCall)
+/// error by the next [`status`](trait.RenderContext.html#tymethod.status)
+/// or [`finish`](trait.RenderContext.html#tymethod.finish). Calls that
+/// return a `Result` also return the error directly.
+pub struct ValidatingContext<R: RenderContext> {
+    inner: R,
+    /// The first violation not yet reported, as the method and the reason.
+    violation: Option<(&'static str, String)>,
+}
+
+impl<R: RenderContext> ValidatingContext<R> {
+    /// Wrap a render context.
+    pub fn new(inner: R) -> ValidatingContext<R> {
+        ValidatingContext {
+            inner,
+            violation: None,
+        }
+    }
+
+    /// The wrapped render context.
+    pub fn inner(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwrap the render context.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Record a violation, if `result` is one, keeping only the first.
+    ///
+    /// Returns whether the call is valid.
+    fn check(&mut self, method: &'static str, result: Result<(), String>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(reason) => {
+                if self.violation.is_none() {
+                    self.violation = Some((method, reason));
+                }
+                false
+            }
+        }
+    }
+
+    /// Like `check`, but for methods that report the error directly.
+    fn check_result(
+        &mut self,
+        method: &'static str,
+        result: Result<(), String>,
+    ) -> Result<(), Error> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(reason) => {
+                let e = invalid_call(method, reason.clone());
+                self.check(method, Err(reason));
+                Err(e)
+            }
+        }
+    }
+
+    /// Report the first violation, if any.
+    fn take_violation(&mut self) -> Result<(), Error> {
+        match self.violation.take() {
+            Some((method, reason)) => Err(invalid_call(method, reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn invalid_call(method: &'static str, reason: String) -> Error {
+    new_error(ErrorKind::InvalidCall { method, reason })
+}
+
+fn check_finite(x: f64, what: &str) -> Result<(), String> {
+    if x.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} is {}", what, x))
+    }
+}
+
+fn check_non_negative(x: f64, what: &str) -> Result<(), String> {
+    check_finite(x, what)?;
+    if x < 0.0 {
+        return Err(format!("{} is negative ({})", what, x));
+    }
+    Ok(())
+}
+
+fn check_unit(x: f64, what: &str) -> Result<(), String> {
+    check_finite(x, what)?;
+    if x < 0.0 || x > 1.0 {
+        return Err(format!("{} is outside 0 to 1 ({})", what, x));
+    }
+    Ok(())
+}
+
+fn check_vec(v: Vec2, what: &str) -> Result<(), String> {
+    if v.x.is_finite() && v.y.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("{} is ({}, {})", what, v.x, v.y))
+    }
+}
+
+fn check_rect(rect: Rect, what: &str) -> Result<(), String> {
+    check_vec(Vec2::new(rect.x0, rect.y0), what)?;
+    check_vec(Vec2::new(rect.x1, rect.y1), what)
+}
+
+fn check_affine(affine: Affine, what: &str) -> Result<(), String> {
+    if affine.as_coeffs().iter().all(|x| x.is_finite()) {
+        Ok(())
+    } else {
+        Err(format!("{} has non-finite coefficients", what))
+    }
+}
+
+fn check_shape(shape: &impl Shape) -> Result<(), String> {
+    for el in shape.to_bez_path(1e-3) {
+        let finite = |p: kurbo::Point| p.x.is_finite() && p.y.is_finite();
+        let ok = match el {
+            PathEl::MoveTo(p) | PathEl::LineTo(p) => finite(p),
+            PathEl::QuadTo(p1, p2) => finite(p1) && finite(p2),
+            PathEl::CurveTo(p1, p2, p3) => finite(p1) && finite(p2) && finite(p3),
+            PathEl::ClosePath => true,
+        };
+        if !ok {
+            return Err("shape has non-finite coordinates".into());
+        }
+    }
+    Ok(())
+}
+
+fn check_color(color: &Color) -> Result<(), String> {
+    match *color {
+        Color::RgbaF32(c, _) if !c.iter().all(|x| x.is_finite()) => {
+            Err(format!("color has non-finite components {:?}", c))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_stroke_style(style: Option<&StrokeStyle>) -> Result<(), String> {
+    let style = match style {
+        Some(style) => style,
+        None => return Ok(()),
+    };
+    if let Some((ref dashes, offset)) = style.dash {
+        for &dash in dashes {
+            check_non_negative(dash, "dash length")?;
+        }
+        check_finite(offset, "dash offset")?;
+    }
+    if let Some(miter_limit) = style.miter_limit {
+        check_non_negative(miter_limit, "miter limit")?;
+    }
+    Ok(())
+}
+
+fn check_gradient(gradient: &Gradient) -> Result<(), String> {
+    let stops = gradient.stops();
+    if stops.len() < 2 {
+        return Err(format!(
+            "gradient has {} stops, but needs at least two",
+            stops.len()
+        ));
+    }
+    for stop in stops {
+        check_finite(stop.pos as f64, "stop position")?;
+        check_color(&stop.color)?;
+    }
+    if stops.windows(2).any(|pair| pair[1].pos < pair[0].pos) {
+        return Err("gradient stops are out of order".into());
+    }
+    let transform = match gradient {
+        Gradient::Linear(linear) => {
+            check_vec(linear.start, "gradient start")?;
+            check_vec(linear.end, "gradient end")?;
+            linear.transform
+        }
+        Gradient::Radial(radial) => {
+            check_vec(radial.center, "gradient center")?;
+            check_vec(radial.origin_offset, "gradient origin offset")?;
+            check_non_negative(radial.start_radius, "gradient start radius")?;
+            check_non_negative(radial.radius, "gradient radius")?;
+            radial.transform
+        }
+        Gradient::Sweep(sweep) => {
+            check_vec(sweep.center, "gradient center")?;
+            check_finite(sweep.start_angle, "gradient start angle")?;
+            check_finite(sweep.end_angle, "gradient end angle")?;
+            sweep.transform
+        }
+    };
+    if let Some(transform) = transform {
+        check_affine(transform, "gradient transform")?;
+    }
+    Ok(())
+}
+
+impl<R: RenderContext> RenderContext for ValidatingContext<R> {
+    type Point = R::Point;
+    type Coord = R::Coord;
+    type Brush = R::Brush;
+    type Text = R::Text;
+    type TextLayout = R::TextLayout;
+    type Image = R::Image;
+
+    fn status(&mut self) -> Result<(), Error> {
+        self.take_violation()?;
+        self.inner.status()
+    }
+
+    fn solid_brush(&mut self, color: Color) -> Self::Brush {
+        // There's no way to skip this, so just record the violation.
+        self.check("solid_brush", check_color(&color));
+        self.inner.solid_brush(color)
+    }
+
+    fn gradient(&mut self, gradient: Gradient) -> Result<Self::Brush, Error> {
+        self.check_result("gradient", check_gradient(&gradient))?;
+        self.inner.gradient(gradient)
+    }
+
+    fn image_brush(
+        &mut self,
+        image: &Self::Image,
+        extend_x: ExtendMode,
+        extend_y: ExtendMode,
+        transform: Affine,
+        interp: InterpolationMode,
+    ) -> Result<Self::Brush, Error> {
+        let result = check_affine(transform, "image transform").and_then(|()| {
+            let c = transform.as_coeffs();
+            if c[0] * c[3] - c[1] * c[2] == 0.0 {
+                Err("image transform isn't invertible".into())
+            } else {
+                Ok(())
+            }
+        });
+        self.check_result("image_brush", result)?;
+        self.inner
+            .image_brush(image, extend_x, extend_y, transform, interp)
+    }
+
+    fn clear(&mut self, color: Color) {
+        if self.check("clear", check_color(&color)) {
+            self.inner.clear(color);
+        }
+    }
+
+    fn stroke(
+        &mut self,
+        shape: impl Shape,
+        brush: &Self::Brush,
+        width: impl RoundInto<Self::Coord>,
+        style: Option<&StrokeStyle>,
+    ) {
+        let width: Self::Coord = width.round_into();
+        let width: f64 = width.into();
+        let result = check_shape(&shape)
+            .and_then(|()| check_non_negative(width, "stroke width"))
+            .and_then(|()| check_stroke_style(style));
+        if self.check("stroke", result) {
+            self.inner.stroke(shape, brush, width, style);
+        }
+    }
+
+    fn fill(&mut self, shape: impl Shape, brush: &Self::Brush, fill_rule: FillRule) {
+        if self.check("fill", check_shape(&shape)) {
+            self.inner.fill(shape, brush, fill_rule);
+        }
+    }
+
+    fn blurred_rect(&mut self, rect: Rect, blur_radius: f64, brush: &Self::Brush) {
+        let result =
+            check_rect(rect, "rect").and_then(|()| check_non_negative(blur_radius, "blur radius"));
+        if self.check("blurred_rect", result) {
+            self.inner.blurred_rect(rect, blur_radius, brush);
+        }
+    }
+
+    fn draw_shadow(&mut self, shape: impl Shape, offset: Vec2, blur_radius: f64, color: Color) {
+        let result = check_shape(&shape)
+            .and_then(|()| check_vec(offset, "shadow offset"))
+            .and_then(|()| check_non_negative(blur_radius, "blur radius"))
+            .and_then(|()| check_color(&color));
+        if self.check("draw_shadow", result) {
+            self.inner.draw_shadow(shape, offset, blur_radius, color);
+        }
+    }
+
+    fn clip(&mut self, shape: impl Shape, fill_rule: FillRule) {
+        if self.check("clip", check_shape(&shape)) {
+            self.inner.clip(shape, fill_rule);
+        }
+    }
+
+    fn mask(&mut self, mask: &Self::Image, rect: impl Into<Rect>, mode: MaskMode) {
+        let rect = rect.into();
+        if self.check("mask", check_rect(rect, "mask rect")) {
+            self.inner.mask(mask, rect, mode);
+        }
+    }
+
+    fn begin_mask(&mut self) -> Result<(), Error> {
+        self.inner.begin_mask()
+    }
+
+    fn end_mask(&mut self, mode: MaskMode) -> Result<(), Error> {
+        self.inner.end_mask(mode)
+    }
+
+    fn text(&mut self) -> &mut Self::Text {
+        self.inner.text()
+    }
+
+    fn draw_text(
+        &mut self,
+        layout: &Self::TextLayout,
+        pos: impl RoundInto<Self::Point>,
+        brush: &Self::Brush,
+    ) {
+        let pos: Self::Point = pos.round_into();
+        let pos: Vec2 = pos.into();
+        if self.check("draw_text", check_vec(pos, "text position")) {
+            self.inner.draw_text(layout, pos, brush);
+        }
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        self.inner.save()
+    }
+
+    fn restore(&mut self) -> Result<(), Error> {
+        self.inner.restore()
+    }
+
+    fn push_layer(&mut self, opacity: f64, clip: Option<impl Shape>) -> Result<(), Error> {
+        let mut result = check_unit(opacity, "layer opacity");
+        if let Some(ref clip) = clip {
+            result = result.and_then(|()| check_shape(clip));
+        }
+        self.check_result("push_layer", result)?;
+        self.inner.push_layer(opacity, clip)
+    }
+
+    fn pop_layer(&mut self) -> Result<(), Error> {
+        self.inner.pop_layer()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let result = self.inner.finish();
+        self.take_violation()?;
+        result
+    }
+
+    fn transform(&mut self, transform: Affine) {
+        if self.check("transform", check_affine(transform, "transform")) {
+            self.inner.transform(transform);
+        }
+    }
+
+    fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.inner.set_blend_mode(mode);
+    }
+
+    fn make_image(
+        &mut self,
+        width: usize,
+        height: usize,
+        buf: &[u8],
+        format: ImageFormat,
+    ) -> Result<Self::Image, Error> {
+        let result = match format.buffer_len(width, height) {
+            None => Err(format!(
+                "{}x{} {:?} image is too large",
+                width, height, format
+            )),
+            Some(expected) if buf.len() != expected => Err(format!(
+                "image buffer is {} bytes, but {}x{} {:?} needs {}",
+                buf.len(),
+                width,
+                height,
+                format,
+                expected
+            )),
+            Some(_) => Ok(()),
+        };
+        self.check_result("make_image", result)?;
+        self.inner.make_image(width, height, buf, format)
+    }
+
+    fn draw_image(
+        &mut self,
+        image: &Self::Image,
+        rect: impl Into<Rect>,
+        interp: InterpolationMode,
+    ) {
+        let rect = rect.into();
+        if self.check("draw_image", check_rect(rect, "image rect")) {
+            self.inner.draw_image(image, rect, interp);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Check color parsing, equality and hashing.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use piet::{Color, ColorSpace};
+
+fn parse(s: &str) -> Color {
+    s.parse()
+        .unwrap_or_else(|_| panic!("failed to parse {:?}", s))
+}
+
+fn hash(color: &Color) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    color.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn hex() {
+    assert_eq!(parse("#f00"), Color::rgba32(0xff_00_00_ff));
+    assert_eq!(parse("#f008"), Color::rgba32(0xff_00_00_88));
+    assert_eq!(parse("#12abEF"), Color::rgba32(0x12_ab_ef_ff));
+    assert_eq!(parse("#12abef80"), Color::rgba32(0x12_ab_ef_80));
+}
+
+#[test]
+fn rgb_functions() {
+    assert_eq!(parse("rgb(255, 0, 0)"), Color::RED);
+    assert_eq!(parse("rgb(255 0 0)"), Color::RED);
+    assert_eq!(parse("rgb(100%, 0%, 0%)"), Color::RED);
+    assert_eq!(parse("rgba(255, 0, 0, 0.5)"), Color::rgba32(0xff_00_00_80));
+    assert_eq!(parse("rgb(255 0 0 / 50%)"), Color::rgba32(0xff_00_00_80));
+    assert_eq!(parse("RGBA(18, 171, 239, 1)"), Color::rgb24(0x12_ab_ef));
+}
+
+#[test]
+fn named_colors() {
+    assert_eq!(parse("red"), Color::RED);
+    assert_eq!(parse(" Red "), Color::RED);
+    assert_eq!(parse("rebeccapurple"), Color::rgb24(0x66_33_99));
+    assert_eq!(parse("transparent"), Color::TRANSPARENT);
+    assert_eq!(parse("red"), parse("rgb(255,0,0)"));
+}
+
+#[test]
+fn malformed() {
+    let inputs = [
+        "",
+        "#",
+        "#12",
+        "#12345",
+        "#ggg",
+        "#+ff",
+        "notacolor",
+        "rgb(1, 2)",
+        "rgb(1, 2, 3",
+        "rgb(a, b, c)",
+        "rgb(1, 2, 3, 4, 5)",
+        "foo(1, 2, 3)",
+    ];
+    for input in &inputs {
+        assert!(input.parse::<Color>().is_err(), "{:?} parsed", input);
+    }
+}
+
+#[test]
+fn equality_ignores_representation() {
+    let precise = Color::rgb_f32(1.0, 0.0, 0.0);
+    assert_eq!(precise, Color::RED);
+    assert_eq!(hash(&precise), hash(&Color::RED));
+    assert_eq!(Color::rgb(1.0, 0.0, 0.0), Color::RED);
+    assert_ne!(Color::rgb_f32(0.5, 0.0, 0.0), Color::rgb(0.5, 0.0, 0.0));
+    let negative_zero = Color::rgba_in(ColorSpace::Srgb, -0.0, 0.0, 0.0, 1.0);
+    assert_eq!(negative_zero, Color::BLACK);
+    assert_eq!(hash(&negative_zero), hash(&Color::BLACK));
+}
+
+#[test]
+fn nan_components() {
+    let nan = Color::rgba_in(ColorSpace::Srgb, std::f64::NAN, 0.0, 0.0, 1.0);
+    assert_eq!(nan, nan.clone());
+    assert_eq!(nan, Color::BLACK);
+}
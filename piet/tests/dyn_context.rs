@@ -0,0 +1,118 @@
+//! Check that drawing through a `DynRenderContext` reaches the wrapped context.
+
+use piet::kurbo::{Circle, Line, Rect, Vec2};
+use piet::{
+    Color, DynAdapter, DynRenderContext, Error, ErrorKind, FillRule, FontBuilder, ImageFormat,
+    InterpolationMode, RecordingContext, RenderContext, Text, TextLayoutBuilder,
+};
+
+fn draw_dyn(rc: &mut dyn DynRenderContext) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    let brush = rc.solid_brush(Color::rgb24(0x00_80_ff));
+    rc.stroke(&Line::new((0.0, 0.0), (20.0, 20.0)), &brush, 2.0, None);
+    rc.with_save(|rc| {
+        rc.clip(&Rect::new(0.0, 0.0, 10.0, 10.0), FillRule::NonZero);
+        rc.fill(&Circle::new((5.0, 5.0), 5.0), &brush, FillRule::EvenOdd);
+        Ok(())
+    })?;
+    let image = rc.make_image(1, 1, &[255, 0, 0], ImageFormat::Rgb)?;
+    rc.draw_image(
+        &image,
+        Rect::new(0.0, 0.0, 4.0, 4.0),
+        InterpolationMode::NearestNeighbor,
+    );
+    let font = rc.new_font("Sans", 12.0)?;
+    let layout = rc.new_text_layout(&font, "hello")?;
+    rc.draw_text(&layout, Vec2::new(10.0, 40.0), &brush);
+    rc.status()
+}
+
+fn draw_direct(rc: &mut RecordingContext) -> Result<(), Error> {
+    rc.clear(Color::WHITE);
+    let brush = rc.solid_brush(Color::rgb24(0x00_80_ff));
+    rc.stroke(Line::new((0.0, 0.0), (20.0, 20.0)), &brush, 2.0, None);
+    rc.with_save(|rc| {
+        rc.clip(Rect::new(0.0, 0.0, 10.0, 10.0), FillRule::NonZero);
+        rc.fill(Circle::new((5.0, 5.0), 5.0), &brush, FillRule::EvenOdd);
+        Ok(())
+    })?;
+    let image = rc.make_image(1, 1, &[255, 0, 0], ImageFormat::Rgb)?;
+    rc.draw_image(
+        &image,
+        Rect::new(0.0, 0.0, 4.0, 4.0),
+        InterpolationMode::NearestNeighbor,
+    );
+    let font = rc.text().new_font_by_name("Sans", 12.0)?.build()?;
+    let layout = rc.text().new_text_layout(&font, "hello")?.build()?;
+    rc.draw_text(&layout, Vec2::new(10.0, 40.0), &brush);
+    rc.status()
+}
+
+fn assert_invalid_input(result: Result<(), Error>) {
+    match result {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidInput => (),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(()) => panic!("invalid handle was accepted"),
+    }
+}
+
+#[test]
+fn draws_through_trait_object() {
+    let mut adapter = DynAdapter::new(RecordingContext::new());
+    draw_dyn(&mut adapter).unwrap();
+    adapter.finish().unwrap();
+    let list = adapter.into_inner().into_display_list();
+
+    let mut rc = RecordingContext::new();
+    draw_direct(&mut rc).unwrap();
+    rc.finish().unwrap();
+    assert_eq!(
+        format!("{:?}", list),
+        format!("{:?}", rc.into_display_list())
+    );
+}
+
+#[test]
+fn foreign_handle() {
+    let mut first = DynAdapter::new(RecordingContext::new());
+    let mut second = DynAdapter::new(RecordingContext::new());
+    let brush = first.solid_brush(Color::BLACK);
+    second.solid_brush(Color::WHITE);
+    second.fill(&Rect::new(0.0, 0.0, 1.0, 1.0), &brush, FillRule::NonZero);
+    assert_invalid_input(second.status());
+    // The error is only reported once.
+    second.status().unwrap();
+    first.status().unwrap();
+}
+
+#[test]
+fn out_of_range_handle() {
+    let mut first = DynAdapter::new(RecordingContext::new());
+    first.solid_brush(Color::BLACK);
+    let brush = first.solid_brush(Color::BLACK);
+    let mut second = DynAdapter::new(RecordingContext::new());
+    second.fill(&Rect::new(0.0, 0.0, 1.0, 1.0), &brush, FillRule::NonZero);
+    assert_invalid_input(second.status());
+    assert!(second.into_inner().display_list().commands.is_empty());
+}
+
+#[test]
+fn released_handle() {
+    let mut adapter = DynAdapter::new(RecordingContext::new());
+    let brush = adapter.solid_brush(Color::BLACK);
+    adapter.release_brush(&brush);
+    adapter.status().unwrap();
+
+    // The slot is reused, but the old handle doesn't reach the new brush.
+    let _reused = adapter.solid_brush(Color::WHITE);
+    adapter.fill(&Rect::new(0.0, 0.0, 1.0, 1.0), &brush, FillRule::NonZero);
+    assert_invalid_input(adapter.status());
+    adapter.release_brush(&brush);
+    assert_invalid_input(adapter.status());
+
+    let font = adapter.new_font("Sans", 12.0).unwrap();
+    adapter.clear_resources();
+    assert_invalid_input(adapter.new_text_layout(&font, "hello").map(|_| ()));
+}
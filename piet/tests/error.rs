@@ -0,0 +1,11 @@
+//! Check properties of the error type.
+
+use piet::Error;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn error_is_send_sync() {
+    // This fails to compile, rather than fails, if it's broken.
+    assert_send_sync::<Error>();
+}
@@ -0,0 +1,105 @@
+//! Check that display lists play back the calls they recorded.
+
+use piet::kurbo::{Affine, Line, Point, Rect, Vec2};
+use piet::{
+    replay, Color, Command, DisplayList, ErrorKind, FillRule, FontBuilder, ImageFormat,
+    InterpolationMode, RecordingContext, RenderContext, StrokeStyle, Text, TextLayoutBuilder,
+};
+
+fn record(f: impl FnOnce(&mut RecordingContext)) -> DisplayList {
+    let mut rc = RecordingContext::new();
+    f(&mut rc);
+    rc.finish().unwrap();
+    rc.into_display_list()
+}
+
+#[test]
+fn replay_matches_recording() {
+    let list = record(|rc| {
+        rc.clear(Color::WHITE);
+        let brush = rc.solid_brush(Color::rgb24(0x00_80_ff));
+        rc.fill(Rect::new(10.0, 10.0, 50.0, 30.0), &brush, FillRule::NonZero);
+        rc.save().unwrap();
+        rc.transform(Affine::translate(Vec2::new(5.0, 5.0)));
+        let mut style = StrokeStyle::new();
+        style.set_dash(vec![4.0, 2.0], 1.0);
+        rc.stroke(
+            Line::new(Point::new(0.0, 0.0), Point::new(20.0, 20.0)),
+            &brush,
+            2.0,
+            Some(&style),
+        );
+        rc.restore().unwrap();
+        let image = rc
+            .make_image(2, 1, &[255, 0, 0, 0, 0, 255], ImageFormat::Rgb)
+            .unwrap();
+        rc.draw_image(
+            &image,
+            Rect::new(0.0, 0.0, 4.0, 2.0),
+            InterpolationMode::Bilinear,
+        );
+        let font = rc
+            .text()
+            .new_font_by_name("Sans", 12.0)
+            .unwrap()
+            .build()
+            .unwrap();
+        let layout = rc
+            .text()
+            .new_text_layout(&font, "hello")
+            .unwrap()
+            .build()
+            .unwrap();
+        rc.draw_text(&layout, Vec2::new(10.0, 40.0), &brush);
+    });
+
+    let mut rc = RecordingContext::new();
+    replay(&list, &mut rc).unwrap();
+    rc.finish().unwrap();
+    let replayed = rc.into_display_list();
+    assert_eq!(format!("{:?}", replayed), format!("{:?}", list));
+}
+
+#[test]
+fn unbalanced_save_restore() {
+    let mut rc = RecordingContext::new();
+    match rc.restore() {
+        Err(e) => match e.kind() {
+            ErrorKind::StackUnbalance => (),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(()) => panic!("restore without save succeeded"),
+    }
+
+    let mut rc = RecordingContext::new();
+    rc.save().unwrap();
+    match rc.finish() {
+        Err(e) => match e.kind() {
+            ErrorKind::StackUnbalance => (),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(()) => panic!("finish with a save outstanding succeeded"),
+    }
+}
+
+#[test]
+fn invalid_index() {
+    let mut list = record(|rc| {
+        let brush = rc.solid_brush(Color::BLACK);
+        rc.fill(Rect::new(0.0, 0.0, 1.0, 1.0), &brush, FillRule::NonZero);
+    });
+    list.brushes.clear();
+    list.commands.push(Command::Clear(Color::WHITE));
+
+    // The bad fill is skipped, and the rest plays back.
+    let mut rc = RecordingContext::new();
+    match replay(&list, &mut rc) {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidInput => (),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(()) => panic!("replay with an invalid brush succeeded"),
+    }
+    let replayed = rc.into_display_list();
+    assert_eq!(replayed.commands.len(), 1);
+}
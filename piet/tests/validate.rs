@@ -0,0 +1,87 @@
+//! Check that a validating context reports invalid calls.
+
+use std::f64::NAN;
+
+use piet::kurbo::{Line, Point, Rect, Vec2};
+use piet::{
+    Color, Error, ErrorKind, FillRule, Gradient, GradientStop, ImageFormat, LinearGradient,
+    RecordingContext, RenderContext, ValidatingContext,
+};
+
+fn context() -> ValidatingContext<RecordingContext> {
+    ValidatingContext::new(RecordingContext::new())
+}
+
+/// Check that a result is an invalid call to the given method.
+fn assert_invalid_call<T>(result: Result<T, Error>, expected: &str) {
+    match result {
+        Err(e) => match e.kind() {
+            ErrorKind::InvalidCall { method, .. } => assert_eq!(*method, expected),
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(_) => panic!("invalid call to {} wasn't reported", expected),
+    }
+}
+
+#[test]
+fn nan_coordinate() {
+    let mut rc = context();
+    let brush = rc.solid_brush(Color::BLACK);
+    rc.fill(Rect::new(NAN, 0.0, 10.0, 10.0), &brush, FillRule::NonZero);
+    assert_invalid_call(rc.finish(), "fill");
+    // The call was skipped.
+    assert!(rc.into_inner().display_list().commands.is_empty());
+}
+
+#[test]
+fn negative_stroke_width() {
+    let mut rc = context();
+    let brush = rc.solid_brush(Color::BLACK);
+    let line = Line::new(Point::new(0.0, 0.0), Point::new(10.0, 10.0));
+    rc.stroke(line, &brush, -1.0, None);
+    assert_invalid_call(rc.status(), "stroke");
+    // The violation is only reported once.
+    assert!(rc.status().is_ok());
+}
+
+#[test]
+fn one_stop_gradient() {
+    let mut rc = context();
+    let stops = vec![GradientStop {
+        pos: 0.0,
+        color: Color::WHITE,
+    }];
+    let gradient = LinearGradient::new(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), stops);
+    assert_invalid_call(rc.gradient(Gradient::Linear(gradient)), "gradient");
+    assert_invalid_call(rc.status(), "gradient");
+}
+
+#[test]
+fn short_image_buffer() {
+    let mut rc = context();
+    let result = rc.make_image(2, 2, &[0; 11], ImageFormat::Rgb);
+    assert_invalid_call(result, "make_image");
+    assert_invalid_call(rc.finish(), "make_image");
+}
+
+#[test]
+fn huge_image() {
+    let mut rc = context();
+    let result = rc.make_image(std::usize::MAX, 2, &[], ImageFormat::RgbaPremul);
+    assert_invalid_call(result, "make_image");
+}
+
+#[test]
+fn layer_opacity_out_of_range() {
+    let mut rc = context();
+    assert_invalid_call(rc.push_layer(1.5, None::<Rect>), "push_layer");
+}
+
+#[test]
+fn first_violation_wins() {
+    let mut rc = context();
+    let brush = rc.solid_brush(Color::BLACK);
+    rc.fill(Rect::new(0.0, NAN, 10.0, 10.0), &brush, FillRule::NonZero);
+    rc.blurred_rect(Rect::new(0.0, 0.0, 10.0, 10.0), -1.0, &brush);
+    assert_invalid_call(rc.finish(), "fill");
+}